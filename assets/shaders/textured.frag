@@ -0,0 +1,14 @@
+#version 330 core
+
+in VS_OUTPUT {
+	vec2 UV;
+} IN;
+
+uniform sampler2D Texture;
+
+out vec4 Color;
+
+void main()
+{
+    Color = texture(Texture, IN.UV);
+}
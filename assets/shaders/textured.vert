@@ -0,0 +1,16 @@
+#version 330 core
+
+layout (location = 0) in vec3 Position;
+layout (location = 2) in vec2 UV;
+
+uniform mat4 ViewProjection;
+
+out VS_OUTPUT {
+	vec2 UV;
+} OUT;
+
+void main()
+{
+    gl_Position = ViewProjection * vec4(Position, 1.0);
+	OUT.UV = UV;
+}
@@ -5,6 +5,10 @@ extern crate syn;
 #[macro_use]
 extern crate quote;
 
+/// Implements `vertex_attrib_pointers` and `vertex_attribs`, and `VertexLayout` with them.
+///
+/// Names are resolved where the derive is used, so `VertexLayout` has to be in scope there,
+/// along with the field types.
 #[proc_macro_derive(VertexAttribPointers, attributes(location))]
 pub fn vertex_attrib_pointers_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = syn::parse_derive_input(&input.to_string()).unwrap();
@@ -14,12 +18,40 @@ pub fn vertex_attrib_pointers_derive(input: proc_macro::TokenStream) -> proc_mac
     gen.parse().unwrap()
 }
 
+/// Implements `Std140`, which has to be in scope where the derive is used.
+#[proc_macro_derive(Std140, attributes(padding))]
+pub fn std140_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
+
+    let gen = generate_block_layout_impl(&ast, "Std140", 16);
+
+    gen.parse().unwrap()
+}
+
+/// Implements `Std430`, which has to be in scope where the derive is used.
+#[proc_macro_derive(Std430, attributes(padding))]
+pub fn std430_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = syn::parse_derive_input(&input.to_string()).unwrap();
+
+    let gen = generate_block_layout_impl(&ast, "Std430", 1);
+
+    gen.parse().unwrap()
+}
+
 fn generate_impl(ast: &syn::DeriveInput) -> quote::Tokens {
     let ident = &ast.ident;
     let generics = &ast.generics;
     let where_clause = &ast.generics.where_clause;
 
-    let fields_vertex_attrib_pointer = generate_vertex_attrib_pointer_calls(&ast.body);
+    let fields = struct_fields(&ast.body);
+    let fields_vertex_attrib_pointer: Vec<_> = fields
+        .iter()
+        .map(|f| generate_struct_field_vertex_attrib_pointer_call(f))
+        .collect();
+    let fields_vertex_attrib: Vec<_> = fields
+        .iter()
+        .map(|f| generate_struct_field_vertex_attrib(f))
+        .collect();
 
     quote! {
         impl #ident #generics #where_clause {
@@ -30,27 +62,43 @@ fn generate_impl(ast: &syn::DeriveInput) -> quote::Tokens {
 
                 #(#fields_vertex_attrib_pointer)*
             }
+
+            /// `(location, component count, component type)` of every attribute.
+            pub fn vertex_attribs() -> Vec<(usize, i32, u32)> {
+                vec![#(#fields_vertex_attrib),*]
+            }
+        }
+
+        impl #generics VertexLayout for #ident #generics #where_clause {
+            fn vertex_attrib_pointers() {
+                Self::vertex_attrib_pointers()
+            }
+
+            fn vertex_attribs() -> Vec<(usize, i32, u32)> {
+                Self::vertex_attribs()
+            }
         }
     }
 }
 
-fn generate_vertex_attrib_pointer_calls(body: &syn::Body) -> Vec<quote::Tokens> {
+fn struct_fields(body: &syn::Body) -> &[syn::Field] {
+    struct_fields_for(body, "VertexAttribPointers")
+}
+
+fn struct_fields_for<'a>(body: &'a syn::Body, derive: &str) -> &'a [syn::Field] {
     match body {
-        &syn::Body::Enum(_) => panic!("VertexAttribPointers can not be implemented for enums"),
+        &syn::Body::Enum(_) => panic!("{} can not be implemented for enums", derive),
         &syn::Body::Struct(syn::VariantData::Unit) => {
-            panic!("VertexAttribPointers can not be implemented for unit structs")
+            panic!("{} can not be implemented for unit structs", derive)
         }
         &syn::Body::Struct(syn::VariantData::Tuple(_)) => {
-            panic!("VertexAttribPointers can not be implemented for tuple structs")
+            panic!("{} can not be implemented for tuple structs", derive)
         }
-        &syn::Body::Struct(syn::VariantData::Struct(ref s)) => s
-            .iter()
-            .map(generate_struct_field_vertex_attrib_pointer_call)
-            .collect(),
+        &syn::Body::Struct(syn::VariantData::Struct(ref s)) => s,
     }
 }
 
-fn generate_struct_field_vertex_attrib_pointer_call(f: &syn::Field) -> quote::Tokens {
+fn field_location(f: &syn::Field) -> &syn::Lit {
     let ident = match f.ident {
         Some(ref i) => format!("{}", i),
         None => String::from(""),
@@ -63,13 +111,17 @@ fn generate_struct_field_vertex_attrib_pointer_call(f: &syn::Field) -> quote::To
         .next()
         .unwrap_or_else(|| panic!("Field {:?} is missing #[location = ?] attribute", ident));
 
-    let loc = match loc_attr.value {
+    match loc_attr.value {
         syn::MetaItem::NameValue(_, ref literal @ syn::Lit::Int(_, _)) => literal,
         _ => panic!(
             "Field {} location attribute must be an integer literal",
             ident
         ),
-    };
+    }
+}
+
+fn generate_struct_field_vertex_attrib_pointer_call(f: &syn::Field) -> quote::Tokens {
+    let loc = field_location(f);
 
     let ty = &f.ty;
     quote! {
@@ -81,6 +133,91 @@ fn generate_struct_field_vertex_attrib_pointer_call(f: &syn::Field) -> quote::To
     }
 }
 
+fn generate_struct_field_vertex_attrib(f: &syn::Field) -> quote::Tokens {
+    let loc = field_location(f);
+
+    let ty = &f.ty;
+    quote! {
+        (#loc, #ty::COMPONENTS, #ty::COMPONENT_TYPE)
+    }
+}
+
+/// Implements `Std140` or `Std430` and checks at compile time that every field sits at the
+/// offset the layout rules give it.
+///
+/// Like the field types' `vertex_attrib_pointer`, the trait is named without a path, so it
+/// has to be in scope where the derive is used.
+///
+/// Fields marked `#[padding]` are skipped; they only exist to move the next field into place.
+fn generate_block_layout_impl(
+    ast: &syn::DeriveInput,
+    layout: &str,
+    min_align: usize,
+) -> quote::Tokens {
+    let ident = &ast.ident;
+    if !ast.generics.ty_params.is_empty() || !ast.generics.lifetimes.is_empty() {
+        panic!("{} can not be implemented for generic structs", layout);
+    }
+
+    let trait_ident = syn::Ident::new(layout);
+    let fields: Vec<&syn::Field> = struct_fields_for(&ast.body, layout)
+        .iter()
+        .filter(|f| !f.attrs.iter().any(|a| a.value.name() == "padding"))
+        .collect();
+
+    let field_aligns: Vec<_> = fields
+        .iter()
+        .map(|f| {
+            let ty = &f.ty;
+            quote! {
+                if <#ty as #trait_ident>::ALIGN > align {
+                    align = <#ty as #trait_ident>::ALIGN;
+                }
+            }
+        })
+        .collect();
+
+    let field_checks: Vec<_> = fields
+        .iter()
+        .map(|f| {
+            let ty = &f.ty;
+            let field = f.ident.as_ref().unwrap();
+            let message = format!(
+                "field `{}` of `{}` is not at its {} offset, add or remove #[padding] fields before it",
+                field, ident, layout
+            );
+            quote! {
+                let offset = end.div_ceil(<#ty as #trait_ident>::ALIGN)
+                    * <#ty as #trait_ident>::ALIGN;
+                assert!(std::mem::offset_of!(#ident, #field) == offset, #message);
+                let end = offset + std::mem::size_of::<#ty>();
+            }
+        })
+        .collect();
+
+    let size_message = format!(
+        "size of `{}` is not a multiple of its {} alignment, add #[padding] fields at the end",
+        ident, layout
+    );
+
+    quote! {
+        unsafe impl #trait_ident for #ident {
+            const ALIGN: usize = {
+                let mut align = #min_align;
+                #(#field_aligns)*
+                align
+            };
+        }
+
+        const _: () = {
+            let end: usize = 0;
+            #(#field_checks)*
+            let align = <#ident as #trait_ident>::ALIGN;
+            assert!(std::mem::size_of::<#ident>() == end.div_ceil(align) * align, #size_message);
+        };
+    }
+}
+
 //impl Vertex {
 //    pub fn vertex_attrib_pointers() {
 //        let stride = std::mem::size_of::<Self>();
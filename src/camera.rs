@@ -0,0 +1,212 @@
+use crate::utils::{Mat4, Quat, Vec2, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub zoom: f32,
+    aspect: f32,
+}
+
+impl Camera2D {
+    pub fn new(width: i32, height: i32) -> Camera2D {
+        Camera2D {
+            position: Vec2::zero(),
+            rotation: 0.0,
+            zoom: 1.0,
+            aspect: aspect_ratio(width, height),
+        }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.aspect = aspect_ratio(width, height);
+    }
+
+    pub fn handle_event(&mut self, event: &glfw::WindowEvent) {
+        if let glfw::WindowEvent::Size(w, h) = *event {
+            self.resize(w, h);
+        }
+    }
+
+    /// Shows the world from -1 to 1 vertically at zoom 1, widened to match the aspect ratio.
+    pub fn projection(&self) -> Mat4 {
+        let half_height = 1.0 / self.zoom;
+        let half_width = half_height * self.aspect;
+        Mat4::orthographic(
+            -half_width,
+            half_width,
+            -half_height,
+            half_height,
+            -1.0,
+            1.0,
+        )
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), -self.rotation).to_mat4()
+            * Mat4::from_translation(-self.position.extend(0.0))
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera3D {
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Projects with `Mat4::perspective_reverse_z`. Draws then need a `RenderState` with
+    /// `clip_depth: ClipDepth::ZeroToOne` and `depth: Some(DepthState::reverse_z())`, and the
+    /// depth buffer cleared to 0.
+    pub reverse_z: bool,
+    aspect: f32,
+}
+
+impl Camera3D {
+    pub fn new(width: i32, height: i32) -> Camera3D {
+        Camera3D {
+            position: Vec3::zero(),
+            orientation: Quat::identity(),
+            fov_y: std::f32::consts::FRAC_PI_3,
+            near: 0.1,
+            far: 100.0,
+            reverse_z: false,
+            aspect: aspect_ratio(width, height),
+        }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.aspect = aspect_ratio(width, height);
+    }
+
+    pub fn handle_event(&mut self, event: &glfw::WindowEvent) {
+        if let glfw::WindowEvent::Size(w, h) = *event {
+            self.resize(w, h);
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.orientation * Vec3::new(0.0, 0.0, -1.0)
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.orientation * Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.orientation * Vec3::new(0.0, 1.0, 0.0)
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        if self.reverse_z {
+            Mat4::perspective_reverse_z(self.fov_y, self.aspect, self.near, self.far)
+        } else {
+            Mat4::perspective(self.fov_y, self.aspect, self.near, self.far)
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        self.orientation.conjugate().to_mat4() * Mat4::from_translation(-self.position)
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+}
+
+fn aspect_ratio(width: i32, height: i32) -> f32 {
+    if height <= 0 {
+        1.0
+    } else {
+        width as f32 / height as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_vec3_approx_eq(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn camera2d_aspect() {
+        let mut camera = Camera2D::new(600, 600);
+        assert_vec3_approx_eq(
+            Vec3::new(0.5, 0.5, 0.0),
+            camera
+                .view_projection()
+                .transform_point(Vec3::new(0.5, 0.5, 0.0)),
+        );
+
+        camera.handle_event(&glfw::WindowEvent::Size(1200, 600));
+        assert_eq!(2.0, camera.aspect());
+        assert_vec3_approx_eq(
+            Vec3::new(0.25, 0.5, 0.0),
+            camera
+                .view_projection()
+                .transform_point(Vec3::new(0.5, 0.5, 0.0)),
+        );
+
+        camera.handle_event(&glfw::WindowEvent::Size(0, 0));
+        assert_eq!(1.0, camera.aspect());
+    }
+
+    #[test]
+    fn camera2d_view() {
+        let mut camera = Camera2D::new(600, 600);
+        camera.position = Vec2::new(1.0, 0.0);
+        camera.zoom = 2.0;
+
+        assert_vec3_approx_eq(
+            Vec3::zero(),
+            camera
+                .view_projection()
+                .transform_point(Vec3::new(1.0, 0.0, 0.0)),
+        );
+        assert_vec3_approx_eq(
+            Vec3::new(1.0, 0.0, 0.0),
+            camera
+                .view_projection()
+                .transform_point(Vec3::new(1.5, 0.0, 0.0)),
+        );
+    }
+
+    #[test]
+    fn camera3d_view() {
+        let mut camera = Camera3D::new(800, 600);
+        camera.position = Vec3::new(0.0, 0.0, 5.0);
+        camera.orientation =
+            Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), std::f32::consts::FRAC_PI_2);
+
+        assert_vec3_approx_eq(Vec3::new(-1.0, 0.0, 0.0), camera.forward());
+        assert_vec3_approx_eq(
+            Vec3::new(0.0, 0.0, -2.0),
+            camera.view().transform_point(Vec3::new(-2.0, 0.0, 5.0)),
+        );
+
+        let expected = Mat4::look_at(
+            camera.position,
+            camera.position + camera.forward(),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+        let v = Vec3::new(1.0, 2.0, 3.0);
+        assert_vec3_approx_eq(
+            expected.transform_point(v),
+            camera.view().transform_point(v),
+        );
+    }
+}
@@ -3,30 +3,79 @@ extern crate glfw;
 #[macro_use]
 extern crate render_derive;
 
+pub mod camera;
 pub mod input_handler;
 pub mod render;
 pub mod resources;
 pub mod utils;
 
+use camera::Camera2D;
 use input_handler::InputHandler;
-use render::Quad;
+use render::{BufferMask, ClearValues, Framebuffer, InternalFormat, Quad, TextureOptions};
 use resources::Resources;
-use std::path::Path;
+use std::env;
+use std::path::{Path, PathBuf};
+use utils::Vec4;
 
 fn main() {
     println!("Welcome to a rusty engine!");
 
-    let mut window = render::Window::new(600, 600);
-    window.set_clear_color(0.15, 0.0, 0.5, 1.0);
+    let args: Vec<String> = env::args().collect();
+    // `--screenshot out.png` renders a single frame without showing a window and saves it.
+    let screenshot = path_argument(&args, "--screenshot");
+    // `--assets dir` reads assets from `dir` rather than beside the executable, e.g. from the
+    // source tree so edits there are picked up by hot reloading.
+    let assets = path_argument(&args, "--assets");
 
-    let res = Resources::from_relative_exe_path(Path::new("assets")).unwrap();
-    let quad = Quad::new(&res).unwrap();
+    let mut window = match screenshot {
+        Some(_) => render::Window::hidden(600, 600).expect("Failed to create a GL context"),
+        None => render::Window::new(600, 600),
+    };
+    let clear = ClearValues {
+        color: Vec4::new(0.15, 0.0, 0.5, 1.0),
+        ..ClearValues::default()
+    };
+
+    let res = match assets {
+        Some(dir) => Resources::from_path(&dir),
+        None => Resources::from_relative_exe_path(Path::new("assets")).unwrap(),
+    };
+    let mut quad = Quad::new(&res).unwrap();
+    let mut camera = Camera2D::new(600, 600);
+
+    if let Some(path) = screenshot {
+        // A hidden window's back buffer is not reliably readable, so the frame is rendered
+        // into a framebuffer instead.
+        let options = TextureOptions {
+            internal_format: Some(InternalFormat::Rgba8),
+            ..TextureOptions::nearest()
+        };
+        let framebuffer = Framebuffer::with_color_texture(600, 600, &options, None).unwrap();
+        framebuffer.bind_with_viewport();
+        window.clear(BufferMask::COLOR, &clear);
+        quad.render(&camera.view_projection()).unwrap();
+        framebuffer.read_pixels(0).save_png(&path).unwrap();
+        println!("Saved {}", path.display());
+        return;
+    }
+
+    let mut watcher = res.watcher();
+    for source in quad.program().sources() {
+        watcher.watch(source);
+    }
 
     let mut input = InputHandler::new();
     while !window.should_close() {
-        window.clear();
+        if !watcher.poll().is_empty() {
+            match quad.reload(&res) {
+                Ok(()) => println!("Reloaded shaders"),
+                Err(e) => println!("Failed to reload shaders: {}", e),
+            }
+        }
+
+        window.clear(BufferMask::COLOR, &clear);
 
-        quad.render();
+        quad.render(&camera.view_projection()).unwrap();
 
         window.swap_buffers();
         input.clear();
@@ -34,9 +83,24 @@ fn main() {
             match event {
                 glfw::WindowEvent::Key(k, _, a, _) => input.update_keys(k, &a),
                 glfw::WindowEvent::MouseButton(m, a, _) => input.update_mouse_buttons(m, &a),
-                glfw::WindowEvent::Size(w, h) => unsafe { gl::Viewport(0, 0, w, h); },
+                glfw::WindowEvent::Size(w, h) => {
+                    camera.resize(w, h);
+                    unsafe {
+                        gl::Viewport(0, 0, w, h);
+                    }
+                }
                 _ => println!("{:?}", event),
             }
         }
     }
 }
+
+/// The path following `flag` on the command line, if the flag is there.
+fn path_argument(args: &[String], flag: &str) -> Option<PathBuf> {
+    args.iter().position(|arg| arg == flag).map(|i| {
+        PathBuf::from(
+            args.get(i + 1)
+                .unwrap_or_else(|| panic!("{} needs a path", flag)),
+        )
+    })
+}
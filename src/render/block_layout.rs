@@ -0,0 +1,129 @@
+use crate::utils::{Mat2, Mat4, Vec2, Vec3, Vec4};
+
+/// A type whose memory layout matches GLSL's std140 rules, for uniform blocks.
+///
+/// Derive it with `#[derive(Std140)]`, which checks every field offset at compile time.
+///
+/// # Safety
+///
+/// `ALIGN` must be the std140 base alignment of the type, and its fields must sit at their
+/// std140 offsets.
+pub unsafe trait Std140: Copy {
+    const ALIGN: usize;
+}
+
+/// A type whose memory layout matches GLSL's std430 rules, for shader storage blocks.
+///
+/// Derive it with `#[derive(Std430)]`, which checks every field offset at compile time.
+///
+/// # Safety
+///
+/// `ALIGN` must be the std430 base alignment of the type, and its fields must sit at their
+/// std430 offsets.
+pub unsafe trait Std430: Copy {
+    const ALIGN: usize;
+}
+
+macro_rules! impl_block_layout {
+    ($($ty:ty => $std140:expr, $std430:expr;)+) => {
+        $(
+            unsafe impl Std140 for $ty {
+                const ALIGN: usize = $std140;
+            }
+
+            unsafe impl Std430 for $ty {
+                const ALIGN: usize = $std430;
+            }
+        )+
+    };
+}
+
+impl_block_layout! {
+    f32 => 4, 4;
+    i32 => 4, 4;
+    u32 => 4, 4;
+    Vec2 => 8, 8;
+    Vec3 => 16, 16;
+    Vec4 => 16, 16;
+    Mat4 => 16, 16;
+}
+
+// std140 pads every matrix column to a vec4, which `Mat2` does not have.
+unsafe impl Std430 for Mat2 {
+    const ALIGN: usize = 8;
+}
+
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = {
+        let align = T::ALIGN.div_ceil(16) * 16;
+        assert!(
+            std::mem::size_of::<T>().is_multiple_of(align),
+            "std140 array elements must be padded to a multiple of 16 bytes"
+        );
+        align
+    };
+}
+
+unsafe impl<T: Std430, const N: usize> Std430 for [T; N] {
+    const ALIGN: usize = {
+        assert!(
+            std::mem::size_of::<T>().is_multiple_of(T::ALIGN),
+            "std430 array elements must be padded to a multiple of their alignment"
+        );
+        T::ALIGN
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Std140, Std430)]
+    #[repr(C)]
+    struct Light {
+        position: Vec3,
+        intensity: f32,
+        color: Vec3,
+        #[padding]
+        _pad: f32,
+    }
+
+    #[derive(Clone, Copy, Std140)]
+    #[repr(C)]
+    struct Scene {
+        view_projection: Mat4,
+        time: f32,
+        #[padding]
+        _pad: [f32; 3],
+        lights: [Light; 4],
+        light_count: u32,
+        #[padding]
+        _pad_end: [u32; 3],
+    }
+
+    #[derive(Clone, Copy, Std430)]
+    #[repr(C)]
+    struct Particle {
+        position: Vec2,
+        velocity: Vec2,
+        age: f32,
+        #[padding]
+        _pad: f32,
+    }
+
+    #[test]
+    fn derived_alignments() {
+        assert_eq!(16, <Light as Std140>::ALIGN);
+        assert_eq!(16, <Light as Std430>::ALIGN);
+        assert_eq!(16, <Scene as Std140>::ALIGN);
+        assert_eq!(8, <Particle as Std430>::ALIGN);
+        assert_eq!(8, <[Particle; 16] as Std430>::ALIGN);
+    }
+
+    #[test]
+    fn array_alignment_rounds_up_to_vec4_in_std140() {
+        assert_eq!(16, <[Vec4; 2] as Std140>::ALIGN);
+        assert_eq!(8, <[Vec2; 2] as Std430>::ALIGN);
+        assert_eq!(4, <[f32; 2] as Std430>::ALIGN);
+    }
+}
@@ -1,8 +1,22 @@
 use gl::types::*;
+use std::cell::Cell;
+use std::marker::PhantomData;
 
 pub type ArrayBuffer = Buffer<BufferTypeArray>;
 pub type ElementArrayBuffer = Buffer<BufferTypeElementArray>;
 
+pub type UniformBuffer = Buffer<BufferTypeUniform>;
+pub type ShaderStorageBuffer = Buffer<BufferTypeShaderStorage>;
+pub type AtomicCounterBuffer = Buffer<BufferTypeAtomicCounter>;
+pub type TransformFeedbackBuffer = Buffer<BufferTypeTransformFeedback>;
+pub type DrawIndirectBuffer = Buffer<BufferTypeDrawIndirect>;
+pub type DispatchIndirectBuffer = Buffer<BufferTypeDispatchIndirect>;
+pub type CopyReadBuffer = Buffer<BufferTypeCopyRead>;
+pub type CopyWriteBuffer = Buffer<BufferTypeCopyWrite>;
+pub type PixelPackBuffer = Buffer<BufferTypePixelPack>;
+pub type PixelUnpackBuffer = Buffer<BufferTypePixelUnpack>;
+pub type TextureBuffer = Buffer<BufferTypeTexture>;
+
 pub type VertexBufferObject = ArrayBuffer;
 pub type IndexBufferObject = ElementArrayBuffer;
 
@@ -10,14 +24,51 @@ pub trait BufferType {
     const BUFFER_TYPE: GLuint;
 }
 
+/// An integer type that can be used for indices in an `ElementArrayBuffer`.
+pub trait ElementIndex: Copy {
+    const INDEX_TYPE: GLenum;
+}
+
+impl ElementIndex for u8 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_BYTE;
+}
+
+impl ElementIndex for u16 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_SHORT;
+}
+
+impl ElementIndex for u32 {
+    const INDEX_TYPE: GLenum = gl::UNSIGNED_INT;
+}
+
+/// Buffer targets with numbered binding points that shaders refer to by `binding`.
+pub trait IndexedBufferType: BufferType {}
+
 pub struct Buffer<B>
 where
     B: BufferType,
 {
     buffer_obj: GLuint,
+    capacity: Cell<usize>,
+    usage: Cell<GLenum>,
+    index_type: Cell<GLenum>,
+    index_count: Cell<usize>,
     _buffer_marker: ::std::marker::PhantomData<B>,
 }
 
+/// A range of a buffer mapped for writing, unmapped again when dropped.
+///
+/// The mapped memory may be uninitialized, so it can only be written, never read.
+pub struct MappedRange<'a, B, T>
+where
+    B: BufferType,
+{
+    ptr: *mut T,
+    len: usize,
+    buffer_obj: GLuint,
+    _buffer: PhantomData<&'a Buffer<B>>,
+}
+
 pub struct VertexArray {
     vao: GLuint,
 }
@@ -32,6 +83,65 @@ impl BufferType for BufferTypeElementArray {
     const BUFFER_TYPE: GLuint = gl::ELEMENT_ARRAY_BUFFER;
 }
 
+pub struct BufferTypeUniform;
+impl BufferType for BufferTypeUniform {
+    const BUFFER_TYPE: GLuint = gl::UNIFORM_BUFFER;
+}
+impl IndexedBufferType for BufferTypeUniform {}
+
+pub struct BufferTypeShaderStorage;
+impl BufferType for BufferTypeShaderStorage {
+    const BUFFER_TYPE: GLuint = gl::SHADER_STORAGE_BUFFER;
+}
+impl IndexedBufferType for BufferTypeShaderStorage {}
+
+pub struct BufferTypeAtomicCounter;
+impl BufferType for BufferTypeAtomicCounter {
+    const BUFFER_TYPE: GLuint = gl::ATOMIC_COUNTER_BUFFER;
+}
+impl IndexedBufferType for BufferTypeAtomicCounter {}
+
+pub struct BufferTypeTransformFeedback;
+impl BufferType for BufferTypeTransformFeedback {
+    const BUFFER_TYPE: GLuint = gl::TRANSFORM_FEEDBACK_BUFFER;
+}
+impl IndexedBufferType for BufferTypeTransformFeedback {}
+
+pub struct BufferTypeDrawIndirect;
+impl BufferType for BufferTypeDrawIndirect {
+    const BUFFER_TYPE: GLuint = gl::DRAW_INDIRECT_BUFFER;
+}
+
+pub struct BufferTypeDispatchIndirect;
+impl BufferType for BufferTypeDispatchIndirect {
+    const BUFFER_TYPE: GLuint = gl::DISPATCH_INDIRECT_BUFFER;
+}
+
+pub struct BufferTypeCopyRead;
+impl BufferType for BufferTypeCopyRead {
+    const BUFFER_TYPE: GLuint = gl::COPY_READ_BUFFER;
+}
+
+pub struct BufferTypeCopyWrite;
+impl BufferType for BufferTypeCopyWrite {
+    const BUFFER_TYPE: GLuint = gl::COPY_WRITE_BUFFER;
+}
+
+pub struct BufferTypePixelPack;
+impl BufferType for BufferTypePixelPack {
+    const BUFFER_TYPE: GLuint = gl::PIXEL_PACK_BUFFER;
+}
+
+pub struct BufferTypePixelUnpack;
+impl BufferType for BufferTypePixelUnpack {
+    const BUFFER_TYPE: GLuint = gl::PIXEL_UNPACK_BUFFER;
+}
+
+pub struct BufferTypeTexture;
+impl BufferType for BufferTypeTexture {
+    const BUFFER_TYPE: GLuint = gl::TEXTURE_BUFFER;
+}
+
 impl<B> Buffer<B>
 where
     B: BufferType,
@@ -44,21 +154,180 @@ where
 
         Buffer {
             buffer_obj,
+            capacity: Cell::new(0),
+            usage: Cell::new(gl::STATIC_DRAW),
+            index_type: Cell::new(gl::UNSIGNED_INT),
+            index_count: Cell::new(0),
             _buffer_marker: ::std::marker::PhantomData,
         }
     }
 
     pub fn buffer_static_data<T>(&self, data: &[T]) {
+        self.buffer_data(data, gl::STATIC_DRAW);
+    }
+
+    /// Uploads data that will be rewritten now and then, e.g. with `sub_data`.
+    pub fn buffer_dynamic_data<T>(&self, data: &[T]) {
+        self.buffer_data(data, gl::DYNAMIC_DRAW);
+    }
+
+    /// Uploads data that will be rewritten about once per draw.
+    pub fn buffer_stream_data<T>(&self, data: &[T]) {
+        self.buffer_data(data, gl::STREAM_DRAW);
+    }
+
+    fn buffer_data<T>(&self, data: &[T], usage: GLenum) {
+        unsafe {
+            gl::BufferData(
+                B::BUFFER_TYPE,
+                ::std::mem::size_of_val(data) as GLsizeiptr,
+                data.as_ptr() as *const GLvoid,
+                usage,
+            );
+        }
+        self.capacity.set(::std::mem::size_of_val(data));
+        self.usage.set(usage);
+    }
+
+    /// Allocates `size` bytes of uninitialized storage.
+    pub fn allocate(&self, size: usize, usage: GLenum) {
         unsafe {
             gl::BufferData(
                 B::BUFFER_TYPE,
-                (data.len() * ::std::mem::size_of::<T>()) as GLsizeiptr,
+                size as GLsizeiptr,
+                ::std::ptr::null(),
+                usage,
+            );
+        }
+        self.capacity.set(size);
+        self.usage.set(usage);
+    }
+
+    /// Allocates `size` bytes of immutable storage with `glBufferStorage`.
+    ///
+    /// The storage can not be reallocated afterwards, so `orphan` and `update_data` must not
+    /// be used on the buffer.
+    pub fn buffer_storage(&self, size: usize, flags: GLbitfield) {
+        unsafe {
+            gl::BufferStorage(
+                B::BUFFER_TYPE,
+                size as GLsizeiptr,
+                ::std::ptr::null(),
+                flags,
+            );
+        }
+        self.capacity.set(size);
+    }
+
+    /// Size of the buffer's storage in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity.get()
+    }
+
+    pub fn usage(&self) -> GLenum {
+        self.usage.get()
+    }
+
+    /// Overwrites part of the buffer, starting `offset` bytes in.
+    ///
+    /// Panics if the data does not fit in the current storage.
+    pub fn sub_data<T>(&self, offset: usize, data: &[T]) {
+        let size = ::std::mem::size_of_val(data);
+        self.check_range(offset, size);
+
+        unsafe {
+            gl::BufferSubData(
+                B::BUFFER_TYPE,
+                offset as GLintptr,
+                size as GLsizeiptr,
                 data.as_ptr() as *const GLvoid,
-                gl::STATIC_DRAW,
             );
         }
     }
 
+    /// Replaces the storage with a fresh allocation of the same size and usage, so that
+    /// writing to it does not wait for draws still reading the old contents.
+    pub fn orphan(&self) {
+        self.allocate(self.capacity(), self.usage());
+    }
+
+    /// Replaces the whole contents with `data`.
+    ///
+    /// The storage is only reallocated when `data` does not fit, and then grows by doubling,
+    /// so a buffer refilled every frame settles on the size of the largest upload. Data that
+    /// fits is written through a mapping that invalidates the old contents, so the write does
+    /// not wait for draws still reading them.
+    pub fn update_data<T: Copy>(&self, data: &[T]) {
+        let size = ::std::mem::size_of_val(data);
+        if size > self.capacity() {
+            self.allocate(grow_capacity(self.capacity(), size), self.usage());
+            self.sub_data(0, data);
+            return;
+        }
+        if data.is_empty() {
+            return;
+        }
+
+        match self.map_range(0, data.len(), gl::MAP_INVALIDATE_BUFFER_BIT) {
+            Some(mut range) => range.write_slice(0, data),
+            None => self.sub_data(0, data),
+        }
+    }
+
+    /// Maps `len` elements starting `offset` bytes in for writing.
+    ///
+    /// `access` is added to `GL_MAP_WRITE_BIT`, e.g. `gl::MAP_INVALIDATE_RANGE_BIT`. Returns
+    /// `None` if the driver refuses the mapping. Panics if the range is out of bounds or
+    /// `offset` is not aligned for `T`.
+    pub fn map_range<T: Copy>(
+        &self,
+        offset: usize,
+        len: usize,
+        access: GLbitfield,
+    ) -> Option<MappedRange<'_, B, T>> {
+        let size = len
+            .checked_mul(::std::mem::size_of::<T>())
+            .unwrap_or_else(|| panic!("mapping {} elements overflows", len));
+        self.check_range(offset, size);
+        assert!(
+            offset.is_multiple_of(::std::mem::align_of::<T>()),
+            "offset {} is not aligned for the mapped type",
+            offset
+        );
+
+        let ptr = unsafe {
+            gl::MapBufferRange(
+                B::BUFFER_TYPE,
+                offset as GLintptr,
+                size as GLsizeiptr,
+                gl::MAP_WRITE_BIT | access,
+            )
+        };
+
+        if ptr.is_null() {
+            return None;
+        }
+
+        Some(MappedRange {
+            ptr: ptr as *mut T,
+            len,
+            buffer_obj: self.buffer_obj,
+            _buffer: PhantomData,
+        })
+    }
+
+    fn check_range(&self, offset: usize, size: usize) {
+        assert!(
+            offset
+                .checked_add(size)
+                .is_some_and(|end| end <= self.capacity()),
+            "range of {} bytes at offset {} is outside the buffer's {} bytes",
+            size,
+            offset,
+            self.capacity()
+        );
+    }
+
     pub fn bind(&self) {
         unsafe {
             gl::BindBuffer(B::BUFFER_TYPE, self.buffer_obj);
@@ -72,17 +341,257 @@ where
     }
 }
 
+impl Buffer<BufferTypeElementArray> {
+    pub fn buffer_static_indices<I: ElementIndex>(&self, indices: &[I]) {
+        self.buffer_indices(indices, gl::STATIC_DRAW);
+    }
+
+    pub fn buffer_dynamic_indices<I: ElementIndex>(&self, indices: &[I]) {
+        self.buffer_indices(indices, gl::DYNAMIC_DRAW);
+    }
+
+    pub fn buffer_stream_indices<I: ElementIndex>(&self, indices: &[I]) {
+        self.buffer_indices(indices, gl::STREAM_DRAW);
+    }
+
+    fn buffer_indices<I: ElementIndex>(&self, indices: &[I], usage: GLenum) {
+        self.buffer_data(indices, usage);
+        self.index_type.set(I::INDEX_TYPE);
+        self.index_count.set(indices.len());
+    }
+
+    /// Replaces the indices like `update_data`, keeping the index count and type in step.
+    pub fn update_indices<I: ElementIndex>(&self, indices: &[I]) {
+        self.update_data(indices);
+        self.index_type.set(I::INDEX_TYPE);
+        self.index_count.set(indices.len());
+    }
+
+    /// Overwrites indices starting at index `first` like `sub_data`, extending the index
+    /// count if they go past it.
+    ///
+    /// Panics if `I` is not the buffer's index type while it holds indices.
+    pub fn sub_indices<I: ElementIndex>(&self, first: usize, indices: &[I]) {
+        let count = written_index_count(
+            self.index_type(),
+            self.index_count(),
+            I::INDEX_TYPE,
+            first,
+            indices.len(),
+        );
+        let offset = first
+            .checked_mul(::std::mem::size_of::<I>())
+            .unwrap_or_else(|| panic!("index {} is outside the buffer", first));
+        self.sub_data(offset, indices);
+        self.index_type.set(I::INDEX_TYPE);
+        self.index_count.set(count);
+    }
+
+    /// `GL_UNSIGNED_BYTE`, `GL_UNSIGNED_SHORT` or `GL_UNSIGNED_INT`, from the last
+    /// `*_indices` call.
+    pub fn index_type(&self) -> GLenum {
+        self.index_type.get()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count.get()
+    }
+
+    /// Draws every index in the buffer. The buffer must be bound, usually through a VAO.
+    pub fn draw_elements(&self, mode: GLenum) {
+        self.draw_elements_range(mode, 0, self.index_count());
+    }
+
+    /// Draws `count` indices starting at index `first`.
+    ///
+    /// Only indices written through the `*_indices` methods are counted; indices written
+    /// otherwise, e.g. through an `IndexRingBuffer`, are drawn with `draw_elements_at`.
+    pub fn draw_elements_range(&self, mode: GLenum, first: usize, count: usize) {
+        assert!(
+            first + count <= self.index_count(),
+            "indices {}..{} are outside the buffer's {} indices",
+            first,
+            first + count,
+            self.index_count()
+        );
+
+        unsafe {
+            gl::DrawElements(
+                mode,
+                count as GLsizei,
+                self.index_type(),
+                (first * index_size(self.index_type())) as *const GLvoid,
+            );
+        }
+    }
+
+    /// Draws `count` indices of `index_type` starting `byte_offset` bytes in, without checking
+    /// them against the buffer's index count.
+    ///
+    /// Panics if `byte_offset` is not aligned for `index_type`.
+    pub fn draw_elements_at(
+        &self,
+        mode: GLenum,
+        byte_offset: usize,
+        count: usize,
+        index_type: GLenum,
+    ) {
+        assert!(
+            byte_offset.is_multiple_of(index_size(index_type)),
+            "offset {} is not aligned for the index type",
+            byte_offset
+        );
+
+        unsafe {
+            gl::DrawElements(
+                mode,
+                count as GLsizei,
+                index_type,
+                byte_offset as *const GLvoid,
+            );
+        }
+    }
+}
+
+/// Index count after writing `len` indices of `new_type` at index `first` into a buffer
+/// holding `index_count` indices of `index_type`.
+fn written_index_count(
+    index_type: GLenum,
+    index_count: usize,
+    new_type: GLenum,
+    first: usize,
+    len: usize,
+) -> usize {
+    assert!(
+        index_count == 0 || index_type == new_type,
+        "writing {}-byte indices into a buffer of {}-byte indices",
+        index_size(new_type),
+        index_size(index_type)
+    );
+    index_count.max(first + len)
+}
+
+fn index_size(index_type: GLenum) -> usize {
+    match index_type {
+        gl::UNSIGNED_BYTE => 1,
+        gl::UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+impl<B> Buffer<B>
+where
+    B: IndexedBufferType,
+{
+    /// Binds the whole buffer to binding point `index`.
+    pub fn bind_base(&self, index: u32) {
+        unsafe {
+            gl::BindBufferBase(B::BUFFER_TYPE, index, self.buffer_obj);
+        }
+    }
+
+    /// Binds `size` bytes starting `offset` bytes in to binding point `index`.
+    ///
+    /// For uniform buffers `offset` must be a multiple of `GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT`.
+    pub fn bind_range(&self, index: u32, offset: usize, size: usize) {
+        unsafe {
+            gl::BindBufferRange(
+                B::BUFFER_TYPE,
+                index,
+                self.buffer_obj,
+                offset as GLintptr,
+                size as GLsizeiptr,
+            );
+        }
+    }
+}
+
 impl<B> Drop for Buffer<B>
 where
     B: BufferType,
 {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteBuffers(1, &mut self.buffer_obj);
+            gl::DeleteBuffers(1, &self.buffer_obj);
+        }
+    }
+}
+
+impl<B> Default for Buffer<B>
+where
+    B: BufferType,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, B, T> MappedRange<'a, B, T>
+where
+    B: BufferType,
+    T: Copy,
+{
+    /// Number of elements mapped.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Writes `value` at element `index`. Panics if `index` is out of range.
+    pub fn write(&mut self, index: usize, value: T) {
+        assert!(
+            index < self.len,
+            "index {} is outside the {} mapped elements",
+            index,
+            self.len
+        );
+        unsafe {
+            self.ptr.add(index).write(value);
+        }
+    }
+
+    /// Writes `values` starting at element `start`. Panics if they do not fit.
+    pub fn write_slice(&mut self, start: usize, values: &[T]) {
+        assert!(
+            start
+                .checked_add(values.len())
+                .is_some_and(|end| end <= self.len),
+            "{} elements at {} do not fit in the {} mapped elements",
+            values.len(),
+            start,
+            self.len
+        );
+        unsafe {
+            ::std::ptr::copy_nonoverlapping(values.as_ptr(), self.ptr.add(start), values.len());
+        }
+    }
+}
+
+impl<'a, B, T> Drop for MappedRange<'a, B, T>
+where
+    B: BufferType,
+{
+    fn drop(&mut self) {
+        // Another buffer may have been bound to the target since the range was mapped.
+        unsafe {
+            gl::BindBuffer(B::BUFFER_TYPE, self.buffer_obj);
+            gl::UnmapBuffer(B::BUFFER_TYPE);
         }
     }
 }
 
+/// Storage size for `required` bytes, doubling `current` until it fits.
+fn grow_capacity(current: usize, required: usize) -> usize {
+    let mut capacity = current.max(1);
+    while capacity < required {
+        capacity *= 2;
+    }
+    capacity
+}
+
 impl VertexArray {
     pub fn new() -> VertexArray {
         let mut vao: GLuint = 0;
@@ -106,10 +615,60 @@ impl VertexArray {
     }
 }
 
+impl Default for VertexArray {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Drop for VertexArray {
     fn drop(&mut self) {
         unsafe {
-            gl::DeleteVertexArrays(1, &mut self.vao);
+            gl::DeleteVertexArrays(1, &self.vao);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_sizes_match_types() {
+        assert_eq!(1, index_size(u8::INDEX_TYPE));
+        assert_eq!(2, index_size(u16::INDEX_TYPE));
+        assert_eq!(4, index_size(u32::INDEX_TYPE));
+    }
+
+    #[test]
+    fn writing_indices_extends_the_count() {
+        assert_eq!(
+            6,
+            written_index_count(gl::UNSIGNED_SHORT, 6, gl::UNSIGNED_SHORT, 2, 3)
+        );
+        assert_eq!(
+            8,
+            written_index_count(gl::UNSIGNED_SHORT, 6, gl::UNSIGNED_SHORT, 5, 3)
+        );
+        assert_eq!(
+            4,
+            written_index_count(gl::UNSIGNED_INT, 0, gl::UNSIGNED_BYTE, 1, 3)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn writing_indices_of_another_type_panics() {
+        written_index_count(gl::UNSIGNED_SHORT, 6, gl::UNSIGNED_INT, 0, 3);
+    }
+
+    #[test]
+    fn capacity_grows_by_doubling() {
+        assert_eq!(64, grow_capacity(64, 10));
+        assert_eq!(64, grow_capacity(64, 64));
+        assert_eq!(128, grow_capacity(64, 65));
+        assert_eq!(512, grow_capacity(64, 300));
+        assert_eq!(16, grow_capacity(0, 16));
+        assert_eq!(1, grow_capacity(0, 0));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// One located message from a shader compiler log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub text: String,
+    pub source_line: Option<String>,
+}
+
+/// Parses Mesa, NVIDIA and AMD style compiler logs.
+///
+/// Source string numbers are resolved through `files`, and `contents[n]` is the text of
+/// `files[n]`, used to fill in `source_line`. Lines in none of the known formats are skipped.
+pub fn parse(log: &str, files: &[String], contents: &[String]) -> Vec<Diagnostic> {
+    log.lines()
+        .filter_map(parse_line)
+        .map(|(index, line, column, severity, text)| Diagnostic {
+            file: files
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| index.to_string()),
+            line,
+            column,
+            severity,
+            text,
+            source_line: contents
+                .get(index)
+                .and_then(|c| c.lines().nth((line as usize).checked_sub(1)?))
+                .map(String::from),
+        })
+        .collect()
+}
+
+type ParsedLine = (usize, u32, Option<u32>, Severity, String);
+
+fn parse_line(line: &str) -> Option<ParsedLine> {
+    let (prefix_severity, rest) = if let Some(rest) = line.strip_prefix("ERROR: ") {
+        (Some(Severity::Error), rest)
+    } else if let Some(rest) = line.strip_prefix("WARNING: ") {
+        (Some(Severity::Warning), rest)
+    } else {
+        (None, line)
+    };
+
+    let (index, rest) = split_number(rest)?;
+
+    if let Some(rest) = rest.strip_prefix(':') {
+        let (line, rest) = split_number(rest)?;
+
+        if let Some(rest) = rest.strip_prefix('(') {
+            // Mesa: `0:12(5): error: message`
+            let (column, rest) = split_number(rest)?;
+            let rest = rest.strip_prefix("):")?.trim_start();
+            let (severity, text) = rest.split_once(':')?;
+            return Some((
+                index as usize,
+                line,
+                Some(column),
+                parse_severity(severity)?,
+                String::from(text.trim()),
+            ));
+        }
+
+        // AMD: `ERROR: 0:12: message`
+        let text = rest.strip_prefix(':')?;
+        return Some((
+            index as usize,
+            line,
+            None,
+            prefix_severity?,
+            String::from(text.trim()),
+        ));
+    }
+
+    // NVIDIA: `0(12) : error C0000: message`
+    let rest = rest.strip_prefix('(')?;
+    let (line, rest) = split_number(rest)?;
+    let rest = rest
+        .strip_prefix(')')?
+        .trim_start()
+        .strip_prefix(':')?
+        .trim_start();
+    let (severity, text) = rest.split_once(' ').unwrap_or((rest, ""));
+    Some((
+        index as usize,
+        line,
+        None,
+        parse_severity(severity)?,
+        String::from(text.trim()),
+    ))
+}
+
+fn split_number(s: &str) -> Option<(u32, &str)> {
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    Some((s[..digits].parse().ok()?, &s[digits..]))
+}
+
+fn parse_severity(word: &str) -> Option<Severity> {
+    match word.trim().to_lowercase().as_str() {
+        "error" | "fatal error" => Some(Severity::Error),
+        "warning" => Some(Severity::Warning),
+        "note" | "info" => Some(Severity::Note),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.text)?;
+
+        if let Some(ref source_line) = self.source_line {
+            write!(f, "\n    {}", source_line)?;
+            if let Some(column) = self.column.filter(|&c| c > 0) {
+                write!(f, "\n    {}^", " ".repeat(column as usize - 1))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> (Vec<String>, Vec<String>) {
+        (
+            vec![String::from("main.frag"), String::from("lib.glsl")],
+            vec![
+                String::from("#version 330 core\nvoid main() {\n    Color = x;\n}\n"),
+                String::from("float sq(float v) {\n    return v * w;\n}\n"),
+            ],
+        )
+    }
+
+    #[test]
+    fn parses_mesa_logs() {
+        let (files, contents) = files();
+        let log = "0:3(13): error: `x' undeclared\n1:2(16): warning: `w' used uninitialized\n";
+
+        let diagnostics = parse(log, &files, &contents);
+
+        assert_eq!(2, diagnostics.len());
+        assert_eq!(
+            Diagnostic {
+                file: String::from("main.frag"),
+                line: 3,
+                column: Some(13),
+                severity: Severity::Error,
+                text: String::from("`x' undeclared"),
+                source_line: Some(String::from("    Color = x;")),
+            },
+            diagnostics[0]
+        );
+        assert_eq!("lib.glsl", diagnostics[1].file);
+        assert_eq!(Severity::Warning, diagnostics[1].severity);
+    }
+
+    #[test]
+    fn parses_nvidia_logs() {
+        let (files, contents) = files();
+        let log = "1(2) : error C1008: undefined variable \"w\"";
+
+        let diagnostics = parse(log, &files, &contents);
+
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("lib.glsl", diagnostics[0].file);
+        assert_eq!(2, diagnostics[0].line);
+        assert_eq!(None, diagnostics[0].column);
+        assert_eq!(Severity::Error, diagnostics[0].severity);
+        assert_eq!("C1008: undefined variable \"w\"", diagnostics[0].text);
+    }
+
+    #[test]
+    fn parses_amd_logs() {
+        let (files, contents) = files();
+        let log = "ERROR: 0:3: 'x' : undeclared identifier\nERROR: 1 compilation errors.  No code generated.\n";
+
+        let diagnostics = parse(log, &files, &contents);
+
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("main.frag", diagnostics[0].file);
+        assert_eq!(3, diagnostics[0].line);
+        assert_eq!("'x' : undeclared identifier", diagnostics[0].text);
+    }
+
+    #[test]
+    fn unknown_source_numbers_keep_the_number() {
+        let diagnostics = parse("7:1(1): error: oops", &[], &[]);
+
+        assert_eq!("7", diagnostics[0].file);
+        assert_eq!(None, diagnostics[0].source_line);
+    }
+
+    #[test]
+    fn display_points_at_the_column() {
+        let (files, contents) = files();
+        let diagnostics = parse("0:3(13): error: `x' undeclared", &files, &contents);
+
+        assert_eq!(
+            "main.frag:3:13: error: `x' undeclared\n        Color = x;\n                ^",
+            diagnostics[0].to_string()
+        );
+    }
+}
@@ -0,0 +1,364 @@
+use super::image::{Image, PixelFormat};
+use super::texture::{mip_levels, unpack_tightly, TextureObject};
+use super::{Error, Filter, InternalFormat, TextureOptions, Wrap};
+use crate::resources::Resources;
+use crate::utils::{Vec3, Vec4};
+
+use gl::types::*;
+use std::convert::TryInto;
+use std::f32::consts::PI;
+
+/// The faces of a cube map, in the order GL numbers them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl CubeFace {
+    pub const ALL: [CubeFace; 6] = [
+        CubeFace::PositiveX,
+        CubeFace::NegativeX,
+        CubeFace::PositiveY,
+        CubeFace::NegativeY,
+        CubeFace::PositiveZ,
+        CubeFace::NegativeZ,
+    ];
+
+    pub fn gl_target(self) -> GLenum {
+        gl::TEXTURE_CUBE_MAP_POSITIVE_X + self as GLenum
+    }
+
+    /// Direction through the point `(s, t)` of the face, both in `[-1, 1]`, with `t` growing
+    /// down the face as GL stores it.
+    pub fn direction(self, s: f32, t: f32) -> Vec3 {
+        match self {
+            CubeFace::PositiveX => Vec3::new(1.0, -t, -s),
+            CubeFace::NegativeX => Vec3::new(-1.0, -t, s),
+            CubeFace::PositiveY => Vec3::new(s, 1.0, t),
+            CubeFace::NegativeY => Vec3::new(s, -1.0, -t),
+            CubeFace::PositiveZ => Vec3::new(s, -t, 1.0),
+            CubeFace::NegativeZ => Vec3::new(-s, -t, -1.0),
+        }
+    }
+}
+
+/// A cube map, e.g. for skyboxes and environment lighting.
+///
+/// Filtering blends across face edges, as `Window` enables `GL_TEXTURE_CUBE_MAP_SEAMLESS`.
+pub struct TextureCube {
+    texture: TextureObject,
+    size: u32,
+    format: InternalFormat,
+}
+
+impl TextureCube {
+    /// Allocates a cube map with `size` x `size` faces and undefined contents.
+    pub fn new(size: u32, options: &TextureOptions) -> TextureCube {
+        let format = options.internal_format.unwrap_or(InternalFormat::Rgba8);
+        let cube = TextureCube::create(size, format, options);
+        let (pixels, kind) = format.allocation_enums();
+        for &face in &CubeFace::ALL {
+            cube.upload(face, pixels, kind, std::ptr::null());
+        }
+        cube.texture.finish(options);
+        cube
+    }
+
+    /// Builds a cube map from six square images of the same size, in `CubeFace::ALL` order.
+    ///
+    /// Each image is shown as seen from inside the cube, the way skybox sets are drawn.
+    /// Panics if the faces are not all the same square size.
+    pub fn from_faces(faces: &[Image; 6], options: &TextureOptions) -> TextureCube {
+        let size = faces[0].width();
+        if let Some(face) = faces
+            .iter()
+            .find(|f| f.width() != size || f.height() != size)
+        {
+            panic!(
+                "Cube map faces must all be {}x{}, found one of {}x{}",
+                size,
+                size,
+                face.width(),
+                face.height()
+            );
+        }
+
+        let format = options
+            .internal_format
+            .unwrap_or_else(|| faces[0].format().into());
+        let cube = TextureCube::create(size, format, options);
+        for (image, &face) in faces.iter().zip(CubeFace::ALL.iter()) {
+            // GL expects the top row of each face first.
+            let mut image = image.clone();
+            image.flip_vertically();
+            cube.upload(
+                face,
+                image.format().gl_format(),
+                image.format().gl_type(),
+                image.data().as_ptr(),
+            );
+        }
+        cube.texture.finish(options);
+        cube
+    }
+
+    /// Loads six face images, named in `CubeFace::ALL` order: right, left, top, bottom,
+    /// front and back.
+    pub fn from_res_faces(
+        res: &Resources,
+        names: &[&str; 6],
+        options: &TextureOptions,
+    ) -> Result<TextureCube, Error> {
+        let mut faces = Vec::with_capacity(6);
+        for &name in names {
+            let image = Image::from_res(res, name)?;
+            let size = faces.first().map_or(image.width(), |f: &Image| f.width());
+            if image.width() != size || image.height() != size {
+                return Err(Error::TextureSize {
+                    name: String::from(name),
+                    message: format!(
+                        "is {}x{}, but cube map faces must all be {}x{}",
+                        image.width(),
+                        image.height(),
+                        size,
+                        size
+                    ),
+                });
+            }
+            faces.push(image);
+        }
+
+        let faces: [Image; 6] = match faces.try_into() {
+            Ok(faces) => faces,
+            Err(_) => unreachable!("one face is loaded per name"),
+        };
+        Ok(TextureCube::from_faces(&faces, options))
+    }
+
+    /// Projects an equirectangular (latitude-longitude) panorama onto `size` x `size` faces.
+    ///
+    /// Float images stay float; the default internal format is the 16-bit float one with
+    /// the panorama's channel count.
+    pub fn from_equirectangular(image: &Image, size: u32, options: &TextureOptions) -> TextureCube {
+        let channels = image.format().channels();
+        let pixels = match channels {
+            1 => PixelFormat::R32F,
+            2 => PixelFormat::Rg32F,
+            3 => PixelFormat::Rgb32F,
+            _ => PixelFormat::Rgba32F,
+        };
+        let format =
+            options
+                .internal_format
+                .unwrap_or_else(|| match (image.format().gl_type(), channels) {
+                    (gl::FLOAT, 1) => InternalFormat::R16F,
+                    (gl::FLOAT, 2) => InternalFormat::Rg16F,
+                    (gl::FLOAT, 3) => InternalFormat::Rgb16F,
+                    (gl::FLOAT, _) => InternalFormat::Rgba16F,
+                    _ => image.format().into(),
+                });
+
+        let cube = TextureCube::create(size, format, options);
+        for &face in &CubeFace::ALL {
+            let data: Vec<u8> = equirectangular_face(image, face, size)
+                .iter()
+                .flat_map(|texel| {
+                    let texel = [texel.x, texel.y, texel.z, texel.w];
+                    texel[..channels]
+                        .iter()
+                        .flat_map(|c| c.to_ne_bytes().to_vec())
+                        .collect::<Vec<u8>>()
+                })
+                .collect();
+            cube.upload(face, pixels.gl_format(), pixels.gl_type(), data.as_ptr());
+        }
+        cube.texture.finish(options);
+        cube
+    }
+
+    /// Loads a panorama, typically a Radiance `.hdr` file, and projects it onto a cube map.
+    pub fn from_res_equirectangular(
+        res: &Resources,
+        name: &str,
+        size: u32,
+        options: &TextureOptions,
+    ) -> Result<TextureCube, Error> {
+        let image = Image::from_res(res, name)?;
+        if image.width() != image.height() * 2 {
+            return Err(Error::TextureSize {
+                name: String::from(name),
+                message: format!(
+                    "is {}x{}, but a panorama must be twice as wide as it is tall",
+                    image.width(),
+                    image.height()
+                ),
+            });
+        }
+        Ok(TextureCube::from_equirectangular(&image, size, options))
+    }
+
+    fn create(size: u32, format: InternalFormat, options: &TextureOptions) -> TextureCube {
+        TextureCube {
+            texture: TextureObject::new(gl::TEXTURE_CUBE_MAP, options),
+            size,
+            format,
+        }
+    }
+
+    fn upload(&self, face: CubeFace, pixels: GLenum, kind: GLenum, data: *const u8) {
+        unpack_tightly();
+        unsafe {
+            gl::TexImage2D(
+                face.gl_target(),
+                0,
+                self.format.gl_enum() as GLint,
+                self.size as GLsizei,
+                self.size as GLsizei,
+                0,
+                pixels,
+                kind,
+                data as *const GLvoid,
+            );
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.texture.id()
+    }
+
+    /// Width and height of each face.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn format(&self) -> InternalFormat {
+        self.format
+    }
+
+    pub fn mip_levels(&self) -> u32 {
+        mip_levels(self.size)
+    }
+
+    /// Binds the cube map to the active texture unit.
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    /// Binds the cube map to texture unit `unit`, leaving that unit active.
+    pub fn bind_to_unit(&self, unit: u32) {
+        self.texture.bind_to_unit(unit);
+    }
+
+    pub fn unbind(&self) {
+        self.texture.unbind();
+    }
+
+    /// Changes the filtering of the bound cube map.
+    pub fn set_filter(&self, min: Filter, mag: Filter, mipmap: Option<Filter>) {
+        self.texture.set_filter(min, mag, mipmap);
+    }
+
+    /// Changes the wrap modes of the bound cube map.
+    pub fn set_wrap(&self, s: Wrap, t: Wrap, r: Wrap) {
+        self.texture.set_wrap(s, t, r);
+    }
+
+    /// Regenerates every mip level of the bound cube map from level 0.
+    pub fn generate_mipmaps(&self) {
+        self.texture.generate_mipmaps();
+    }
+}
+
+/// Samples one cube face from a panorama, rows in GL's top-first face order.
+fn equirectangular_face(image: &Image, face: CubeFace, size: u32) -> Vec<Vec4> {
+    let mut texels = Vec::with_capacity(size as usize * size as usize);
+    for row in 0..size {
+        for column in 0..size {
+            let s = (column as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let t = (row as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let direction = face.direction(s, t).normalize();
+
+            let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+            let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+            texels.push(sample_bilinear(image, u, v));
+        }
+    }
+    texels
+}
+
+/// Samples with `u` wrapping around and `v` clamped, as a panorama needs.
+fn sample_bilinear(image: &Image, u: f32, v: f32) -> Vec4 {
+    let (width, height) = (image.width() as i64, image.height() as i64);
+    let x = u * width as f32 - 0.5;
+    let y = v * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width) as u32;
+        let y = y.clamp(0, height - 1) as u32;
+        image.texel(x, y)
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let bottom = texel(x0, y0) * (1.0 - fx) + texel(x0 + 1, y0) * fx;
+    let top = texel(x0, y0 + 1) * (1.0 - fx) + texel(x0 + 1, y0 + 1) * fx;
+    bottom * (1.0 - fy) + top * fy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_centers_point_along_their_axes() {
+        let expected = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for (face, expected) in CubeFace::ALL.iter().zip(expected.iter()) {
+            assert_eq!(*expected, face.direction(0.0, 0.0));
+        }
+        assert_eq!(
+            gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+            CubeFace::NegativeZ.gl_target()
+        );
+    }
+
+    #[test]
+    fn side_faces_have_the_sky_at_the_top() {
+        for &face in &[
+            CubeFace::PositiveX,
+            CubeFace::NegativeX,
+            CubeFace::PositiveZ,
+            CubeFace::NegativeZ,
+        ] {
+            assert!(face.direction(0.0, -1.0).y > 0.0);
+        }
+    }
+
+    #[test]
+    fn projects_panoramas_onto_faces() {
+        // Top half white, bottom half black; 8x4, bottom row first.
+        let data: Vec<u8> = (0..32).map(|i| if i < 16 { 0 } else { 255 }).collect();
+        let panorama = Image::new(8, 4, PixelFormat::R8, data);
+
+        let up = equirectangular_face(&panorama, CubeFace::PositiveY, 4);
+        let down = equirectangular_face(&panorama, CubeFace::NegativeY, 4);
+        let side = equirectangular_face(&panorama, CubeFace::PositiveX, 4);
+
+        assert!(up.iter().all(|t| t.x > 0.99));
+        assert!(down.iter().all(|t| t.x < 0.01));
+        // Top row of a side face looks up, bottom row looks down.
+        assert!(side[1].x > 0.99);
+        assert!(side[13].x < 0.01);
+    }
+}
@@ -0,0 +1,206 @@
+//! zlib compression with fixed Huffman codes and greedy LZ77 matching, as used to write PNGs.
+
+use super::inflate::{adler32, DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+
+const WINDOW: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+/// Candidates tried per position before settling for the best match so far.
+const MAX_CHAIN: usize = 64;
+
+/// Compresses `data` into a zlib stream.
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // Default compression, no preset dictionary.
+    writer.bytes.extend_from_slice(&[0x78, 0x9c]);
+
+    // A single final block with the fixed codes.
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut chains = Chains {
+        head: vec![usize::MAX; 1 << HASH_BITS],
+        previous: vec![usize::MAX; WINDOW],
+    };
+
+    let mut position = 0;
+    while position < data.len() {
+        let (length, distance) = chains.longest_match(data, position);
+        if length >= MIN_MATCH {
+            writer.length(length);
+            writer.distance(distance);
+            for p in position..position + length {
+                chains.insert(data, p);
+            }
+            position += length;
+        } else {
+            writer.literal(data[position]);
+            chains.insert(data, position);
+            position += 1;
+        }
+    }
+    writer.symbol(256);
+
+    let mut bytes = writer.finish();
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from(bytes[0]) << 16 | u32::from(bytes[1]) << 8 | u32::from(bytes[2]);
+    (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Earlier positions by the hash of the three bytes starting there, newest first.
+struct Chains {
+    head: Vec<usize>,
+    /// The position before each one with the same hash, indexed modulo the window size.
+    previous: Vec<usize>,
+}
+
+impl Chains {
+    fn insert(&mut self, data: &[u8], position: usize) {
+        if position + MIN_MATCH <= data.len() {
+            let hash = hash(&data[position..]);
+            self.previous[position % WINDOW] = self.head[hash];
+            self.head[hash] = position;
+        }
+    }
+
+    /// Finds the longest earlier occurrence of the bytes at `position`, returning its length
+    /// and distance back. The length is 0 if nothing matches.
+    fn longest_match(&self, data: &[u8], position: usize) -> (usize, usize) {
+        if position + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+
+        let max = MAX_MATCH.min(data.len() - position);
+        let mut best = (0, 0);
+        let mut candidate = self.head[hash(&data[position..])];
+        for _ in 0..MAX_CHAIN {
+            if candidate == usize::MAX || position - candidate >= WINDOW {
+                break;
+            }
+            let length = data[candidate..]
+                .iter()
+                .zip(&data[position..position + max])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, position - candidate);
+                if length == max {
+                    break;
+                }
+            }
+            candidate = self.previous[candidate % WINDOW];
+        }
+        best
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    /// Writes the low `count` bits of `value`, least significant first.
+    fn bits(&mut self, value: u32, count: u32) {
+        self.buffer |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is stored most significant bit first.
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    /// Writes a literal/length symbol with the fixed code.
+    fn symbol(&mut self, symbol: u16) {
+        let symbol = u32::from(symbol);
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn literal(&mut self, byte: u8) {
+        self.symbol(u16::from(byte));
+    }
+
+    fn length(&mut self, length: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|&base| base as usize <= length)
+            .unwrap();
+        self.symbol(257 + index as u16);
+        self.bits(
+            (length - LENGTH_BASE[index] as usize) as u32,
+            u32::from(LENGTH_EXTRA[index]),
+        );
+    }
+
+    fn distance(&mut self, distance: usize) {
+        let index = DISTANCE_BASE
+            .iter()
+            .rposition(|&base| base as usize <= distance)
+            .unwrap();
+        self.code(index as u32, 5);
+        self.bits(
+            (distance - DISTANCE_BASE[index] as usize) as u32,
+            u32::from(DISTANCE_EXTRA[index]),
+        );
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::inflate::zlib_decompress;
+    use super::*;
+
+    #[test]
+    fn round_trips_through_inflate() {
+        let mut x: u32 = 7;
+        let noisy: Vec<u8> = (0..5000)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345);
+                (x >> 16) as u8
+            })
+            .collect();
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            b"a".to_vec(),
+            b"abcabcabcabcabcabc".to_vec(),
+            vec![0; 100_000],
+            noisy,
+        ];
+
+        for input in inputs {
+            assert_eq!(input, zlib_decompress(&zlib_compress(&input)).unwrap());
+        }
+    }
+
+    #[test]
+    fn compresses_repetitive_data() {
+        let rows: Vec<u8> = (0..64 * 64).map(|i| (i % 64) as u8).collect();
+
+        assert!(zlib_compress(&rows).len() < rows.len() / 20);
+    }
+}
@@ -0,0 +1,200 @@
+use super::compile_log::Diagnostic;
+use super::framebuffer;
+use super::reflection;
+use crate::resources;
+
+use gl::types::*;
+use std::fmt;
+
+/// Errors from loading and building anything in `render`: shaders, models, images and GL
+/// objects.
+#[derive(Debug)]
+pub enum Error {
+    ResourceLoad {
+        name: String,
+        inner: resources::Error,
+    },
+    CanNotDetermineShaderTypeForResource {
+        name: String,
+    },
+    NoShaderStagesFound {
+        name: String,
+    },
+    ComputeWithGraphicsStages {
+        name: String,
+    },
+    InvalidInclude {
+        name: String,
+        line: usize,
+    },
+    IncludeCycle {
+        name: String,
+        chain: Vec<String>,
+    },
+    CompileError {
+        name: String,
+        message: String,
+        diagnostics: Vec<Diagnostic>,
+    },
+    LinkError {
+        name: String,
+        message: String,
+    },
+    UniformNotFound {
+        name: String,
+        uniform: String,
+    },
+    BlockNotFound {
+        name: String,
+        block: String,
+    },
+    UniformTypeMismatch {
+        name: String,
+        uniform: String,
+        expected: &'static [GLenum],
+        actual: GLenum,
+    },
+    UniformArrayTooLong {
+        name: String,
+        uniform: String,
+        capacity: usize,
+        provided: usize,
+    },
+    VertexLayoutMismatch {
+        name: String,
+        attribute: String,
+        message: String,
+    },
+    ModelParse {
+        name: String,
+        line: usize,
+        message: String,
+    },
+    ModelImport {
+        name: String,
+        message: String,
+    },
+    ImageDecode {
+        name: String,
+        message: String,
+    },
+    TextureSize {
+        name: String,
+        message: String,
+    },
+    FramebufferIncomplete {
+        status: GLenum,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ResourceLoad { name, inner } => {
+                write!(f, "Failed to load resource {}: {}", name, inner)
+            }
+            Error::CanNotDetermineShaderTypeForResource { name } => {
+                write!(f, "Can not determine the shader stage of {}", name)
+            }
+            Error::NoShaderStagesFound { name } => {
+                write!(f, "No shader stage files found for {}", name)
+            }
+            Error::ComputeWithGraphicsStages { name } => {
+                write!(f, "{}.comp can not share a program with other stages", name)
+            }
+            Error::InvalidInclude { name, line } => {
+                write!(f, "{}:{}: expected #include \"path\"", name, line)
+            }
+            Error::IncludeCycle { name, chain } => {
+                write!(f, "Include cycle in {}: {}", name, chain.join(" -> "))
+            }
+            Error::CompileError {
+                name,
+                message,
+                diagnostics,
+            } => {
+                write!(f, "Failed to compile {}:", name)?;
+                if diagnostics.is_empty() {
+                    write!(f, "\n{}", message.trim_end())
+                } else {
+                    diagnostics.iter().try_for_each(|d| write!(f, "\n{}", d))
+                }
+            }
+            Error::LinkError { name, message } => {
+                write!(f, "Failed to link {}:\n{}", name, message.trim_end())
+            }
+            Error::UniformNotFound { name, uniform } => {
+                write!(f, "{} has no active uniform {}", name, uniform)
+            }
+            Error::BlockNotFound { name, block } => {
+                write!(f, "{} has no active block {}", name, block)
+            }
+            Error::UniformTypeMismatch {
+                name,
+                uniform,
+                expected,
+                actual,
+            } => {
+                let expected: Vec<&str> = expected
+                    .iter()
+                    .map(|&t| reflection::gl_type_name(t))
+                    .collect();
+                write!(
+                    f,
+                    "Uniform {} in {} is a {}, but was set as {}",
+                    uniform,
+                    name,
+                    reflection::gl_type_name(*actual),
+                    expected.join(" or ")
+                )
+            }
+            Error::UniformArrayTooLong {
+                name,
+                uniform,
+                capacity,
+                provided,
+            } => write!(
+                f,
+                "Uniform {} in {} holds {} values, but {} were given",
+                uniform, name, capacity, provided
+            ),
+            Error::VertexLayoutMismatch {
+                name,
+                attribute,
+                message,
+            } => write!(
+                f,
+                "Vertex layout does not match attribute {} of {}: {}",
+                attribute, name, message
+            ),
+            Error::ModelParse {
+                name,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", name, line, message),
+            Error::ModelImport { name, message } => {
+                write!(f, "Failed to import {}: {}", name, message)
+            }
+            Error::ImageDecode { name, message } => {
+                write!(f, "Failed to decode image {}: {}", name, message)
+            }
+            Error::TextureSize { name, message } => {
+                write!(f, "Image {} has the wrong size: {}", name, message)
+            }
+            Error::FramebufferIncomplete { status } => write!(
+                f,
+                "Framebuffer is incomplete: {}",
+                framebuffer::status_name(*status)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ResourceLoad { inner, .. } => Some(inner),
+            _ => None,
+        }
+    }
+}
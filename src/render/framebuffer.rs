@@ -0,0 +1,700 @@
+use super::image::{Image, PixelFormat};
+use super::texture::Texture2D;
+use super::{Error, Filter, InternalFormat, TextureOptions, Window};
+use crate::utils::Vec4;
+
+use gl::types::*;
+use std::ops::BitOr;
+
+/// Which of a framebuffer's color, depth and stencil buffers an operation touches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferMask {
+    pub color: bool,
+    pub depth: bool,
+    pub stencil: bool,
+}
+
+impl BufferMask {
+    pub const COLOR: BufferMask = BufferMask {
+        color: true,
+        depth: false,
+        stencil: false,
+    };
+    pub const DEPTH: BufferMask = BufferMask {
+        color: false,
+        depth: true,
+        stencil: false,
+    };
+    pub const STENCIL: BufferMask = BufferMask {
+        color: false,
+        depth: false,
+        stencil: true,
+    };
+    pub const ALL: BufferMask = BufferMask {
+        color: true,
+        depth: true,
+        stencil: true,
+    };
+
+    pub fn gl_bits(self) -> GLbitfield {
+        let mut bits = 0;
+        if self.color {
+            bits |= gl::COLOR_BUFFER_BIT;
+        }
+        if self.depth {
+            bits |= gl::DEPTH_BUFFER_BIT;
+        }
+        if self.stencil {
+            bits |= gl::STENCIL_BUFFER_BIT;
+        }
+        bits
+    }
+}
+
+impl BitOr for BufferMask {
+    type Output = BufferMask;
+
+    fn bitor(self, other: BufferMask) -> BufferMask {
+        BufferMask {
+            color: self.color || other.color,
+            depth: self.depth || other.depth,
+            stencil: self.stencil || other.stencil,
+        }
+    }
+}
+
+/// Image storage that can only be rendered to, not sampled. Needed for multisampling.
+pub struct Renderbuffer {
+    id: GLuint,
+    width: u32,
+    height: u32,
+    format: InternalFormat,
+    samples: u32,
+}
+
+impl Renderbuffer {
+    pub fn new(width: u32, height: u32, format: InternalFormat) -> Renderbuffer {
+        Renderbuffer::multisampled(width, height, format, 0)
+    }
+
+    /// Allocates a renderbuffer with `samples` samples per pixel. The driver may round the
+    /// count up; 0 means no multisampling.
+    pub fn multisampled(
+        width: u32,
+        height: u32,
+        format: InternalFormat,
+        samples: u32,
+    ) -> Renderbuffer {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples as GLsizei,
+                format.gl_enum(),
+                width as GLsizei,
+                height as GLsizei,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+
+        Renderbuffer {
+            id,
+            width,
+            height,
+            format,
+            samples,
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> InternalFormat {
+        self.format
+    }
+
+    /// Samples per pixel that were asked for; 0 if not multisampled.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.id);
+        }
+    }
+}
+
+/// An image a framebuffer renders into.
+pub enum Attachment {
+    /// Level 0 of a texture, which can be sampled once rendering is done.
+    Texture(Texture2D),
+    Renderbuffer(Renderbuffer),
+}
+
+impl Attachment {
+    pub fn width(&self) -> u32 {
+        match self {
+            Attachment::Texture(texture) => texture.width(),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.width(),
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            Attachment::Texture(texture) => texture.height(),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.height(),
+        }
+    }
+
+    pub fn format(&self) -> InternalFormat {
+        match self {
+            Attachment::Texture(texture) => texture.format(),
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.format(),
+        }
+    }
+
+    fn samples(&self) -> u32 {
+        match self {
+            Attachment::Texture(_) => 0,
+            Attachment::Renderbuffer(renderbuffer) => renderbuffer.samples(),
+        }
+    }
+
+    /// Attaches to `point` of the framebuffer bound to `GL_FRAMEBUFFER`.
+    fn attach(&self, point: GLenum) {
+        unsafe {
+            match self {
+                Attachment::Texture(texture) => {
+                    gl::FramebufferTexture2D(
+                        gl::FRAMEBUFFER,
+                        point,
+                        gl::TEXTURE_2D,
+                        texture.id(),
+                        0,
+                    );
+                }
+                Attachment::Renderbuffer(renderbuffer) => {
+                    gl::FramebufferRenderbuffer(
+                        gl::FRAMEBUFFER,
+                        point,
+                        gl::RENDERBUFFER,
+                        renderbuffer.id(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// An offscreen render target with any number of color attachments and optional depth and
+/// stencil attachments.
+///
+/// The framebuffer owns its attachments, so textures rendered into stay reachable through
+/// `color_texture` and `depth_texture`.
+pub struct Framebuffer {
+    id: GLuint,
+    width: u32,
+    height: u32,
+    samples: u32,
+    colors: Vec<Attachment>,
+    depth: Option<Attachment>,
+    stencil: Option<Attachment>,
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer that renders fragment outputs `0..colors.len()` into `colors`.
+    ///
+    /// `depth` may use a combined depth-stencil format, in which case it serves as the
+    /// stencil buffer too. Panics if an attachment's format doesn't suit its slot or the
+    /// attachments differ in size. Returns `Error::FramebufferIncomplete` if the driver
+    /// rejects the combination.
+    pub fn new(
+        colors: Vec<Attachment>,
+        depth: Option<Attachment>,
+        stencil: Option<Attachment>,
+    ) -> Result<Framebuffer, Error> {
+        let first = colors
+            .iter()
+            .chain(depth.iter())
+            .chain(stencil.iter())
+            .next()
+            .expect("A framebuffer needs at least one attachment");
+        let (width, height, samples) = (first.width(), first.height(), first.samples());
+
+        for (index, color) in colors.iter().enumerate() {
+            assert!(
+                color.format().is_color(),
+                "Color attachment {} has the non-color format {:?}",
+                index,
+                color.format()
+            );
+        }
+        if let Some(depth) = &depth {
+            assert!(
+                depth.format().has_depth(),
+                "Depth attachment has the format {:?}",
+                depth.format()
+            );
+        }
+        if let Some(stencil) = &stencil {
+            assert!(
+                stencil.format() == InternalFormat::Stencil8,
+                "Stencil attachment has the format {:?}",
+                stencil.format()
+            );
+            assert!(
+                !depth.as_ref().is_some_and(|d| d.format().has_stencil()),
+                "A framebuffer can't have both a depth-stencil and a stencil attachment"
+            );
+        }
+        for attachment in colors.iter().chain(depth.iter()).chain(stencil.iter()) {
+            assert!(
+                attachment.width() == width && attachment.height() == height,
+                "Framebuffer attachments must all be {}x{}, found one of {}x{}",
+                width,
+                height,
+                attachment.width(),
+                attachment.height()
+            );
+        }
+
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        let framebuffer = Framebuffer {
+            id,
+            width,
+            height,
+            samples,
+            colors,
+            depth,
+            stencil,
+        };
+
+        framebuffer.bind();
+        for (index, color) in framebuffer.colors.iter().enumerate() {
+            color.attach(gl::COLOR_ATTACHMENT0 + index as GLenum);
+        }
+        if let Some(depth) = &framebuffer.depth {
+            depth.attach(if depth.format().has_stencil() {
+                gl::DEPTH_STENCIL_ATTACHMENT
+            } else {
+                gl::DEPTH_ATTACHMENT
+            });
+        }
+        if let Some(stencil) = &framebuffer.stencil {
+            stencil.attach(gl::STENCIL_ATTACHMENT);
+        }
+
+        let indices: Vec<u32> = (0..framebuffer.colors.len() as u32).collect();
+        framebuffer.set_draw_buffers(&indices);
+        unsafe {
+            gl::ReadBuffer(if framebuffer.colors.is_empty() {
+                gl::NONE
+            } else {
+                gl::COLOR_ATTACHMENT0
+            });
+        }
+
+        let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+        framebuffer.unbind();
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(Error::FramebufferIncomplete { status });
+        }
+        Ok(framebuffer)
+    }
+
+    /// A framebuffer with one color texture and, if `depth` is set, a depth renderbuffer:
+    /// the usual target for post-processing.
+    ///
+    /// `options.mipmap_filter` is ignored: rendering only fills the base level, so sampling
+    /// other levels would read undefined texels. Call `generate_mipmaps` on the texture and set
+    /// its filter yourself to sample it mipmapped.
+    pub fn with_color_texture(
+        width: u32,
+        height: u32,
+        options: &TextureOptions,
+        depth: Option<InternalFormat>,
+    ) -> Result<Framebuffer, Error> {
+        let options = TextureOptions {
+            mipmap_filter: None,
+            ..*options
+        };
+        let color = Attachment::Texture(Texture2D::new(width, height, &options));
+        let depth =
+            depth.map(|format| Attachment::Renderbuffer(Renderbuffer::new(width, height, format)));
+        Framebuffer::new(vec![color], depth, None)
+    }
+
+    /// A framebuffer with only a depth texture, for rendering shadow maps.
+    pub fn with_depth_texture(
+        width: u32,
+        height: u32,
+        format: InternalFormat,
+    ) -> Result<Framebuffer, Error> {
+        let options = TextureOptions {
+            internal_format: Some(format),
+            ..TextureOptions::nearest()
+        };
+        let depth = Attachment::Texture(Texture2D::new(width, height, &options));
+        Framebuffer::new(Vec::new(), Some(depth), None)
+    }
+
+    /// A framebuffer of multisampled renderbuffers, to be resolved into a single-sampled
+    /// framebuffer of the same size with `resolve`.
+    pub fn multisampled(
+        width: u32,
+        height: u32,
+        samples: u32,
+        colors: &[InternalFormat],
+        depth: Option<InternalFormat>,
+    ) -> Result<Framebuffer, Error> {
+        let renderbuffer = |format| {
+            Attachment::Renderbuffer(Renderbuffer::multisampled(width, height, format, samples))
+        };
+        Framebuffer::new(
+            colors.iter().map(|&format| renderbuffer(format)).collect(),
+            depth.map(renderbuffer),
+            None,
+        )
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Samples per pixel that were asked for; 0 if not multisampled.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn color_attachments(&self) -> &[Attachment] {
+        &self.colors
+    }
+
+    /// The texture behind color attachment `index`, if it is one.
+    pub fn color_texture(&self, index: usize) -> Option<&Texture2D> {
+        match self.colors.get(index) {
+            Some(Attachment::Texture(texture)) => Some(texture),
+            _ => None,
+        }
+    }
+
+    /// The texture behind the depth attachment, if it is one.
+    pub fn depth_texture(&self) -> Option<&Texture2D> {
+        match &self.depth {
+            Some(Attachment::Texture(texture)) => Some(texture),
+            _ => None,
+        }
+    }
+
+    pub fn depth_attachment(&self) -> Option<&Attachment> {
+        self.depth.as_ref()
+    }
+
+    pub fn stencil_attachment(&self) -> Option<&Attachment> {
+        self.stencil.as_ref()
+    }
+
+    /// Binds the framebuffer for drawing and reading. The viewport is left alone; see
+    /// `bind_with_viewport`.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+        }
+    }
+
+    /// Binds the framebuffer and sets the viewport to cover all of it.
+    ///
+    /// `Window::bind_framebuffer` switches back to the window and its viewport.
+    pub fn bind_with_viewport(&self) {
+        self.bind();
+        unsafe {
+            gl::Viewport(0, 0, self.width as GLsizei, self.height as GLsizei);
+        }
+    }
+
+    /// Binds the default framebuffer.
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Routes fragment output `i` of the bound framebuffer to color attachment `indices[i]`.
+    pub fn set_draw_buffers(&self, indices: &[u32]) {
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= self.colors.len()) {
+            panic!(
+                "Color attachment {} does not exist; the framebuffer has {}",
+                index,
+                self.colors.len()
+            );
+        }
+
+        let buffers: Vec<GLenum> = indices.iter().map(|&i| gl::COLOR_ATTACHMENT0 + i).collect();
+        unsafe {
+            if buffers.is_empty() {
+                gl::DrawBuffer(gl::NONE);
+            } else {
+                gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+            }
+        }
+    }
+
+    /// Binds the framebuffer and clears its draw buffer `index` to `color`, independently of
+    /// the other attachments and the clear color. That is color attachment `index` unless
+    /// `set_draw_buffers` routed it elsewhere.
+    ///
+    /// Like `Window::clear`, this enables the color write mask through `window`'s state cache
+    /// first, but an enabled scissor box still limits it.
+    pub fn clear_color(&self, window: &Window, index: u32, color: Vec4) {
+        self.bind();
+        window.enable_writes(BufferMask::COLOR);
+        let color = [color.x, color.y, color.z, color.w];
+        unsafe {
+            gl::ClearBufferfv(gl::COLOR, index as GLint, color.as_ptr());
+        }
+    }
+
+    /// Binds the framebuffer and clears its depth and stencil attachments, enabling their write
+    /// masks through `window`'s state cache first, like `clear_color`.
+    pub fn clear_depth_stencil(&self, window: &Window, depth: f32, stencil: i32) {
+        self.bind();
+        window.enable_writes(BufferMask::DEPTH | BufferMask::STENCIL);
+        unsafe {
+            gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil);
+        }
+    }
+
+    /// Reads color attachment `index` back as RGBA8 pixels, bottom row first.
+    ///
+    /// Multisampled framebuffers can't be read directly, so this panics on them; `resolve`
+    /// them first.
+    pub fn read_pixels(&self, index: u32) -> Image {
+        assert_eq!(
+            self.samples, 0,
+            "Can't read a multisampled framebuffer; resolve it first"
+        );
+        assert!(
+            (index as usize) < self.colors.len(),
+            "Color attachment {} does not exist; the framebuffer has {}",
+            index,
+            self.colors.len()
+        );
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + index);
+        }
+        let image = read_pixels(self.width, self.height);
+        unsafe {
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+        image
+    }
+
+    /// Copies `buffers` into `target`, scaling to its size.
+    ///
+    /// Color is copied from attachment 0 to every draw buffer of `target`. Depth and stencil
+    /// can only be copied with `Filter::Nearest`, which this panics on otherwise.
+    pub fn blit(&self, target: &Framebuffer, buffers: BufferMask, filter: Filter) {
+        self.blit_to(target.id, target.width, target.height, buffers, filter);
+    }
+
+    /// Copies `buffers` into the window's default framebuffer, scaling to its size.
+    pub fn blit_to_window(&self, window: &Window, buffers: BufferMask, filter: Filter) {
+        let (width, height) = window.framebuffer_size();
+        self.blit_to(0, width, height, buffers, filter);
+    }
+
+    /// Resolves every attachment of a multisampled framebuffer into the matching attachment
+    /// of `target`, which must be the same size.
+    pub fn resolve(&self, target: &Framebuffer) {
+        assert!(
+            self.width == target.width && self.height == target.height,
+            "Can't resolve a {}x{} framebuffer into a {}x{} one",
+            self.width,
+            self.height,
+            target.width,
+            target.height
+        );
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target.id);
+            for index in 0..self.colors.len().min(target.colors.len()) {
+                let attachment = gl::COLOR_ATTACHMENT0 + index as GLenum;
+                gl::ReadBuffer(attachment);
+                gl::DrawBuffers(1, &attachment);
+                self.blit_bound(self.width, self.height, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            }
+
+            let mut bits = 0;
+            if self.depth.is_some() && target.depth.is_some() {
+                bits |= gl::DEPTH_BUFFER_BIT;
+            }
+            if self.has_stencil() && target.has_stencil() {
+                bits |= gl::STENCIL_BUFFER_BIT;
+            }
+            if bits != 0 {
+                self.blit_bound(self.width, self.height, bits, gl::NEAREST);
+            }
+
+            // Put back the read and draw buffers set up on creation.
+            if !self.colors.is_empty() {
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            }
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.id);
+            let indices: Vec<u32> = (0..target.colors.len() as u32).collect();
+            target.set_draw_buffers(&indices);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    fn has_stencil(&self) -> bool {
+        self.stencil.is_some()
+            || self
+                .depth
+                .as_ref()
+                .is_some_and(|d| d.format().has_stencil())
+    }
+
+    fn blit_to(
+        &self,
+        target: GLuint,
+        width: u32,
+        height: u32,
+        buffers: BufferMask,
+        filter: Filter,
+    ) {
+        assert!(
+            filter == Filter::Nearest || !(buffers.depth || buffers.stencil),
+            "Depth and stencil can only be blitted with Filter::Nearest"
+        );
+        let filter = match filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        };
+
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target);
+            self.blit_bound(width, height, buffers.gl_bits(), filter);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+
+    /// Blits all of the read framebuffer to `width` x `height` of the draw framebuffer.
+    fn blit_bound(&self, width: u32, height: u32, bits: GLbitfield, filter: GLenum) {
+        unsafe {
+            gl::BlitFramebuffer(
+                0,
+                0,
+                self.width as GLint,
+                self.height as GLint,
+                0,
+                0,
+                width as GLint,
+                height as GLint,
+                bits,
+                filter,
+            );
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+/// Reads `width` x `height` RGBA8 pixels from the read buffer of the bound read framebuffer.
+pub(super) fn read_pixels(width: u32, height: u32) -> Image {
+    let format = PixelFormat::Rgba8;
+    let mut data = vec![0u8; width as usize * height as usize * format.bytes_per_pixel()];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width as GLsizei,
+            height as GLsizei,
+            format.gl_format(),
+            format.gl_type(),
+            data.as_mut_ptr() as *mut GLvoid,
+        );
+    }
+    Image::new(width, height, format, data)
+}
+
+/// The `GL_FRAMEBUFFER_*` name of a `glCheckFramebufferStatus` result.
+pub(super) fn status_name(status: GLenum) -> &'static str {
+    match status {
+        gl::FRAMEBUFFER_COMPLETE => "GL_FRAMEBUFFER_COMPLETE",
+        gl::FRAMEBUFFER_UNDEFINED => "GL_FRAMEBUFFER_UNDEFINED",
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "GL_FRAMEBUFFER_INCOMPLETE_ATTACHMENT",
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => {
+            "GL_FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT"
+        }
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "GL_FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER",
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "GL_FRAMEBUFFER_INCOMPLETE_READ_BUFFER",
+        gl::FRAMEBUFFER_UNSUPPORTED => "GL_FRAMEBUFFER_UNSUPPORTED",
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE",
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "GL_FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS",
+        _ => "an unknown status",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_masks_combine() {
+        assert_eq!(gl::COLOR_BUFFER_BIT, BufferMask::COLOR.gl_bits());
+        assert_eq!(
+            gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT,
+            (BufferMask::DEPTH | BufferMask::STENCIL).gl_bits()
+        );
+        assert_eq!(
+            BufferMask::ALL,
+            BufferMask::COLOR | BufferMask::DEPTH | BufferMask::STENCIL
+        );
+    }
+
+    #[test]
+    fn names_incomplete_statuses() {
+        assert_eq!(
+            "GL_FRAMEBUFFER_INCOMPLETE_MULTISAMPLE",
+            status_name(gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE)
+        );
+        assert_eq!("an unknown status", status_name(0));
+    }
+}
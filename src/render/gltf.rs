@@ -0,0 +1,1686 @@
+//! glTF 2.0 models, as `.gltf` with external or embedded buffers or as binary `.glb`.
+//!
+//! Texture coordinates are flipped to put `v = 0` at the bottom like GL and OBJ, and the
+//! tangent handedness flipped with them.
+
+use super::json::{self, Value};
+use super::primitives::MeshData;
+use super::{Error, MeshVertex, Topology};
+use crate::resources::{self, Resources};
+use crate::utils::{Mat4, Quat, Vec2, Vec3, Vec4};
+
+use gl::types::*;
+
+/// Most elements an accessor without a `bufferView` may have; it starts out all zeros.
+const MAX_ZERO_ELEMENTS: usize = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gltf {
+    pub scenes: Vec<Scene>,
+    /// The scene to show by default.
+    pub scene: Option<usize>,
+    pub nodes: Vec<Node>,
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<Material>,
+    pub textures: Vec<Texture>,
+    pub images: Vec<Image>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<Animation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub name: Option<String>,
+    /// Root nodes of the scene.
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    Matrix(Mat4),
+    Decomposed {
+        translation: Vec3,
+        rotation: Quat,
+        scale: Vec3,
+    },
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        match *self {
+            Transform::Matrix(matrix) => matrix,
+            Transform::Decomposed {
+                translation,
+                rotation,
+                scale,
+            } => Mat4::from_translation(translation) * rotation.to_mat4() * Mat4::from_scale(scale),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: Option<String>,
+    pub children: Vec<usize>,
+    /// Relative to the parent node.
+    pub transform: Transform,
+    pub mesh: Option<usize>,
+    pub skin: Option<usize>,
+    /// Morph target weights, overriding the mesh's.
+    pub weights: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+    /// Default morph target weights.
+    pub weights: Vec<f32>,
+}
+
+/// One draw call's worth of a mesh.
+///
+/// `joints` and `weights` are empty unless the mesh is skinned, and then have one entry
+/// per vertex. Normals and tangents missing from the file are generated for triangle lists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Primitive {
+    pub topology: Topology,
+    pub data: MeshData,
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<Vec4>,
+    pub material: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureRef {
+    /// Index into `Gltf::textures`.
+    pub texture: usize,
+    /// Which `TEXCOORD_n` set to sample with.
+    pub tex_coord: u32,
+}
+
+/// A metallic-roughness PBR material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: Option<String>,
+    pub base_color: Vec4,
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive: Vec3,
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+/// An image with the sampler settings to use it with, as GL enums.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    pub image: Option<usize>,
+    pub mag_filter: Option<GLenum>,
+    pub min_filter: Option<GLenum>,
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Image {
+    /// A separate file, as a resource name.
+    Resource(String),
+    Embedded {
+        mime_type: String,
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    pub name: Option<String>,
+    pub joints: Vec<usize>,
+    /// One per joint.
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub skeleton: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    CubicSpline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationPath {
+    Translation,
+    Rotation,
+    Scale,
+    Weights,
+}
+
+/// Keyframes for one animated property.
+///
+/// `output` holds 3 floats per key for translation and scale, 4 (`x, y, z, w`) for rotation
+/// and one per morph target for weights. Cubic spline keys hold an in-tangent, the value and
+/// an out-tangent, in that order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationSampler {
+    pub input: Vec<f32>,
+    pub output: Vec<f32>,
+    pub interpolation: Interpolation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channel {
+    pub sampler: usize,
+    pub node: Option<usize>,
+    pub path: AnimationPath,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+    pub samplers: Vec<AnimationSampler>,
+}
+
+impl Animation {
+    /// Time of the last keyframe in seconds.
+    pub fn duration(&self) -> f32 {
+        self.samplers
+            .iter()
+            .filter_map(|s| s.input.last().cloned())
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Gltf {
+    /// Loads a `.gltf` or `.glb` resource and any buffers it references.
+    pub fn from_res(res: &Resources, name: &str) -> Result<Gltf, Error> {
+        let load = |file: &str| {
+            res.load_bytes(file).map_err(|e| Error::ResourceLoad {
+                name: String::from(file),
+                inner: e,
+            })
+        };
+
+        parse(name, &load(name)?, load)
+    }
+
+    /// World transform of every node, indexed like `nodes`.
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut world = vec![Mat4::identity(); self.nodes.len()];
+        let mut has_parent = vec![false; self.nodes.len()];
+        for node in &self.nodes {
+            for &child in &node.children {
+                has_parent[child] = true;
+            }
+        }
+
+        let mut stack: Vec<(usize, Mat4)> = (0..self.nodes.len())
+            .filter(|&i| !has_parent[i])
+            .map(|i| (i, Mat4::identity()))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            world[index] = parent * node.transform.matrix();
+            stack.extend(node.children.iter().map(|&child| (child, world[index])));
+        }
+
+        world
+    }
+}
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4e4f_534a;
+const GLB_CHUNK_BIN: u32 = 0x004e_4942;
+
+/// Parses a `.gltf` or `.glb` file, loading external buffers with `load`.
+pub fn parse<F>(name: &str, bytes: &[u8], load: F) -> Result<Gltf, Error>
+where
+    F: FnMut(&str) -> Result<Vec<u8>, Error>,
+{
+    let error = |message: &str| Error::ModelImport {
+        name: String::from(name),
+        message: String::from(message),
+    };
+
+    let (text, bin) = if bytes.starts_with(GLB_MAGIC) {
+        split_glb(bytes).map_err(error)?
+    } else {
+        (bytes, None)
+    };
+
+    let text = std::str::from_utf8(text).map_err(|_| error("the JSON is not valid UTF-8"))?;
+    let json = json::parse(text).map_err(|(line, message)| Error::ModelParse {
+        name: String::from(name),
+        line,
+        message,
+    })?;
+
+    Document::new(name, json, bin, load)?.read()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Splits a GLB container into its JSON and optional binary chunks.
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<Vec<u8>>), &'static str> {
+    if read_u32(bytes, 4) != Some(2) {
+        return Err("only version 2 GLB files are supported");
+    }
+    let length = read_u32(bytes, 8).ok_or("truncated GLB header")? as usize;
+    let bytes = bytes
+        .get(..length)
+        .ok_or("GLB file is shorter than its header says")?;
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset < bytes.len() {
+        let chunk_length = read_u32(bytes, offset).ok_or("truncated GLB chunk")? as usize;
+        let chunk_type = read_u32(bytes, offset + 4).ok_or("truncated GLB chunk")?;
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or("truncated GLB chunk")?;
+
+        match chunk_type {
+            GLB_CHUNK_JSON if json.is_none() => json = Some(data),
+            GLB_CHUNK_BIN if bin.is_none() => bin = Some(data.to_vec()),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+
+    Ok((json.ok_or("GLB file has no JSON chunk")?, bin))
+}
+
+/// How the components of an accessor's elements are stored.
+#[derive(Clone, Copy)]
+struct Format {
+    components: usize,
+    component_type: usize,
+    normalized: bool,
+}
+
+struct Document<'a> {
+    name: &'a str,
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Document<'a> {
+    fn new<F>(
+        name: &'a str,
+        json: Value,
+        mut bin: Option<Vec<u8>>,
+        mut load: F,
+    ) -> Result<Document<'a>, Error>
+    where
+        F: FnMut(&str) -> Result<Vec<u8>, Error>,
+    {
+        let mut document = Document {
+            name,
+            json,
+            buffers: Vec::new(),
+        };
+
+        let mut buffers = Vec::new();
+        for (i, buffer) in document.array("buffers").iter().enumerate() {
+            let context = format!("buffers[{}]", i);
+            let byte_length = document.required_usize(buffer, "byteLength", &context)?;
+
+            let data = match buffer.get("uri").and_then(Value::as_str) {
+                Some(uri) if uri.starts_with("data:") => document.data_uri(uri, &context)?.1,
+                Some(uri) => load(&resources::sibling_name(name, &percent_decode(uri)))?,
+                None if i == 0 && bin.is_some() => bin.take().unwrap_or_default(),
+                None => return Err(document.error(format!("{} has no data", context))),
+            };
+
+            if data.len() < byte_length {
+                return Err(document.error(format!(
+                    "{} holds {} bytes, but byteLength is {}",
+                    context,
+                    data.len(),
+                    byte_length
+                )));
+            }
+            buffers.push(data);
+        }
+
+        document.buffers = buffers;
+        Ok(document)
+    }
+
+    fn read(&self) -> Result<Gltf, Error> {
+        let nodes = self.read_nodes()?;
+        let meshes = self.read_meshes()?;
+        let skins = self.read_skins()?;
+        let materials = self.read_materials()?;
+        let textures = self.read_textures()?;
+        let images = self.read_images()?;
+        let animations = self.read_animations()?;
+
+        let mut scenes = Vec::new();
+        for (i, scene) in self.array("scenes").iter().enumerate() {
+            let context = format!("scenes[{}]", i);
+            scenes.push(Scene {
+                name: optional_string(scene, "name"),
+                nodes: self.indices(scene, "nodes", nodes.len(), &context)?,
+            });
+        }
+        let scene = self.index(&self.json, "scene", scenes.len(), "the document")?;
+
+        let gltf = Gltf {
+            scenes,
+            scene,
+            nodes,
+            meshes,
+            materials,
+            textures,
+            images,
+            skins,
+            animations,
+        };
+        self.check_references(&gltf)?;
+        Ok(gltf)
+    }
+
+    fn error(&self, message: String) -> Error {
+        Error::ModelImport {
+            name: String::from(self.name),
+            message,
+        }
+    }
+
+    fn array(&self, key: &str) -> &[Value] {
+        array(&self.json, key)
+    }
+
+    fn required_usize(&self, value: &Value, key: &str, context: &str) -> Result<usize, Error> {
+        match value.get(key) {
+            Some(v) => v.as_usize().ok_or_else(|| {
+                self.error(format!("{}.{} is not a valid index or count", context, key))
+            }),
+            None => Err(self.error(format!("{} has no {}", context, key))),
+        }
+    }
+
+    fn index(
+        &self,
+        value: &Value,
+        key: &str,
+        count: usize,
+        context: &str,
+    ) -> Result<Option<usize>, Error> {
+        match value.get(key) {
+            Some(_) => {
+                let index = self.required_usize(value, key, context)?;
+                if index >= count {
+                    return Err(self.error(format!(
+                        "{}.{} is {}, but there are only {}",
+                        context, key, index, count
+                    )));
+                }
+                Ok(Some(index))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn indices(
+        &self,
+        value: &Value,
+        key: &str,
+        count: usize,
+        context: &str,
+    ) -> Result<Vec<usize>, Error> {
+        array(value, key)
+            .iter()
+            .map(|v| match v.as_usize() {
+                Some(index) if index < count => Ok(index),
+                _ => Err(self.error(format!("{}.{} has an invalid index", context, key))),
+            })
+            .collect()
+    }
+
+    fn f32_or(&self, value: &Value, key: &str, default: f32, context: &str) -> Result<f32, Error> {
+        match value.get(key) {
+            Some(v) => v
+                .as_f64()
+                .map(|n| n as f32)
+                .ok_or_else(|| self.error(format!("{}.{} is not a number", context, key))),
+            None => Ok(default),
+        }
+    }
+
+    fn floats(
+        &self,
+        value: &Value,
+        key: &str,
+        len: usize,
+        context: &str,
+    ) -> Result<Option<Vec<f32>>, Error> {
+        let values = match value.get(key) {
+            Some(v) => v.as_array(),
+            None => return Ok(None),
+        };
+
+        values
+            .filter(|v| len == 0 || v.len() == len)
+            .and_then(|v| {
+                v.iter()
+                    .map(|n| n.as_f64().map(|n| n as f32))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .map(Some)
+            .ok_or_else(|| {
+                self.error(format!(
+                    "{}.{} is not an array of {} numbers",
+                    context, key, len
+                ))
+            })
+    }
+
+    /// Splits a `data:` URI into its MIME type and decoded bytes.
+    fn data_uri(&self, uri: &str, context: &str) -> Result<(String, Vec<u8>), Error> {
+        let (header, data) = uri
+            .strip_prefix("data:")
+            .and_then(|rest| rest.split_once(','))
+            .ok_or_else(|| self.error(format!("{} has a malformed data URI", context)))?;
+        let mime_type = header
+            .strip_suffix(";base64")
+            .ok_or_else(|| self.error(format!("{} has a data URI that is not base64", context)))?;
+
+        let data = base64_decode(data)
+            .ok_or_else(|| self.error(format!("{} has invalid base64 data", context)))?;
+        Ok((String::from(mime_type), data))
+    }
+
+    fn buffer_view(&self, index: usize, context: &str) -> Result<(&[u8], Option<usize>), Error> {
+        let context = format!("{} bufferView {}", context, index);
+        let view = self
+            .array("bufferViews")
+            .get(index)
+            .ok_or_else(|| self.error(format!("{} does not exist", context)))?;
+
+        let buffer = self
+            .index(view, "buffer", self.buffers.len(), &context)?
+            .ok_or_else(|| self.error(format!("{} has no buffer", context)))?;
+        let offset = match view.get("byteOffset") {
+            Some(_) => self.required_usize(view, "byteOffset", &context)?,
+            None => 0,
+        };
+        let length = self.required_usize(view, "byteLength", &context)?;
+        let stride = match view.get("byteStride") {
+            Some(_) => Some(self.required_usize(view, "byteStride", &context)?),
+            None => None,
+        };
+
+        let data = offset
+            .checked_add(length)
+            .and_then(|end| self.buffers[buffer].get(offset..end))
+            .ok_or_else(|| self.error(format!("{} is outside its buffer", context)))?;
+        Ok((data, stride))
+    }
+
+    /// Reads `count` elements from a buffer view.
+    fn read_view(
+        &self,
+        view: usize,
+        offset: usize,
+        count: usize,
+        format: Format,
+        context: &str,
+    ) -> Result<Vec<f64>, Error> {
+        let Format {
+            components,
+            component_type,
+            normalized,
+        } = format;
+        let (data, stride) = self.buffer_view(view, context)?;
+        let size = component_size(component_type).ok_or_else(|| {
+            self.error(format!(
+                "{} has unknown componentType {}",
+                context, component_type
+            ))
+        })?;
+        let element = components * size;
+        let stride = stride.unwrap_or(element);
+        if stride < element {
+            return Err(self.error(format!(
+                "{} has a byteStride smaller than its elements",
+                context
+            )));
+        }
+
+        // With the stride at least an element long, this also bounds what is allocated below.
+        let end = match count {
+            0 => Some(0),
+            _ => stride
+                .checked_mul(count - 1)
+                .and_then(|n| n.checked_add(offset))
+                .and_then(|n| n.checked_add(element)),
+        };
+        if end.is_none_or(|end| end > data.len()) {
+            return Err(self.error(format!("{} reads past the end of its bufferView", context)));
+        }
+
+        let mut values = Vec::with_capacity(count * components);
+        for i in 0..count {
+            for j in 0..components {
+                let start = offset + i * stride + j * size;
+                values.push(read_component(
+                    &data[start..start + size],
+                    component_type,
+                    normalized,
+                ));
+            }
+        }
+        Ok(values)
+    }
+
+    /// Reads an accessor as plain numbers, returning them with the component count.
+    ///
+    /// Normalized integers are mapped to `[0, 1]` or `[-1, 1]` unless `raw` is set.
+    fn read_accessor(&self, index: usize, raw: bool) -> Result<(Vec<f64>, usize), Error> {
+        let context = format!("accessors[{}]", index);
+        let accessor = self
+            .array("accessors")
+            .get(index)
+            .ok_or_else(|| self.error(format!("{} does not exist", context)))?;
+
+        let count = self.required_usize(accessor, "count", &context)?;
+        let component_type = self.required_usize(accessor, "componentType", &context)?;
+        let normalized = !raw
+            && accessor
+                .get("normalized")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+        let components = match accessor.get("type").and_then(Value::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") | Some("MAT2") => 4,
+            Some("MAT3") => 9,
+            Some("MAT4") => 16,
+            _ => return Err(self.error(format!("{} has an unknown type", context))),
+        };
+        let format = Format {
+            components,
+            component_type,
+            normalized,
+        };
+        let offset = match accessor.get("byteOffset") {
+            Some(_) => self.required_usize(accessor, "byteOffset", &context)?,
+            None => 0,
+        };
+
+        let mut values = match accessor.get("bufferView") {
+            Some(_) => {
+                let view = self.required_usize(accessor, "bufferView", &context)?;
+                self.read_view(view, offset, count, format, &context)?
+            }
+            None => {
+                // Nothing bounds the size of an accessor without data, so cap it.
+                if count > MAX_ZERO_ELEMENTS {
+                    return Err(self.error(format!(
+                        "{} has no bufferView and {} elements, more than the {} allowed",
+                        context, count, MAX_ZERO_ELEMENTS
+                    )));
+                }
+                vec![0.0; count * components]
+            }
+        };
+
+        if let Some(sparse) = accessor.get("sparse") {
+            let context = format!("{}.sparse", context);
+            let sparse_count = self.required_usize(sparse, "count", &context)?;
+            let part = |key: &str| {
+                sparse
+                    .get(key)
+                    .ok_or_else(|| self.error(format!("{} has no {}", context, key)))
+            };
+            let (indices, replacements) = (part("indices")?, part("values")?);
+
+            let offset = |value: &Value| match value.get("byteOffset") {
+                Some(_) => self.required_usize(value, "byteOffset", &context),
+                None => Ok(0),
+            };
+            let targets = self.read_view(
+                self.required_usize(indices, "bufferView", &context)?,
+                offset(indices)?,
+                sparse_count,
+                Format {
+                    components: 1,
+                    component_type: self.required_usize(indices, "componentType", &context)?,
+                    normalized: false,
+                },
+                &context,
+            )?;
+            let replacements = self.read_view(
+                self.required_usize(replacements, "bufferView", &context)?,
+                offset(replacements)?,
+                sparse_count,
+                format,
+                &context,
+            )?;
+
+            for (target, replacement) in targets.iter().zip(replacements.chunks(components)) {
+                let target = *target as usize;
+                if target >= count {
+                    return Err(self.error(format!(
+                        "{} replaces element {} of {}",
+                        context, target, count
+                    )));
+                }
+                values[target * components..(target + 1) * components].copy_from_slice(replacement);
+            }
+        }
+
+        Ok((values, components))
+    }
+
+    /// Reads a float accessor, checking it has `components` components per element.
+    fn read_floats(
+        &self,
+        index: usize,
+        components: usize,
+        context: &str,
+    ) -> Result<Vec<f32>, Error> {
+        let (values, actual) = self.read_accessor(index, false)?;
+        if actual != components {
+            return Err(self.error(format!(
+                "{} uses accessors[{}], which has {} components instead of {}",
+                context, index, actual, components
+            )));
+        }
+        Ok(values.into_iter().map(|v| v as f32).collect())
+    }
+
+    fn read_nodes(&self) -> Result<Vec<Node>, Error> {
+        let nodes = self.array("nodes");
+        let mut parents = vec![None; nodes.len()];
+
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let context = format!("nodes[{}]", i);
+                let children = self.indices(node, "children", nodes.len(), &context)?;
+                for &child in &children {
+                    if child == i || parents[child].replace(i).is_some() {
+                        return Err(self.error(format!("node {} has more than one parent", child)));
+                    }
+                }
+
+                let transform = match self.floats(node, "matrix", 16, &context)? {
+                    Some(m) => Transform::Matrix(Mat4::from_cols(
+                        Vec4::new(m[0], m[1], m[2], m[3]),
+                        Vec4::new(m[4], m[5], m[6], m[7]),
+                        Vec4::new(m[8], m[9], m[10], m[11]),
+                        Vec4::new(m[12], m[13], m[14], m[15]),
+                    )),
+                    None => {
+                        let t = self
+                            .floats(node, "translation", 3, &context)?
+                            .unwrap_or_else(|| vec![0.0; 3]);
+                        let r = self
+                            .floats(node, "rotation", 4, &context)?
+                            .unwrap_or_else(|| vec![0.0, 0.0, 0.0, 1.0]);
+                        let s = self
+                            .floats(node, "scale", 3, &context)?
+                            .unwrap_or_else(|| vec![1.0; 3]);
+                        Transform::Decomposed {
+                            translation: Vec3::new(t[0], t[1], t[2]),
+                            rotation: Quat::new(r[0], r[1], r[2], r[3]),
+                            scale: Vec3::new(s[0], s[1], s[2]),
+                        }
+                    }
+                };
+
+                Ok(Node {
+                    name: optional_string(node, "name"),
+                    children,
+                    transform,
+                    mesh: self.index(node, "mesh", self.array("meshes").len(), &context)?,
+                    skin: self.index(node, "skin", self.array("skins").len(), &context)?,
+                    weights: self
+                        .floats(node, "weights", 0, &context)?
+                        .unwrap_or_default(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .and_then(|nodes| {
+                // Every node reachable from a root means there are no cycles.
+                let mut seen = vec![false; nodes.len()];
+                let mut stack: Vec<usize> =
+                    (0..nodes.len()).filter(|&i| parents[i].is_none()).collect();
+                while let Some(i) = stack.pop() {
+                    seen[i] = true;
+                    stack.extend(&nodes[i].children);
+                }
+                match seen.iter().position(|s| !s) {
+                    Some(i) => Err(self.error(format!("node {} is part of a cycle", i))),
+                    None => Ok(nodes),
+                }
+            })
+    }
+
+    fn read_meshes(&self) -> Result<Vec<GltfMesh>, Error> {
+        let materials = self.array("materials").len();
+
+        self.array("meshes")
+            .iter()
+            .enumerate()
+            .map(|(i, mesh)| {
+                let primitives = array(mesh, "primitives")
+                    .iter()
+                    .enumerate()
+                    .map(|(j, primitive)| {
+                        let context = format!("meshes[{}].primitives[{}]", i, j);
+                        self.read_primitive(primitive, materials, &context)
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                Ok(GltfMesh {
+                    name: optional_string(mesh, "name"),
+                    primitives,
+                    weights: self
+                        .floats(mesh, "weights", 0, &format!("meshes[{}]", i))?
+                        .unwrap_or_default(),
+                })
+            })
+            .collect()
+    }
+
+    fn read_primitive(
+        &self,
+        primitive: &Value,
+        materials: usize,
+        context: &str,
+    ) -> Result<Primitive, Error> {
+        let attributes = primitive
+            .get("attributes")
+            .ok_or_else(|| self.error(format!("{} has no attributes", context)))?;
+        let accessors = self.array("accessors").len();
+        let attribute = |key: &str| self.index(attributes, key, accessors, context);
+
+        let positions = match attribute("POSITION")? {
+            Some(index) => self.read_floats(index, 3, context)?,
+            None => return Err(self.error(format!("{} has no POSITION", context))),
+        };
+        let count = positions.len() / 3;
+
+        let optional = |key: &str, components: usize| -> Result<Option<Vec<f32>>, Error> {
+            let values = match attribute(key)? {
+                Some(index) => self.read_floats(index, components, context)?,
+                None => return Ok(None),
+            };
+            if values.len() != count * components {
+                return Err(self.error(format!(
+                    "{} has {} {} values for {} vertices",
+                    context,
+                    values.len() / components,
+                    key,
+                    count
+                )));
+            }
+            Ok(Some(values))
+        };
+
+        let normals = optional("NORMAL", 3)?;
+        let uvs = optional("TEXCOORD_0", 2)?;
+        let tangents = optional("TANGENT", 4)?;
+        let weights = optional("WEIGHTS_0", 4)?;
+        let joints = match attribute("JOINTS_0")? {
+            Some(index) => {
+                let (values, components) = self.read_accessor(index, true)?;
+                if components != 4 || values.len() != count * 4 {
+                    return Err(self.error(format!("{} has a malformed JOINTS_0", context)));
+                }
+                values
+                    .chunks(4)
+                    .map(|j| [j[0] as u16, j[1] as u16, j[2] as u16, j[3] as u16])
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        let mut data = MeshData::new();
+        for i in 0..count {
+            let normal = normals.as_ref().map_or(Vec3::zero(), |n| {
+                Vec3::new(n[i * 3], n[i * 3 + 1], n[i * 3 + 2])
+            });
+            let uv = uvs
+                .as_ref()
+                .map_or(Vec2::zero(), |t| Vec2::new(t[i * 2], 1.0 - t[i * 2 + 1]));
+            let tangent = tangents.as_ref().map_or(Vec4::zero(), |t| {
+                Vec4::new(t[i * 4], t[i * 4 + 1], t[i * 4 + 2], -t[i * 4 + 3])
+            });
+            data.vertices.push(MeshVertex::new(
+                Vec3::new(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]),
+                normal,
+                uv,
+                tangent,
+            ));
+        }
+
+        data.indices = match self.index(primitive, "indices", accessors, context)? {
+            Some(index) => {
+                let (values, _) = self.read_accessor(index, true)?;
+                values.into_iter().map(|v| v as u32).collect()
+            }
+            None => (0..count as u32).collect(),
+        };
+        if let Some(&bad) = data.indices.iter().find(|&&i| i as usize >= count) {
+            return Err(self.error(format!(
+                "{} has index {} but {} vertices",
+                context, bad, count
+            )));
+        }
+
+        let topology = match primitive.get("mode").map(|m| m.as_usize()) {
+            None | Some(Some(4)) => Topology::Triangles,
+            Some(Some(0)) => Topology::Points,
+            Some(Some(1)) => Topology::Lines,
+            Some(Some(2)) => Topology::LineLoop,
+            Some(Some(3)) => Topology::LineStrip,
+            Some(Some(5)) => Topology::TriangleStrip,
+            Some(Some(6)) => Topology::TriangleFan,
+            _ => return Err(self.error(format!("{} has an unknown mode", context))),
+        };
+
+        if topology == Topology::Triangles {
+            if !data.indices.len().is_multiple_of(3) {
+                return Err(self.error(format!("{} has a partial triangle", context)));
+            }
+            if normals.is_none() {
+                data.generate_normals();
+            }
+            if tangents.is_none() {
+                data.generate_tangents();
+            }
+        }
+
+        Ok(Primitive {
+            topology,
+            data,
+            joints,
+            weights: weights
+                .map(|w| {
+                    w.chunks(4)
+                        .map(|w| Vec4::new(w[0], w[1], w[2], w[3]))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            material: self.index(primitive, "material", materials, context)?,
+        })
+    }
+
+    fn read_skins(&self) -> Result<Vec<Skin>, Error> {
+        let nodes = self.array("nodes").len();
+
+        self.array("skins")
+            .iter()
+            .enumerate()
+            .map(|(i, skin)| {
+                let context = format!("skins[{}]", i);
+                let joints = self.indices(skin, "joints", nodes, &context)?;
+                let inverse_bind_matrices = match self.index(
+                    skin,
+                    "inverseBindMatrices",
+                    self.array("accessors").len(),
+                    &context,
+                )? {
+                    Some(index) => {
+                        let values = self.read_floats(index, 16, &context)?;
+                        if values.len() != joints.len() * 16 {
+                            return Err(self.error(format!(
+                                "{} needs one inverse bind matrix per joint",
+                                context
+                            )));
+                        }
+                        values
+                            .chunks(16)
+                            .map(|m| {
+                                Mat4::from_cols(
+                                    Vec4::new(m[0], m[1], m[2], m[3]),
+                                    Vec4::new(m[4], m[5], m[6], m[7]),
+                                    Vec4::new(m[8], m[9], m[10], m[11]),
+                                    Vec4::new(m[12], m[13], m[14], m[15]),
+                                )
+                            })
+                            .collect()
+                    }
+                    None => vec![Mat4::identity(); joints.len()],
+                };
+
+                Ok(Skin {
+                    name: optional_string(skin, "name"),
+                    joints,
+                    inverse_bind_matrices,
+                    skeleton: self.index(skin, "skeleton", nodes, &context)?,
+                })
+            })
+            .collect()
+    }
+
+    fn read_materials(&self) -> Result<Vec<Material>, Error> {
+        let textures = self.array("textures").len();
+
+        self.array("materials")
+            .iter()
+            .enumerate()
+            .map(|(i, material)| {
+                let context = format!("materials[{}]", i);
+                let texture_ref = |value: &Value, key: &str| -> Result<Option<TextureRef>, Error> {
+                    let info = match value.get(key) {
+                        Some(info) => info,
+                        None => return Ok(None),
+                    };
+                    let context = format!("{}.{}", context, key);
+                    let texture = self
+                        .index(info, "index", textures, &context)?
+                        .ok_or_else(|| self.error(format!("{} has no index", context)))?;
+                    let tex_coord = match info.get("texCoord") {
+                        Some(_) => self.required_usize(info, "texCoord", &context)? as u32,
+                        None => 0,
+                    };
+                    Ok(Some(TextureRef { texture, tex_coord }))
+                };
+
+                let null = Value::Null;
+                let pbr = material.get("pbrMetallicRoughness").unwrap_or(&null);
+                let base_color = self
+                    .floats(pbr, "baseColorFactor", 4, &context)?
+                    .unwrap_or_else(|| vec![1.0; 4]);
+                let emissive = self
+                    .floats(material, "emissiveFactor", 3, &context)?
+                    .unwrap_or_else(|| vec![0.0; 3]);
+                let alpha_mode = match material.get("alphaMode").and_then(Value::as_str) {
+                    None | Some("OPAQUE") => AlphaMode::Opaque,
+                    Some("MASK") => AlphaMode::Mask,
+                    Some("BLEND") => AlphaMode::Blend,
+                    Some(other) => {
+                        return Err(
+                            self.error(format!("{} has unknown alphaMode {}", context, other))
+                        )
+                    }
+                };
+
+                Ok(Material {
+                    name: optional_string(material, "name"),
+                    base_color: Vec4::new(
+                        base_color[0],
+                        base_color[1],
+                        base_color[2],
+                        base_color[3],
+                    ),
+                    base_color_texture: texture_ref(pbr, "baseColorTexture")?,
+                    metallic: self.f32_or(pbr, "metallicFactor", 1.0, &context)?,
+                    roughness: self.f32_or(pbr, "roughnessFactor", 1.0, &context)?,
+                    metallic_roughness_texture: texture_ref(pbr, "metallicRoughnessTexture")?,
+                    normal_texture: texture_ref(material, "normalTexture")?,
+                    normal_scale: self.f32_or(
+                        material.get("normalTexture").unwrap_or(&null),
+                        "scale",
+                        1.0,
+                        &context,
+                    )?,
+                    occlusion_texture: texture_ref(material, "occlusionTexture")?,
+                    occlusion_strength: self.f32_or(
+                        material.get("occlusionTexture").unwrap_or(&null),
+                        "strength",
+                        1.0,
+                        &context,
+                    )?,
+                    emissive: Vec3::new(emissive[0], emissive[1], emissive[2]),
+                    emissive_texture: texture_ref(material, "emissiveTexture")?,
+                    alpha_mode,
+                    alpha_cutoff: self.f32_or(material, "alphaCutoff", 0.5, &context)?,
+                    double_sided: material
+                        .get("doubleSided")
+                        .and_then(Value::as_bool)
+                        .unwrap_or(false),
+                })
+            })
+            .collect()
+    }
+
+    fn read_textures(&self) -> Result<Vec<Texture>, Error> {
+        let samplers = self.array("samplers");
+
+        self.array("textures")
+            .iter()
+            .enumerate()
+            .map(|(i, texture)| {
+                let context = format!("textures[{}]", i);
+                let null = Value::Null;
+                let sampler = match self.index(texture, "sampler", samplers.len(), &context)? {
+                    Some(index) => &samplers[index],
+                    None => &null,
+                };
+                let filter = |key: &str| {
+                    sampler
+                        .get(key)
+                        .and_then(Value::as_usize)
+                        .map(|f| f as GLenum)
+                };
+
+                Ok(Texture {
+                    image: self.index(texture, "source", self.array("images").len(), &context)?,
+                    mag_filter: filter("magFilter"),
+                    min_filter: filter("minFilter"),
+                    wrap_s: filter("wrapS").unwrap_or(gl::REPEAT),
+                    wrap_t: filter("wrapT").unwrap_or(gl::REPEAT),
+                })
+            })
+            .collect()
+    }
+
+    fn read_images(&self) -> Result<Vec<Image>, Error> {
+        self.array("images")
+            .iter()
+            .enumerate()
+            .map(|(i, image)| {
+                let context = format!("images[{}]", i);
+                match image.get("uri").and_then(Value::as_str) {
+                    Some(uri) if uri.starts_with("data:") => {
+                        let (mime_type, data) = self.data_uri(uri, &context)?;
+                        Ok(Image::Embedded { mime_type, data })
+                    }
+                    Some(uri) => Ok(Image::Resource(resources::sibling_name(
+                        self.name,
+                        &percent_decode(uri),
+                    ))),
+                    None => {
+                        let view = self.required_usize(image, "bufferView", &context)?;
+                        let mime_type = optional_string(image, "mimeType")
+                            .ok_or_else(|| self.error(format!("{} has no mimeType", context)))?;
+                        Ok(Image::Embedded {
+                            mime_type,
+                            data: self.buffer_view(view, &context)?.0.to_vec(),
+                        })
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn read_animations(&self) -> Result<Vec<Animation>, Error> {
+        let accessors = self.array("accessors").len();
+        let nodes = self.array("nodes").len();
+
+        self.array("animations")
+            .iter()
+            .enumerate()
+            .map(|(i, animation)| {
+                let samplers = array(animation, "samplers")
+                    .iter()
+                    .enumerate()
+                    .map(|(j, sampler)| {
+                        let context = format!("animations[{}].samplers[{}]", i, j);
+                        let accessor = |key: &str| {
+                            self.index(sampler, key, accessors, &context)?
+                                .ok_or_else(|| self.error(format!("{} has no {}", context, key)))
+                        };
+                        let input = self.read_floats(accessor("input")?, 1, &context)?;
+                        let (output, _) = self.read_accessor(accessor("output")?, false)?;
+                        let interpolation =
+                            match sampler.get("interpolation").and_then(Value::as_str) {
+                                None | Some("LINEAR") => Interpolation::Linear,
+                                Some("STEP") => Interpolation::Step,
+                                Some("CUBICSPLINE") => Interpolation::CubicSpline,
+                                Some(other) => {
+                                    return Err(self.error(format!(
+                                        "{} has unknown interpolation {}",
+                                        context, other
+                                    )))
+                                }
+                            };
+
+                        Ok(AnimationSampler {
+                            input,
+                            output: output.into_iter().map(|v| v as f32).collect(),
+                            interpolation,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                let channels = array(animation, "channels")
+                    .iter()
+                    .enumerate()
+                    .map(|(j, channel)| {
+                        let context = format!("animations[{}].channels[{}]", i, j);
+                        let target = channel
+                            .get("target")
+                            .ok_or_else(|| self.error(format!("{} has no target", context)))?;
+                        let path = match target.get("path").and_then(Value::as_str) {
+                            Some("translation") => AnimationPath::Translation,
+                            Some("rotation") => AnimationPath::Rotation,
+                            Some("scale") => AnimationPath::Scale,
+                            Some("weights") => AnimationPath::Weights,
+                            _ => {
+                                return Err(
+                                    self.error(format!("{} has an unknown target path", context))
+                                )
+                            }
+                        };
+
+                        Ok(Channel {
+                            sampler: self
+                                .index(channel, "sampler", samplers.len(), &context)?
+                                .ok_or_else(|| self.error(format!("{} has no sampler", context)))?,
+                            node: self.index(target, "node", nodes, &context)?,
+                            path,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                Ok(Animation {
+                    name: optional_string(animation, "name"),
+                    channels,
+                    samplers,
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that skinned primitives only use joints their skins have.
+    fn check_references(&self, gltf: &Gltf) -> Result<(), Error> {
+        for (i, node) in gltf.nodes.iter().enumerate() {
+            let (mesh, skin) = match (node.mesh, node.skin) {
+                (Some(mesh), Some(skin)) => (&gltf.meshes[mesh], &gltf.skins[skin]),
+                _ => continue,
+            };
+
+            let max_joint = mesh
+                .primitives
+                .iter()
+                .flat_map(|p| p.joints.iter().flatten())
+                .max();
+            if let Some(&joint) = max_joint.filter(|&&j| j as usize >= skin.joints.len()) {
+                return Err(self.error(format!(
+                    "nodes[{}] uses joint {}, but its skin has {} joints",
+                    i,
+                    joint,
+                    skin.joints.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn array<'v>(value: &'v Value, key: &str) -> &'v [Value] {
+    value.get(key).and_then(Value::as_array).unwrap_or(&[])
+}
+
+fn optional_string(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(String::from)
+}
+
+fn component_size(component_type: usize) -> Option<usize> {
+    match component_type as GLenum {
+        gl::BYTE | gl::UNSIGNED_BYTE => Some(1),
+        gl::SHORT | gl::UNSIGNED_SHORT => Some(2),
+        gl::UNSIGNED_INT | gl::FLOAT => Some(4),
+        _ => None,
+    }
+}
+
+fn read_component(bytes: &[u8], component_type: usize, normalized: bool) -> f64 {
+    match component_type as GLenum {
+        gl::BYTE => {
+            let v = bytes[0] as i8 as f64;
+            if normalized {
+                (v / 127.0).max(-1.0)
+            } else {
+                v
+            }
+        }
+        gl::UNSIGNED_BYTE => {
+            let v = bytes[0] as f64;
+            if normalized {
+                v / 255.0
+            } else {
+                v
+            }
+        }
+        gl::SHORT => {
+            let v = i16::from_le_bytes([bytes[0], bytes[1]]) as f64;
+            if normalized {
+                (v / 32767.0).max(-1.0)
+            } else {
+                v
+            }
+        }
+        gl::UNSIGNED_SHORT => {
+            let v = u16::from_le_bytes([bytes[0], bytes[1]]) as f64;
+            if normalized {
+                v / 65535.0
+            } else {
+                v
+            }
+        }
+        gl::UNSIGNED_INT => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+    }
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+
+    for c in text
+        .bytes()
+        .filter(|&c| c != b'=' && !c.is_ascii_whitespace())
+    {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | value as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            data.push((bits >> bit_count) as u8);
+        }
+    }
+
+    Some(data)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_files(name: &str) -> Result<Vec<u8>, Error> {
+        Err(Error::ModelImport {
+            name: String::from(name),
+            message: String::from("no files in this test"),
+        })
+    }
+
+    fn floats(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|v| v.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    fn matrix_floats(m: &Mat4) -> Vec<u8> {
+        let values: Vec<f32> = (0..16).map(|i| m.cols[i / 4][i % 4]).collect();
+        floats(&values)
+    }
+
+    /// A triangle's positions at 0 and its `u16` indices at 36, padded to 44 bytes.
+    fn triangle_buffer() -> Vec<u8> {
+        let mut bin = floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        bin.extend([0u16, 1, 2].iter().flat_map(|i| i.to_le_bytes().to_vec()));
+        bin.extend(&[0, 0]);
+        bin
+    }
+
+    const TRIANGLE_VIEWS: &str = r#"
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+            { "buffer": 0, "byteOffset": 36, "byteLength": 6 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "meshes": [
+            { "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1 }] }
+        ]"#;
+
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        while !json.len().is_multiple_of(4) {
+            json.push(b' ');
+        }
+
+        let mut glb = b"glTF".to_vec();
+        glb.extend(&2u32.to_le_bytes());
+        glb.extend(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend(&(json.len() as u32).to_le_bytes());
+        glb.extend(&GLB_CHUNK_JSON.to_le_bytes());
+        glb.extend(&json);
+        glb.extend(&(bin.len() as u32).to_le_bytes());
+        glb.extend(&GLB_CHUNK_BIN.to_le_bytes());
+        glb.extend(bin);
+        glb
+    }
+
+    #[test]
+    fn reads_glb_meshes_and_hierarchy() {
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [
+                    {{ "name": "root", "children": [1], "translation": [1, 0, 0] }},
+                    {{ "mesh": 0, "translation": [0, 2, 0], "scale": [2, 2, 2] }}
+                ],
+                "buffers": [{{ "byteLength": 44 }}],
+                {}
+            }}"#,
+            TRIANGLE_VIEWS
+        );
+
+        let gltf = parse("triangle.glb", &glb(&json, &triangle_buffer()), no_files).unwrap();
+
+        assert_eq!(Some(0), gltf.scene);
+        assert_eq!(vec![0], gltf.scenes[0].nodes);
+        assert_eq!(Some(String::from("root")), gltf.nodes[0].name);
+
+        let primitive = &gltf.meshes[0].primitives[0];
+        assert_eq!(Topology::Triangles, primitive.topology);
+        assert_eq!(vec![0, 1, 2], primitive.data.indices);
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), primitive.data.vertices[1].pos);
+        // Normals are generated when the file has none.
+        assert_eq!(Vec3::new(0.0, 0.0, 1.0), primitive.data.vertices[0].normal);
+
+        let world = gltf.world_transforms();
+        let origin = world[1] * Vec4::new(0.0, 0.0, 0.0, 1.0);
+        let corner = world[1] * Vec4::new(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(Vec4::new(1.0, 2.0, 0.0, 1.0), origin);
+        assert_eq!(Vec4::new(3.0, 2.0, 0.0, 1.0), corner);
+    }
+
+    #[test]
+    fn loads_external_buffers_beside_the_document() {
+        let json = format!(
+            r#"{{
+                "buffers": [{{ "byteLength": 44, "uri": "tri%20angle.bin" }}],
+                {}
+            }}"#,
+            TRIANGLE_VIEWS
+        );
+        let mut requested = Vec::new();
+
+        let gltf = parse("models/triangle.gltf", json.as_bytes(), |file: &str| {
+            requested.push(String::from(file));
+            Ok(triangle_buffer())
+        })
+        .unwrap();
+
+        assert_eq!(vec![String::from("models/tri angle.bin")], requested);
+        assert_eq!(3, gltf.meshes[0].primitives[0].data.vertices.len());
+    }
+
+    #[test]
+    fn decodes_data_uris() {
+        // "AAAAAA==" is four zero bytes.
+        let json = r#"{
+            "buffers": [{ "byteLength": 4, "uri": "data:application/octet-stream;base64,AAAAAA==" }],
+            "images": [{ "uri": "data:image/png;base64,aGVsbG8=" }, { "uri": "textures/wood.png" }]
+        }"#;
+
+        let gltf = parse("models/box.gltf", json.as_bytes(), no_files).unwrap();
+
+        assert_eq!(
+            vec![
+                Image::Embedded {
+                    mime_type: String::from("image/png"),
+                    data: b"hello".to_vec(),
+                },
+                Image::Resource(String::from("models/textures/wood.png")),
+            ],
+            gltf.images
+        );
+    }
+
+    #[test]
+    fn reads_skins_and_animations() {
+        let mut bin = triangle_buffer();
+        // Joints as u8 at 44, weights at 56, inverse bind matrices at 104, keyframes at 232.
+        bin.extend(&[0, 1, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0]);
+        bin.extend(floats(&[
+            1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.5, 0.5, 0.0, 0.0,
+        ]));
+        let mut inverse = Mat4::identity();
+        inverse.cols[3] = Vec4::new(0.0, -2.0, 0.0, 1.0);
+        bin.extend(matrix_floats(&Mat4::identity()));
+        bin.extend(matrix_floats(&inverse));
+        bin.extend(floats(&[0.0, 1.5]));
+        bin.extend(floats(&[0.0, 0.0, 0.0, 0.0, 3.0, 0.0]));
+
+        let json = r#"{
+            "nodes": [
+                { "children": [1, 2] },
+                { "translation": [0, 2, 0] },
+                { "mesh": 0, "skin": 0 }
+            ],
+            "skins": [{ "joints": [0, 1], "inverseBindMatrices": 4, "skeleton": 0 }],
+            "animations": [{
+                "name": "lift",
+                "samplers": [{ "input": 5, "output": 6, "interpolation": "STEP" }],
+                "channels": [{ "sampler": 0, "target": { "node": 1, "path": "translation" } }]
+            }],
+            "buffers": [{ "byteLength": 264 }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 36 },
+                { "buffer": 0, "byteOffset": 36, "byteLength": 6 },
+                { "buffer": 0, "byteOffset": 44, "byteLength": 220 }
+            ],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+                { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" },
+                { "bufferView": 2, "componentType": 5121, "count": 3, "type": "VEC4" },
+                { "bufferView": 2, "byteOffset": 12, "componentType": 5126, "count": 3, "type": "VEC4" },
+                { "bufferView": 2, "byteOffset": 60, "componentType": 5126, "count": 2, "type": "MAT4" },
+                { "bufferView": 2, "byteOffset": 188, "componentType": 5126, "count": 2, "type": "SCALAR" },
+                { "bufferView": 2, "byteOffset": 196, "componentType": 5126, "count": 2, "type": "VEC3" }
+            ],
+            "meshes": [{
+                "primitives": [{
+                    "attributes": { "POSITION": 0, "JOINTS_0": 2, "WEIGHTS_0": 3 },
+                    "indices": 1
+                }]
+            }]
+        }"#;
+
+        let gltf = parse("skinned.glb", &glb(json, &bin), no_files).unwrap();
+
+        let primitive = &gltf.meshes[0].primitives[0];
+        assert_eq!(
+            vec![[0, 1, 0, 0], [1, 0, 0, 0], [1, 1, 0, 0]],
+            primitive.joints
+        );
+        assert_eq!(Vec4::new(0.5, 0.5, 0.0, 0.0), primitive.weights[2]);
+
+        let skin = &gltf.skins[0];
+        assert_eq!(vec![0, 1], skin.joints);
+        assert_eq!(Some(0), skin.skeleton);
+        assert_eq!(vec![Mat4::identity(), inverse], skin.inverse_bind_matrices);
+
+        let animation = &gltf.animations[0];
+        assert_eq!(1.5, animation.duration());
+        assert_eq!(
+            Channel {
+                sampler: 0,
+                node: Some(1),
+                path: AnimationPath::Translation,
+            },
+            animation.channels[0]
+        );
+        assert_eq!(Interpolation::Step, animation.samplers[0].interpolation);
+        assert_eq!(
+            vec![0.0, 0.0, 0.0, 0.0, 3.0, 0.0],
+            animation.samplers[0].output
+        );
+    }
+
+    #[test]
+    fn reads_pbr_materials_and_samplers() {
+        let json = r#"{
+            "materials": [{
+                "name": "gold",
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [1, 0.8, 0.2, 1],
+                    "baseColorTexture": { "index": 0, "texCoord": 1 },
+                    "roughnessFactor": 0.25
+                },
+                "normalTexture": { "index": 0, "scale": 0.5 },
+                "alphaMode": "MASK",
+                "doubleSided": true
+            }],
+            "textures": [{ "source": 0, "sampler": 0 }],
+            "samplers": [{ "magFilter": 9728, "wrapS": 33071 }],
+            "images": [{ "uri": "gold.png" }]
+        }"#;
+
+        let gltf = parse("gold.gltf", json.as_bytes(), no_files).unwrap();
+
+        let material = &gltf.materials[0];
+        assert_eq!(Vec4::new(1.0, 0.8, 0.2, 1.0), material.base_color);
+        assert_eq!(
+            Some(TextureRef {
+                texture: 0,
+                tex_coord: 1,
+            }),
+            material.base_color_texture
+        );
+        assert_eq!(1.0, material.metallic);
+        assert_eq!(0.25, material.roughness);
+        assert_eq!(0.5, material.normal_scale);
+        assert_eq!(AlphaMode::Mask, material.alpha_mode);
+        assert!(material.double_sided);
+
+        let texture = &gltf.textures[0];
+        assert_eq!(Some(gl::NEAREST), texture.mag_filter);
+        assert_eq!(None, texture.min_filter);
+        assert_eq!(
+            (gl::CLAMP_TO_EDGE, gl::REPEAT),
+            (texture.wrap_s, texture.wrap_t)
+        );
+    }
+
+    #[test]
+    fn applies_sparse_accessors() {
+        let mut bin = floats(&[1.0, 2.0, 3.0, 4.0]);
+        bin.extend(&[3, 1, 0, 0]);
+        bin.extend(floats(&[40.0, 20.0]));
+
+        let json = r#"{
+            "buffers": [{ "byteLength": 28 }],
+            "bufferViews": [
+                { "buffer": 0, "byteOffset": 0, "byteLength": 16 },
+                { "buffer": 0, "byteOffset": 16, "byteLength": 12 }
+            ],
+            "accessors": [{
+                "bufferView": 0, "componentType": 5126, "count": 4, "type": "SCALAR",
+                "sparse": {
+                    "count": 2,
+                    "indices": { "bufferView": 1, "componentType": 5121 },
+                    "values": { "bufferView": 1, "byteOffset": 4 }
+                }
+            }]
+        }"#;
+        let document = Document::new(
+            "sparse.gltf",
+            json::parse(json).unwrap(),
+            Some(bin),
+            no_files,
+        )
+        .unwrap();
+
+        let (values, components) = document.read_accessor(0, false).unwrap();
+
+        assert_eq!(1, components);
+        assert_eq!(vec![1.0, 20.0, 3.0, 40.0], values);
+    }
+
+    #[test]
+    fn rejects_oversized_accessors() {
+        let json = r#"{
+            "buffers": [{ "byteLength": 16 }],
+            "bufferViews": [{ "buffer": 0, "byteLength": 16, "byteStride": 4 }],
+            "accessors": [
+                { "bufferView": 0, "componentType": 5126, "count": 4611686018427387904, "type": "SCALAR" },
+                { "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3" },
+                { "componentType": 5126, "count": 1000000000000000, "type": "VEC3" }
+            ]
+        }"#;
+        let document = Document::new(
+            "huge.gltf",
+            json::parse(json).unwrap(),
+            Some(floats(&[0.0; 4])),
+            no_files,
+        )
+        .unwrap();
+
+        for index in 0..3 {
+            match document.read_accessor(index, false) {
+                Err(Error::ModelImport { .. }) => {}
+                other => panic!("expected an import error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_bad_documents() {
+        match parse("broken.gltf", b"{\n  \"nodes\": [\n}", no_files) {
+            Err(Error::ModelParse { line, .. }) => assert_eq!(3, line),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+
+        let out_of_range = br#"{ "nodes": [{ "children": [4] }] }"#;
+        match parse("broken.gltf", out_of_range, no_files) {
+            Err(Error::ModelImport { message, .. }) => {
+                assert!(message.contains("nodes[0].children"))
+            }
+            other => panic!("expected an import error, got {:?}", other.map(|_| ())),
+        }
+
+        let cycle = br#"{ "nodes": [{ "children": [1] }, { "children": [0] }] }"#;
+        assert!(parse("cycle.gltf", cycle, no_files).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+//! Golden-image tests: each scene is rendered offscreen and compared with a reference PNG in
+//! `tests/golden/`.
+//!
+//! They need a GL context, so the test is ignored by default. Run it with
+//! `cargo test -- --include-ignored`, under `xvfb-run -a` on a machine without a display.
+//! Without a context the test fails. The references should match Mesa's llvmpipe, which is what
+//! CI renders with.
+//! `GOLDEN_UPDATE=1` rewrites the references from the current output instead of comparing.
+
+use super::*;
+use crate::resources::Resources;
+use crate::utils::{Mat4, Vec3, Vec4};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Width and height every scene is rendered at.
+const SIZE: u32 = 64;
+/// Largest difference per channel that still counts as a match, to absorb rounding
+/// differences between drivers.
+const TOLERANCE: u8 = 2;
+const CLEAR: ClearValues = ClearValues {
+    color: Vec4 {
+        x: 0.15,
+        y: 0.0,
+        z: 0.5,
+        w: 1.0,
+    },
+    depth: 1.0,
+    stencil: 0,
+};
+
+type Draw = Box<dyn FnMut(u32) -> Result<(), Error>>;
+
+struct Scene {
+    name: &'static str,
+    /// Frames to render; only the last one is compared.
+    frames: u32,
+    /// Loads the scene and returns a function drawing frame `n`.
+    build: fn(&Resources) -> Result<Draw, Error>,
+}
+
+const SCENES: &[Scene] = &[
+    Scene {
+        name: "clear",
+        frames: 1,
+        build: |_| Ok(Box::new(|_| Ok(()))),
+    },
+    Scene {
+        name: "triangle",
+        frames: 1,
+        build: |res| {
+            let triangle = Triangle::new(res)?;
+            Ok(Box::new(move |_| triangle.render(&Mat4::identity())))
+        },
+    },
+    Scene {
+        name: "quad",
+        frames: 1,
+        build: |res| {
+            let quad = Quad::new(res)?;
+            Ok(Box::new(move |_| quad.render(&Mat4::identity())))
+        },
+    },
+    Scene {
+        name: "textured_quad",
+        frames: 1,
+        build: |res| {
+            // Every texel differs, so flipped or mirrored sampling shows up.
+            let data = (0..4)
+                .flat_map(|y| (0..4).flat_map(move |x| vec![x * 85, y * 85, 255]))
+                .collect();
+            let image = Image::new(4, 4, PixelFormat::Rgb8, data);
+            let texture = Texture2D::from_image(&image, &TextureOptions::nearest());
+            let quad = Quad::textured(res, texture)?;
+            Ok(Box::new(move |_| quad.render(&Mat4::identity())))
+        },
+    },
+    Scene {
+        name: "updated_indices",
+        frames: 1,
+        build: |res| {
+            // Starts out with a single byte index, so a stale count or type draws nothing. The
+            // first update grows the storage and the second is written into it.
+            let triangle = IndexedTriangle::new(res)?;
+            let ibo = ElementArrayBuffer::new();
+            ibo.bind();
+            ibo.buffer_dynamic_indices(&[0u8]);
+            ibo.update_indices(&[0u32; 4]);
+            ibo.update_indices(&[0u16, 0, 0]);
+            ibo.sub_indices(1, &[1u16, 2]);
+            Ok(Box::new(move |_| {
+                triangle.bind()?;
+                ibo.draw_elements(gl::TRIANGLES);
+                Ok(())
+            }))
+        },
+    },
+    Scene {
+        name: "ring_indices",
+        frames: 3,
+        build: |res| {
+            let triangle = IndexedTriangle::new(res)?;
+            let mut ring = IndexRingBuffer::new(64, 2).expect("no glBufferStorage");
+            Ok(Box::new(move |_| {
+                triangle.bind()?;
+                ring.begin_frame();
+                // Misaligns the indices within the region, as pushing other data first would.
+                ring.push(&[0u8]).unwrap();
+                let offset = ring.push(&[0u16, 1, 2]).unwrap();
+                ring.buffer()
+                    .draw_elements_at(gl::TRIANGLES, offset, 3, u16::INDEX_TYPE);
+                ring.end_frame();
+                Ok(())
+            }))
+        },
+    },
+    Scene {
+        name: "moving_quad",
+        frames: 4,
+        build: |res| {
+            let quad = Quad::new(res)?;
+            Ok(Box::new(move |frame| {
+                let offset = Vec3::new(frame as f32 * 0.125, 0.0, 0.0);
+                quad.render(&Mat4::from_translation(offset))
+            }))
+        },
+    },
+];
+
+/// The triangle scene's program and vertices, for drawing with an index buffer.
+struct IndexedTriangle {
+    program: Program,
+    vao: VertexArray,
+    _vbo: ArrayBuffer,
+}
+
+impl IndexedTriangle {
+    /// Leaves the VAO bound, so the index buffer bound next is attached to it.
+    fn new(res: &Resources) -> Result<IndexedTriangle, Error> {
+        let vertices = [
+            Vertex::new((-0.5, -0.5, 0.0).into(), (1.0, 0.0, 0.0).into()),
+            Vertex::new((0.5, -0.5, 0.0).into(), (0.0, 1.0, 0.0).into()),
+            Vertex::new((0.0, 0.5, 0.0).into(), (0.0, 0.0, 1.0).into()),
+        ];
+        let program = Program::from_res(res, "shaders/triangle")?;
+        program.check_vertex_attribs(&Vertex::vertex_attribs())?;
+
+        let vao = VertexArray::new();
+        vao.bind();
+        let vbo = ArrayBuffer::new();
+        vbo.bind();
+        vbo.buffer_static_data(&vertices);
+        Vertex::vertex_attrib_pointers();
+
+        Ok(IndexedTriangle {
+            program,
+            vao,
+            _vbo: vbo,
+        })
+    }
+
+    fn bind(&self) -> Result<(), Error> {
+        self.program.bind();
+        self.program.set_mat4("ViewProjection", &Mat4::identity())?;
+        self.vao.bind();
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Comparison {
+    /// Pixels with a channel further than the tolerance from the reference.
+    mismatched: usize,
+    max_difference: u8,
+}
+
+/// Compares two RGBA8 images of the same size.
+fn compare(actual: &Image, expected: &Image, tolerance: u8) -> Comparison {
+    let mut comparison = Comparison {
+        mismatched: 0,
+        max_difference: 0,
+    };
+    for (a, e) in actual.data().chunks(4).zip(expected.data().chunks(4)) {
+        let difference = a
+            .iter()
+            .zip(e)
+            .map(|(&a, &e)| (i16::from(a) - i16::from(e)).unsigned_abs() as u8)
+            .max()
+            .unwrap();
+        comparison.max_difference = comparison.max_difference.max(difference);
+        if difference > tolerance {
+            comparison.mismatched += 1;
+        }
+    }
+    comparison
+}
+
+/// The reference dimmed to grey, with mismatched pixels in red.
+fn diff_image(actual: &Image, expected: &Image, tolerance: u8) -> Image {
+    let data = actual
+        .data()
+        .chunks(4)
+        .zip(expected.data().chunks(4))
+        .flat_map(|(a, e)| {
+            let mismatched = a.iter().zip(e).any(|(&a, &e)| {
+                (i16::from(a) - i16::from(e)).unsigned_abs() > u16::from(tolerance)
+            });
+            if mismatched {
+                vec![255, 0, 0, 255]
+            } else {
+                let grey = ((u16::from(e[0]) + u16::from(e[1]) + u16::from(e[2])) / 12) as u8;
+                vec![grey, grey, grey, 255]
+            }
+        })
+        .collect();
+    Image::new(actual.width(), actual.height(), PixelFormat::Rgba8, data)
+}
+
+fn manifest_path(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(path)
+}
+
+fn render(window: &Window, res: &Resources, scene: &Scene) -> Result<Image, Error> {
+    let options = TextureOptions {
+        internal_format: Some(InternalFormat::Rgba8),
+        ..TextureOptions::nearest()
+    };
+    let framebuffer = Framebuffer::with_color_texture(SIZE, SIZE, &options, None)?;
+    let mut draw = (scene.build)(res)?;
+
+    framebuffer.bind_with_viewport();
+    for frame in 0..scene.frames {
+        window.clear(BufferMask::COLOR, &CLEAR);
+        draw(frame)?;
+    }
+    let image = framebuffer.read_pixels(0);
+    window.bind_framebuffer();
+    Ok(image)
+}
+
+fn save(scene: &Scene, image: &Image, path: &Path) -> Result<(), String> {
+    image
+        .save_png(path)
+        .map_err(|e| format!("{}: failed to write {}: {}", scene.name, path.display(), e))
+}
+
+/// Renders `scene` and checks it against its reference, describing what went wrong if not.
+fn check(window: &Window, res: &Resources, scene: &Scene) -> Result<(), String> {
+    let actual = render(window, res, scene).map_err(|e| format!("{}: {}", scene.name, e))?;
+    let reference = manifest_path("tests/golden").join(format!("{}.png", scene.name));
+    if env::var_os("GOLDEN_UPDATE").is_some() {
+        fs::create_dir_all(manifest_path("tests/golden"))
+            .map_err(|e| format!("{}: {}", scene.name, e))?;
+        return save(scene, &actual, &reference);
+    }
+
+    let output = manifest_path("target/golden");
+    fs::create_dir_all(&output).map_err(|e| format!("{}: {}", scene.name, e))?;
+    let actual_path = output.join(format!("{}.png", scene.name));
+    save(scene, &actual, &actual_path)?;
+
+    let expected = match fs::read(&reference).map(|data| image::decode(&data)) {
+        Ok(Ok(expected)) => expected,
+        Ok(Err(e)) => return Err(format!("{}: {}: {}", scene.name, reference.display(), e)),
+        Err(_) => {
+            return Err(format!(
+                "{}: no reference at {}; rerun with GOLDEN_UPDATE=1 to accept {}",
+                scene.name,
+                reference.display(),
+                actual_path.display()
+            ))
+        }
+    };
+    if expected.width() != actual.width()
+        || expected.height() != actual.height()
+        || expected.format() != actual.format()
+    {
+        return Err(format!(
+            "{}: the reference is a {}x{} {:?} image, but the output is {}x{} {:?}",
+            scene.name,
+            expected.width(),
+            expected.height(),
+            expected.format(),
+            actual.width(),
+            actual.height(),
+            actual.format()
+        ));
+    }
+
+    let comparison = compare(&actual, &expected, TOLERANCE);
+    if comparison.mismatched == 0 {
+        return Ok(());
+    }
+    let diff_path = output.join(format!("{}.diff.png", scene.name));
+    save(
+        scene,
+        &diff_image(&actual, &expected, TOLERANCE),
+        &diff_path,
+    )?;
+    Err(format!(
+        "{}: {} pixels differ by up to {}; see {} and {}",
+        scene.name,
+        comparison.mismatched,
+        comparison.max_difference,
+        actual_path.display(),
+        diff_path.display()
+    ))
+}
+
+#[test]
+#[ignore = "needs a GL context; run with --include-ignored"]
+fn scenes_match_golden_images() {
+    let window = Window::hidden(SIZE, SIZE).expect(
+        "no GL context could be created; run under xvfb-run on a machine without a display",
+    );
+    let res = Resources::from_path(&manifest_path("assets"));
+
+    let failures: Vec<String> = SCENES
+        .iter()
+        .filter_map(|scene| check(&window, &res, scene).err())
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn comparisons_allow_the_tolerance() {
+    let expected = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![10, 20, 30, 255, 0, 0, 0, 255],
+    );
+    let close = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![12, 19, 30, 255, 0, 0, 0, 253],
+    );
+    let far = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![10, 20, 30, 255, 0, 9, 0, 255],
+    );
+
+    assert_eq!(
+        Comparison {
+            mismatched: 0,
+            max_difference: 2
+        },
+        compare(&close, &expected, 2)
+    );
+    assert_eq!(
+        Comparison {
+            mismatched: 1,
+            max_difference: 9
+        },
+        compare(&far, &expected, 2)
+    );
+}
+
+#[test]
+fn diff_images_mark_mismatches_in_red() {
+    let expected = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![120, 120, 120, 255, 0, 0, 0, 255],
+    );
+    let actual = Image::new(
+        2,
+        1,
+        PixelFormat::Rgba8,
+        vec![120, 120, 120, 255, 0, 90, 0, 255],
+    );
+
+    let diff = diff_image(&actual, &expected, 2);
+
+    assert_eq!(&[30, 30, 30, 255], diff.pixel(0, 0));
+    assert_eq!(&[255, 0, 0, 255], diff.pixel(1, 0));
+}
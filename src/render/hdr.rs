@@ -0,0 +1,277 @@
+//! Radiance RGBE (`.hdr`) decoding, flat or run-length encoded.
+
+use super::image::{image_size, Image, PixelFormat};
+
+pub fn is_hdr(data: &[u8]) -> bool {
+    data.starts_with(b"#?RADIANCE") || data.starts_with(b"#?RGBE")
+}
+
+/// Decodes a Radiance file to `Rgb32F` pixels in linear light.
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    if !is_hdr(data) {
+        return Err(String::from("not a Radiance HDR file"));
+    }
+
+    let mut offset = 0;
+    let mut next_line = || -> Result<&[u8], String> {
+        let rest = &data[offset..];
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| String::from("header is truncated"))?;
+        offset += end + 1;
+        Ok(&rest[..end])
+    };
+
+    // Header variables end at the first blank line.
+    next_line()?;
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(format!(
+                "unsupported pixel format {}",
+                String::from_utf8_lossy(&line[7..])
+            ));
+        }
+    }
+
+    let resolution = String::from_utf8_lossy(next_line()?).into_owned();
+    let (top_down, height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        [y, height, "+X", width] if y == "-Y" || y == "+Y" => (
+            y == "-Y",
+            height
+                .parse::<u32>()
+                .map_err(|_| String::from("invalid height"))?,
+            width
+                .parse::<u32>()
+                .map_err(|_| String::from("invalid width"))?,
+        ),
+        _ => return Err(format!("unsupported resolution line {}", resolution)),
+    };
+    if width == 0 || height == 0 {
+        return Err(String::from("image has no pixels"));
+    }
+
+    // Every scanline takes at least 4 bytes, and repeats let a few bytes expand to many
+    // pixels, so only the smaller of the image and the input is reserved up front.
+    let size = image_size(width as usize, height as usize, 12)?;
+    let remaining = data.len() - offset;
+    if remaining / 4 < height as usize {
+        return Err(String::from("pixel data is truncated"));
+    }
+
+    let mut reader = Reader { data, offset };
+    let mut rgbe = Vec::with_capacity((size / 3).min(remaining));
+    for _ in 0..height {
+        reader.scanline(width as usize, &mut rgbe)?;
+    }
+
+    let mut pixels = Vec::with_capacity(rgbe.len() * 3);
+    for pixel in rgbe.chunks(4) {
+        for channel in rgbe_to_rgb(pixel).iter() {
+            pixels.extend_from_slice(&channel.to_ne_bytes());
+        }
+    }
+
+    let mut image = Image::new(width, height, PixelFormat::Rgb32F, pixels);
+    if top_down {
+        image.flip_vertically();
+    }
+    Ok(image)
+}
+
+fn rgbe_to_rgb(pixel: &[u8]) -> [f32; 3] {
+    if pixel[3] == 0 {
+        return [0.0; 3];
+    }
+    let scale = 2f32.powi(i32::from(pixel[3]) - (128 + 8));
+    [
+        f32::from(pixel[0]) * scale,
+        f32::from(pixel[1]) * scale,
+        f32::from(pixel[2]) * scale,
+    ]
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + count)
+            .ok_or_else(|| String::from("pixel data is truncated"))?;
+        self.offset += count;
+        Ok(bytes)
+    }
+
+    /// Appends one scanline of RGBE pixels to `out`.
+    fn scanline(&mut self, width: usize, out: &mut Vec<u8>) -> Result<(), String> {
+        let start = self.bytes(4)?;
+        let new_rle =
+            (8..0x8000).contains(&width) && start[0] == 2 && start[1] == 2 && start[2] & 0x80 == 0;
+        if !new_rle {
+            self.offset -= 4;
+            return self.flat_scanline(width, out);
+        }
+        if (usize::from(start[2]) << 8 | usize::from(start[3])) != width {
+            return Err(String::from("scanline width does not match the image"));
+        }
+
+        // Each channel is run-length encoded separately.
+        let mut channels = vec![0u8; width * 4];
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = self.bytes(1)?[0] as usize;
+                let (run, values) = if count > 128 {
+                    (count - 128, None)
+                } else {
+                    (count, Some(self.bytes(count)?))
+                };
+                if run == 0 || x + run > width {
+                    return Err(String::from("run overflows the scanline"));
+                }
+                match values {
+                    Some(values) => {
+                        for (i, &value) in values.iter().enumerate() {
+                            channels[(x + i) * 4 + channel] = value;
+                        }
+                    }
+                    None => {
+                        let value = self.bytes(1)?[0];
+                        for i in 0..run {
+                            channels[(x + i) * 4 + channel] = value;
+                        }
+                    }
+                }
+                x += run;
+            }
+        }
+        out.extend_from_slice(&channels);
+        Ok(())
+    }
+
+    /// Reads uncompressed pixels, expanding the original format's `(1, 1, 1, n)` repeats.
+    fn flat_scanline(&mut self, width: usize, out: &mut Vec<u8>) -> Result<(), String> {
+        let line_start = out.len();
+        let mut shift: u32 = 0;
+        while out.len() - line_start < width * 4 {
+            let pixel = self.bytes(4)?;
+            if pixel[..3] == [1, 1, 1] {
+                if out.len() == line_start {
+                    return Err(String::from("repeat with no previous pixel"));
+                }
+                // Each further repeat in a row adds 8 more significant bits to the count.
+                if shift > usize::BITS - 8 {
+                    return Err(String::from("too many repeats in a row"));
+                }
+                let count = (pixel[3] as usize) << shift;
+                let previous = out[out.len() - 4..].to_vec();
+                for _ in 0..count.min(width - (out.len() - line_start) / 4) {
+                    out.extend_from_slice(&previous);
+                }
+                shift += 8;
+            } else {
+                out.extend_from_slice(pixel);
+                shift = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [f32; 3] {
+        let bytes = image.pixel(x, y);
+        let channel = |i: usize| {
+            f32::from_ne_bytes([
+                bytes[i * 4],
+                bytes[i * 4 + 1],
+                bytes[i * 4 + 2],
+                bytes[i * 4 + 3],
+            ])
+        };
+        [channel(0), channel(1), channel(2)]
+    }
+
+    #[test]
+    fn decodes_flat_pixels_top_down() {
+        let mut file = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 1\n".to_vec();
+        // 128 * 2^(129 - 136) = 1.0, then 64 * 2^(131 - 136) = 2.0.
+        file.extend_from_slice(&[128, 0, 64, 129, 64, 64, 0, 131]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!(PixelFormat::Rgb32F, image.format());
+        assert_eq!([1.0, 0.0, 0.5], pixel(&image, 0, 1));
+        assert_eq!([2.0, 2.0, 0.0], pixel(&image, 0, 0));
+    }
+
+    #[test]
+    fn decodes_run_length_scanlines() {
+        let mut file = b"#?RGBE\n\n+Y 1 +X 8\n".to_vec();
+        file.extend_from_slice(&[2, 2, 0, 8]);
+        // Red: a run of 8. Green: 8 literals. Blue: two runs of 4. Exponent: a run of 8.
+        file.extend_from_slice(&[136, 128]);
+        file.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        file.extend_from_slice(&[132, 0, 132, 128]);
+        file.extend_from_slice(&[136, 136]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!([128.0, 0.0, 0.0], pixel(&image, 0, 0));
+        assert_eq!([128.0, 112.0, 128.0], pixel(&image, 7, 0));
+    }
+
+    #[test]
+    fn expands_old_style_repeats() {
+        let mut file = b"#?RADIANCE\n\n-Y 1 +X 4\n".to_vec();
+        file.extend_from_slice(&[128, 128, 128, 129, 1, 1, 1, 3]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!([1.0; 3], pixel(&image, 3, 0));
+    }
+
+    #[test]
+    fn rejects_endless_repeat_chains() {
+        let mut file = b"#?RADIANCE\n\n-Y 1 +X 4\n".to_vec();
+        file.extend_from_slice(&[128, 128, 128, 129]);
+        for _ in 0..10 {
+            file.extend_from_slice(&[1, 1, 1, 0]);
+        }
+
+        assert!(decode(&file).is_err());
+    }
+
+    #[test]
+    fn rejects_huge_dimensions_before_allocating() {
+        let overflowing = b"#?RADIANCE\n\n-Y 4294967295 +X 4294967295\n";
+        let huge = b"#?RADIANCE\n\n-Y 100000 +X 100000\n";
+
+        assert_eq!(
+            Err(String::from("image is too large")),
+            decode(overflowing).map(|_| ())
+        );
+        assert_eq!(
+            Err(String::from("pixel data is truncated")),
+            decode(huge).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        assert!(decode(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n\0\0\0\0").is_err());
+        assert!(decode(b"#?RADIANCE\n\n-Y 1 +X 1\n\0\0").is_err());
+        assert!(decode(b"#?RADIANCE\n\n+X 1 -Y 1\n\0\0\0\0").is_err());
+    }
+}
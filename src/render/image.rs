@@ -0,0 +1,818 @@
+//! Decoded images, ready to upload to textures.
+//!
+//! Rows are stored bottom row first, which is the order GL expects, so `v = 0` samples the
+//! bottom of the image.
+
+use super::{hdr, png, Error};
+use crate::resources::Resources;
+use crate::utils::Vec4;
+
+use gl::types::*;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Layout of one pixel in an `Image`. 16-bit and float samples are native-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    R16,
+    Rg16,
+    Rgb16,
+    Rgba16,
+    R32F,
+    Rg32F,
+    Rgb32F,
+    Rgba32F,
+}
+
+impl PixelFormat {
+    /// The 8 or 16-bit unsigned format with `channels` channels.
+    pub fn with_channels(channels: usize, sixteen_bit: bool) -> PixelFormat {
+        match (channels, sixteen_bit) {
+            (1, false) => PixelFormat::R8,
+            (2, false) => PixelFormat::Rg8,
+            (3, false) => PixelFormat::Rgb8,
+            (4, false) => PixelFormat::Rgba8,
+            (1, true) => PixelFormat::R16,
+            (2, true) => PixelFormat::Rg16,
+            (3, true) => PixelFormat::Rgb16,
+            (4, true) => PixelFormat::Rgba16,
+            _ => panic!("Pixel formats have 1 to 4 channels, not {}", channels),
+        }
+    }
+
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::R8 | PixelFormat::R16 | PixelFormat::R32F => 1,
+            PixelFormat::Rg8 | PixelFormat::Rg16 | PixelFormat::Rg32F => 2,
+            PixelFormat::Rgb8 | PixelFormat::Rgb16 | PixelFormat::Rgb32F => 3,
+            PixelFormat::Rgba8 | PixelFormat::Rgba16 | PixelFormat::Rgba32F => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> usize {
+        self.channels() * self.bytes_per_channel()
+    }
+
+    pub fn bytes_per_channel(self) -> usize {
+        match self.gl_type() {
+            gl::UNSIGNED_BYTE => 1,
+            gl::UNSIGNED_SHORT => 2,
+            _ => 4,
+        }
+    }
+
+    /// The `format` argument of `glTexImage2D` for this layout.
+    pub fn gl_format(self) -> GLenum {
+        match self.channels() {
+            1 => gl::RED,
+            2 => gl::RG,
+            3 => gl::RGB,
+            _ => gl::RGBA,
+        }
+    }
+
+    /// The `type` argument of `glTexImage2D` for this layout.
+    pub fn gl_type(self) -> GLenum {
+        match self {
+            PixelFormat::R8 | PixelFormat::Rg8 | PixelFormat::Rgb8 | PixelFormat::Rgba8 => {
+                gl::UNSIGNED_BYTE
+            }
+            PixelFormat::R16 | PixelFormat::Rg16 | PixelFormat::Rgb16 | PixelFormat::Rgba16 => {
+                gl::UNSIGNED_SHORT
+            }
+            _ => gl::FLOAT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// Wraps tightly packed pixels, bottom row first.
+    ///
+    /// Panics if `data` is not exactly `width * height` pixels long.
+    pub fn new(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> Image {
+        let expected = width as usize * height as usize * format.bytes_per_pixel();
+        if data.len() != expected {
+            panic!(
+                "A {}x{} {:?} image needs {} bytes, got {}",
+                width,
+                height,
+                format,
+                expected,
+                data.len()
+            );
+        }
+
+        Image {
+            width,
+            height,
+            format,
+            data,
+        }
+    }
+
+    /// Loads a PNG, TGA, BMP or Radiance HDR resource, picking the decoder by its contents.
+    pub fn from_res(res: &Resources, name: &str) -> Result<Image, Error> {
+        let data = load(res, name)?;
+        decode(&data).map_err(|message| Error::ImageDecode {
+            name: String::from(name),
+            message,
+        })
+    }
+
+    /// Loads headerless pixel data, stored top row first like most raw exports.
+    pub fn from_res_raw(
+        res: &Resources,
+        name: &str,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> Result<Image, Error> {
+        let data = load(res, name)?;
+        let expected = width as usize * height as usize * format.bytes_per_pixel();
+        if data.len() != expected {
+            return Err(Error::ImageDecode {
+                name: String::from(name),
+                message: format!(
+                    "expected {} bytes for a {}x{} {:?} image, found {}",
+                    expected,
+                    width,
+                    height,
+                    format,
+                    data.len()
+                ),
+            });
+        }
+
+        let mut image = Image::new(width, height, format, data);
+        image.flip_vertically();
+        Ok(image)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// The bytes of the pixel at `(x, y)`, counting `y` from the bottom row.
+    pub fn pixel(&self, x: u32, y: u32) -> &[u8] {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel ({}, {}) is outside the {}x{} image",
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let size = self.format.bytes_per_pixel();
+        let start = (y as usize * self.width as usize + x as usize) * size;
+        &self.data[start..start + size]
+    }
+
+    /// The pixel at `(x, y)` as floats, with integer formats normalized to `[0, 1]`.
+    ///
+    /// Missing channels read as 0 and missing alpha as 1, like sampling in a shader.
+    pub fn texel(&self, x: u32, y: u32) -> Vec4 {
+        let pixel = self.pixel(x, y);
+        let size = self.format.bytes_per_channel();
+        let channel = |i: usize| -> f32 {
+            let bytes = &pixel[i * size..(i + 1) * size];
+            match size {
+                1 => f32::from(bytes[0]) / 255.0,
+                2 => f32::from(u16::from_ne_bytes([bytes[0], bytes[1]])) / 65535.0,
+                _ => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            }
+        };
+
+        let mut texel = [0.0, 0.0, 0.0, 1.0];
+        for (i, value) in texel.iter_mut().enumerate().take(self.format.channels()) {
+            *value = channel(i);
+        }
+        Vec4::new(texel[0], texel[1], texel[2], texel[3])
+    }
+
+    /// Copies out a `width` x `height` region with its bottom-left corner at `(x, y)`.
+    pub fn region(&self, x: u32, y: u32, width: u32, height: u32) -> Image {
+        assert!(
+            x + width <= self.width && y + height <= self.height,
+            "A {}x{} region at ({}, {}) does not fit in the {}x{} image",
+            width,
+            height,
+            x,
+            y,
+            self.width,
+            self.height
+        );
+        let size = self.format.bytes_per_pixel();
+        let mut data = Vec::with_capacity(width as usize * height as usize * size);
+        for row in y..y + height {
+            let start = (row as usize * self.width as usize + x as usize) * size;
+            data.extend_from_slice(&self.data[start..start + width as usize * size]);
+        }
+        Image::new(width, height, self.format, data)
+    }
+
+    /// Cuts the image into `columns` x `rows` equal tiles in reading order, starting at the
+    /// top left, or `None` if it doesn't divide evenly.
+    pub fn split_grid(&self, columns: u32, rows: u32) -> Option<Vec<Image>> {
+        if columns == 0
+            || rows == 0
+            || !self.width.is_multiple_of(columns)
+            || !self.height.is_multiple_of(rows)
+        {
+            return None;
+        }
+        let (tile_width, tile_height) = (self.width / columns, self.height / rows);
+        let tiles = (0..rows)
+            .rev()
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                self.region(
+                    column * tile_width,
+                    row * tile_height,
+                    tile_width,
+                    tile_height,
+                )
+            })
+            .collect();
+        Some(tiles)
+    }
+
+    /// Encodes the image as a PNG file. Panics for float formats, which PNG can't store.
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(self)
+    }
+
+    /// Writes the image to `path` as a PNG file. Panics for float formats.
+    pub fn save_png(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_png())
+    }
+
+    pub fn flip_vertically(&mut self) {
+        let row = self.width as usize * self.format.bytes_per_pixel();
+        let height = self.height as usize;
+        for y in 0..height / 2 {
+            let (top, bottom) = self.data.split_at_mut((height - 1 - y) * row);
+            top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+        }
+    }
+}
+
+pub(super) fn load(res: &Resources, name: &str) -> Result<Vec<u8>, Error> {
+    res.load_bytes(name).map_err(|e| Error::ResourceLoad {
+        name: String::from(name),
+        inner: e,
+    })
+}
+
+/// Decodes a PNG, BMP, Radiance HDR or TGA file. TGA has no signature, so it is the fallback.
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    if png::is_png(data) {
+        png::decode(data)
+    } else if hdr::is_hdr(data) {
+        hdr::decode(data)
+    } else if data.starts_with(b"BM") {
+        decode_bmp(data)
+    } else {
+        decode_tga(data)
+    }
+}
+
+fn u16_le(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| String::from("file is truncated"))
+}
+
+fn u32_le(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| String::from("file is truncated"))
+}
+
+/// `width * height * bytes`, or an error if that overflows, checked before anything of that
+/// size is allocated.
+pub(super) fn image_size(width: usize, height: usize, bytes: usize) -> Result<usize, String> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(bytes))
+        .ok_or_else(|| String::from("image is too large"))
+}
+
+/// Expands a packed 15/16-bit BGR pixel to RGB(A).
+fn bgr555(pixel: u16, alpha: bool, out: &mut Vec<u8>) {
+    let expand = |v: u16| ((v & 0x1f) * 255 / 31) as u8;
+    out.extend_from_slice(&[expand(pixel >> 10), expand(pixel >> 5), expand(pixel)]);
+    if alpha {
+        out.push(if pixel & 0x8000 != 0 { 255 } else { 0 });
+    }
+}
+
+/// Decodes a Truevision TGA file: color-mapped, true-color or grayscale, optionally RLE.
+pub fn decode_tga(data: &[u8]) -> Result<Image, String> {
+    if data.len() < 18 {
+        return Err(String::from("file is too short for a TGA header"));
+    }
+    let id_length = data[0] as usize;
+    let has_color_map = data[1] == 1;
+    let image_type = data[2];
+    let map_start = u16_le(data, 3)? as usize;
+    let map_length = u16_le(data, 5)? as usize;
+    let map_depth = data[7];
+    let width = u16_le(data, 12)? as u32;
+    let height = u16_le(data, 14)? as u32;
+    let depth = data[16];
+    let descriptor = data[17];
+
+    let rle = image_type & 8 != 0;
+    // True-color and grayscale images may still carry a color map, which is skipped.
+    let color_mapped = image_type & !8 == 1;
+    let (format, pixel_depth) = match (image_type & !8, depth) {
+        (1, 8) if has_color_map => match map_depth {
+            15 | 24 => (PixelFormat::Rgb8, map_depth),
+            16 | 32 => (PixelFormat::Rgba8, map_depth),
+            _ => return Err(format!("unsupported color map depth {}", map_depth)),
+        },
+        (2, 15) | (2, 24) => (PixelFormat::Rgb8, depth),
+        (2, 16) | (2, 32) => (PixelFormat::Rgba8, depth),
+        (3, 8) => (PixelFormat::R8, 8),
+        (3, 16) => (PixelFormat::Rg8, 16),
+        _ => {
+            return Err(format!(
+                "unsupported image type {} at {} bits per pixel",
+                image_type, depth
+            ))
+        }
+    };
+    if width == 0 || height == 0 {
+        return Err(String::from("image has no pixels"));
+    }
+
+    let mut offset = 18 + id_length;
+    let map_bytes = (map_depth as usize).div_ceil(8);
+    let color_map = if has_color_map {
+        let map = data
+            .get(offset..offset + map_length * map_bytes)
+            .ok_or_else(|| String::from("color map is truncated"))?;
+        offset += map.len();
+        map
+    } else {
+        &[]
+    };
+
+    // Read the stored pixels, expanding RLE packets.
+    let stored_bytes = (depth as usize).div_ceil(8);
+    let size = image_size(width as usize, height as usize, stored_bytes)?;
+    let truncated = || String::from("pixel data is truncated");
+    // RLE data grows as packets are read rather than up front, so a header claiming a huge
+    // image cannot make it allocate more than the packets expand to.
+    let mut stored = Vec::new();
+    if rle {
+        while stored.len() < size {
+            let packet = *data.get(offset).ok_or_else(truncated)?;
+            let run = (packet & 0x7f) as usize + 1;
+            offset += 1;
+            if packet & 0x80 != 0 {
+                let pixel = data
+                    .get(offset..offset + stored_bytes)
+                    .ok_or_else(truncated)?;
+                for _ in 0..run {
+                    stored.extend_from_slice(pixel);
+                }
+                offset += stored_bytes;
+            } else {
+                let pixels = data
+                    .get(offset..offset + run * stored_bytes)
+                    .ok_or_else(truncated)?;
+                stored.extend_from_slice(pixels);
+                offset += pixels.len();
+            }
+        }
+        stored.truncate(size);
+    } else {
+        stored.extend_from_slice(data.get(offset..offset + size).ok_or_else(truncated)?);
+    }
+
+    let mut pixels = Vec::with_capacity(image_size(
+        width as usize,
+        height as usize,
+        format.bytes_per_pixel(),
+    )?);
+    for pixel in stored.chunks(stored_bytes) {
+        let pixel = if color_mapped {
+            let index = pixel[0] as usize;
+            let entry = index
+                .checked_sub(map_start)
+                .filter(|&i| i < map_length)
+                .ok_or_else(|| format!("color index {} is outside the color map", index))?;
+            &color_map[entry * map_bytes..(entry + 1) * map_bytes]
+        } else {
+            pixel
+        };
+
+        match (format, pixel_depth) {
+            (_, 15) | (_, 16) if format != PixelFormat::Rg8 => bgr555(
+                u16::from_le_bytes([pixel[0], pixel[1]]),
+                pixel_depth == 16,
+                &mut pixels,
+            ),
+            (PixelFormat::Rgb8, _) | (PixelFormat::Rgba8, _) => {
+                pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+                if format == PixelFormat::Rgba8 {
+                    pixels.push(pixel[3]);
+                }
+            }
+            _ => pixels.extend_from_slice(pixel),
+        }
+    }
+
+    let mut image = Image::new(width, height, format, pixels);
+    if descriptor & 0x10 != 0 {
+        mirror_rows(&mut image);
+    }
+    if descriptor & 0x20 != 0 {
+        image.flip_vertically();
+    }
+    Ok(image)
+}
+
+fn mirror_rows(image: &mut Image) {
+    let size = image.format.bytes_per_pixel();
+    let row = image.width as usize * size;
+    for row in image.data.chunks_mut(row) {
+        let width = row.len() / size;
+        for x in 0..width / 2 {
+            let (left, right) = row.split_at_mut((width - 1 - x) * size);
+            left[x * size..(x + 1) * size].swap_with_slice(&mut right[..size]);
+        }
+    }
+}
+
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+/// Decodes an uncompressed Windows BMP: paletted 1/4/8-bit or 16/24/32-bit with optional
+/// bit field masks.
+pub fn decode_bmp(data: &[u8]) -> Result<Image, String> {
+    let pixel_offset = u32_le(data, 10)? as usize;
+    let header_size = u32_le(data, 14)? as usize;
+    if header_size < 40 {
+        return Err(String::from("OS/2 bitmaps are not supported"));
+    }
+    let width = u32_le(data, 18)? as i32;
+    let height = u32_le(data, 22)? as i32;
+    let depth = u16_le(data, 28)?;
+    let compression = u32_le(data, 30)?;
+    let palette_size = u32_le(data, 46)? as usize;
+
+    if width <= 0 || height == 0 {
+        return Err(String::from("image has no pixels"));
+    }
+    let (width, top_down) = (width as u32, height < 0);
+    let height = height.unsigned_abs();
+
+    let masks = match (compression, depth) {
+        (BI_RGB, 16) => Some([0x7c00, 0x03e0, 0x001f, 0]),
+        (BI_RGB, 32) => Some([0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0]),
+        (BI_RGB, _) => None,
+        (BI_BITFIELDS, 16)
+        | (BI_BITFIELDS, 32)
+        | (BI_ALPHABITFIELDS, 16)
+        | (BI_ALPHABITFIELDS, 32) => {
+            // Masks follow a 40-byte header, or are part of V4 and V5 headers.
+            let alpha = if compression == BI_ALPHABITFIELDS || header_size >= 56 {
+                u32_le(data, 14 + 52)?
+            } else {
+                0
+            };
+            Some([
+                u32_le(data, 14 + 40)?,
+                u32_le(data, 14 + 44)?,
+                u32_le(data, 14 + 48)?,
+                alpha,
+            ])
+        }
+        _ => {
+            return Err(format!(
+                "unsupported compression {} at {} bits per pixel",
+                compression, depth
+            ))
+        }
+    };
+
+    let palette: Vec<[u8; 3]> = if depth <= 8 {
+        let count = if palette_size == 0 {
+            1 << depth
+        } else {
+            palette_size
+        };
+        let start = 14 + header_size;
+        data.get(start..start + count * 4)
+            .ok_or_else(|| String::from("palette is truncated"))?
+            .chunks(4)
+            .map(|c| [c[2], c[1], c[0]])
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let format = match masks {
+        Some([_, _, _, alpha]) if alpha != 0 => PixelFormat::Rgba8,
+        _ => PixelFormat::Rgb8,
+    };
+    let row_bytes = image_size(width as usize, depth as usize, 1)?.div_ceil(32) * 4;
+    let stored_size = image_size(row_bytes, height as usize, 1)?;
+    if pixel_offset
+        .checked_add(stored_size)
+        .is_none_or(|end| end > data.len())
+    {
+        return Err(String::from("pixel data is truncated"));
+    }
+    let mut pixels = Vec::with_capacity(image_size(
+        width as usize,
+        height as usize,
+        format.bytes_per_pixel(),
+    )?);
+
+    for y in 0..height as usize {
+        let start = pixel_offset + y * row_bytes;
+        let row = &data[start..start + row_bytes];
+
+        for x in 0..width as usize {
+            match (depth, masks) {
+                (1, _) | (2, _) | (4, _) | (8, _) => {
+                    let bit = x * depth as usize;
+                    let shift = 8 - depth as usize - bit % 8;
+                    let index = ((row[bit / 8] >> shift) & ((1u16 << depth) - 1) as u8) as usize;
+                    let color = palette
+                        .get(index)
+                        .ok_or_else(|| format!("color index {} is outside the palette", index))?;
+                    pixels.extend_from_slice(color);
+                }
+                (24, _) => pixels.extend_from_slice(&[row[x * 3 + 2], row[x * 3 + 1], row[x * 3]]),
+                (16, Some(masks)) | (32, Some(masks)) => {
+                    let value = if depth == 16 {
+                        u32::from(u16::from_le_bytes([row[x * 2], row[x * 2 + 1]]))
+                    } else {
+                        u32::from_le_bytes([
+                            row[x * 4],
+                            row[x * 4 + 1],
+                            row[x * 4 + 2],
+                            row[x * 4 + 3],
+                        ])
+                    };
+                    let channels = if format == PixelFormat::Rgba8 { 4 } else { 3 };
+                    pixels.extend(
+                        masks[..channels]
+                            .iter()
+                            .map(|&mask| extract_masked(value, mask)),
+                    );
+                }
+                _ => return Err(format!("unsupported bit depth {}", depth)),
+            }
+        }
+    }
+
+    let mut image = Image::new(width, height, format, pixels);
+    if top_down {
+        image.flip_vertically();
+    }
+    Ok(image)
+}
+
+/// Scales the bits of `value` selected by `mask` to 0-255.
+fn extract_masked(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let bits = (value & mask) >> mask.trailing_zeros();
+    let max = mask >> mask.trailing_zeros();
+    (u64::from(bits) * 255 / u64::from(max)) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flips_rows() {
+        let mut image = Image::new(2, 3, PixelFormat::R8, vec![1, 2, 3, 4, 5, 6]);
+
+        image.flip_vertically();
+
+        assert_eq!(&[5, 6, 3, 4, 1, 2][..], image.data());
+        assert_eq!(&[1], image.pixel(0, 2));
+    }
+
+    #[test]
+    fn splits_sprite_sheets_from_the_top_left() {
+        // Bottom row first: 3 4 / 1 2 as seen.
+        let sheet = Image::new(2, 2, PixelFormat::R8, vec![3, 4, 1, 2]);
+
+        let tiles = sheet.split_grid(2, 2).unwrap();
+
+        let values: Vec<u8> = tiles.iter().map(|t| t.data()[0]).collect();
+        assert_eq!(vec![1, 2, 3, 4], values);
+        assert!(sheet.split_grid(3, 1).is_none());
+    }
+
+    #[test]
+    fn texels_normalize_and_fill_channels() {
+        let image = Image::new(1, 1, PixelFormat::Rg8, vec![255, 51]);
+        let wide = Image::new(1, 1, PixelFormat::R16, 65535u16.to_ne_bytes().to_vec());
+
+        assert_eq!(Vec4::new(1.0, 0.2, 0.0, 1.0), image.texel(0, 0));
+        assert_eq!(Vec4::new(1.0, 0.0, 0.0, 1.0), wide.texel(0, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_mismatched_data() {
+        Image::new(2, 2, PixelFormat::Rgb8, vec![0; 11]);
+    }
+
+    fn tga_header(image_type: u8, width: u16, height: u16, depth: u8, descriptor: u8) -> Vec<u8> {
+        let mut header = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&[depth, descriptor]);
+        header
+    }
+
+    #[test]
+    fn decodes_top_left_tga() {
+        let mut file = tga_header(2, 2, 2, 32, 0x28);
+        // BGRA, top row first because of the descriptor.
+        file.extend_from_slice(&[0, 0, 255, 255, 0, 255, 0, 255, 255, 0, 0, 255, 0, 0, 0, 0]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!(PixelFormat::Rgba8, image.format());
+        assert_eq!(&[255, 0, 0, 255], image.pixel(0, 1));
+        assert_eq!(&[0, 255, 0, 255], image.pixel(1, 1));
+        assert_eq!(&[0, 0, 255, 255], image.pixel(0, 0));
+        assert_eq!(&[0, 0, 0, 0], image.pixel(1, 0));
+    }
+
+    #[test]
+    fn skips_the_color_map_of_true_color_tga() {
+        let mut file = tga_header(2, 1, 1, 32, 0);
+        // A map of two 24-bit entries that must not be used.
+        file[1] = 1;
+        file[5..8].copy_from_slice(&[2, 0, 24]);
+        file.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        file.extend_from_slice(&[0, 0, 255, 128]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!(&[255, 0, 0, 128][..], image.data());
+    }
+
+    #[test]
+    fn decodes_rle_tga() {
+        let mut file = tga_header(10, 3, 2, 24, 0);
+        // A run of four blue pixels, then two raw pixels.
+        file.extend_from_slice(&[0x83, 255, 0, 0, 0x01, 0, 255, 0, 0, 0, 255]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!(
+            &[0, 0, 255, 0, 0, 255, 0, 0, 255, 0, 0, 255, 0, 255, 0, 255, 0, 0][..],
+            image.data()
+        );
+    }
+
+    #[test]
+    fn decodes_grayscale_tga() {
+        let mut file = tga_header(3, 2, 1, 8, 0);
+        file.extend_from_slice(&[10, 20]);
+
+        let image = decode(&file).unwrap();
+
+        assert_eq!(
+            (PixelFormat::R8, &[10, 20][..]),
+            (image.format(), image.data())
+        );
+    }
+
+    #[test]
+    fn rejects_huge_tga_before_allocating() {
+        let file = tga_header(2, 0xffff, 0xffff, 32, 0);
+
+        assert_eq!(
+            Err(String::from("pixel data is truncated")),
+            decode(&file).map(|_| ())
+        );
+    }
+
+    fn bmp(
+        width: i32,
+        height: i32,
+        depth: u16,
+        compression: u32,
+        extra: &[u8],
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let offset = 14 + 40 + extra.len() as u32;
+        let mut file = b"BM".to_vec();
+        file.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+        file.extend_from_slice(&offset.to_le_bytes());
+        file.extend_from_slice(&40u32.to_le_bytes());
+        file.extend_from_slice(&width.to_le_bytes());
+        file.extend_from_slice(&height.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&depth.to_le_bytes());
+        file.extend_from_slice(&compression.to_le_bytes());
+        file.extend_from_slice(&[0; 20]);
+        file.extend_from_slice(extra);
+        file.extend_from_slice(pixels);
+        file
+    }
+
+    #[test]
+    fn rejects_huge_bmp_before_allocating() {
+        let file = bmp(0x7fff_ffff, 0x7fff_ffff, 24, BI_RGB, &[], &[]);
+
+        assert_eq!(
+            Err(String::from("pixel data is truncated")),
+            decode(&file).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn decodes_padded_24_bit_bmp() {
+        // Rows of one BGR pixel are padded to four bytes, bottom row first.
+        let pixels = [255, 0, 0, 0, 0, 0, 255, 0];
+
+        let image = decode(&bmp(1, 2, 24, BI_RGB, &[], &pixels)).unwrap();
+
+        assert_eq!(&[0, 0, 255, 255, 0, 0][..], image.data());
+    }
+
+    #[test]
+    fn decodes_top_down_paletted_bmp() {
+        let palette = [0, 0, 0, 0, 255, 255, 255, 0];
+        let pixels = [0b1000_0000, 0, 0, 0, 0b0100_0000, 0, 0, 0];
+
+        let image = decode(&bmp(2, -2, 1, BI_RGB, &palette, &pixels)).unwrap();
+
+        assert_eq!(
+            &[0, 0, 0, 255, 255, 255, 255, 255, 255, 0, 0, 0][..],
+            image.data()
+        );
+    }
+
+    #[test]
+    fn decodes_bitfield_bmp_with_alpha() {
+        let mut masks = Vec::new();
+        for mask in &[0x0000_f800u32, 0x0000_07e0, 0x0000_001f] {
+            masks.extend_from_slice(&mask.to_le_bytes());
+        }
+        let pixels = 0xf800u16.to_le_bytes();
+
+        let image = decode(&bmp(
+            1,
+            1,
+            16,
+            BI_BITFIELDS,
+            &masks,
+            &[pixels[0], pixels[1], 0, 0],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            (PixelFormat::Rgb8, &[255, 0, 0][..]),
+            (image.format(), image.data())
+        );
+        assert_eq!(128, extract_masked(0x8000_0000, 0xff00_0000));
+    }
+}
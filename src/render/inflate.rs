@@ -0,0 +1,369 @@
+//! zlib (RFC 1950) and DEFLATE (RFC 1951) decompression, as used by PNG.
+
+pub(super) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+pub(super) const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+pub(super) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+pub(super) const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order the code length code lengths of a dynamic block are stored in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompresses a zlib stream, checking its header and Adler-32 checksum.
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 {
+        return Err(String::from("zlib stream is truncated"));
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0f != 8 || (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(String::from("invalid zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(String::from("zlib preset dictionaries are not supported"));
+    }
+
+    let mut reader = BitReader::new(&data[2..]);
+    let output = inflate(&mut reader)?;
+
+    let end = 2 + reader.byte_position();
+    let checksum = data
+        .get(end..end + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| String::from("zlib stream is missing its checksum"))?;
+    if checksum != adler32(&output) {
+        return Err(String::from("zlib checksum mismatch"));
+    }
+    Ok(output)
+}
+
+fn inflate(reader: &mut BitReader) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let len = reader.bits(16)?;
+                let nlen = reader.bits(16)?;
+                if len != !nlen & 0xffff {
+                    return Err(String::from("stored block length is corrupt"));
+                }
+                output.extend_from_slice(reader.bytes(len as usize)?);
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(reader)?;
+                inflate_block(reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(String::from("invalid block type")),
+        }
+
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize
+                    + reader.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(String::from("invalid distance code"));
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + reader.bits(u32::from(DISTANCE_EXTRA[index]))? as usize;
+                if distance > output.len() {
+                    return Err(String::from(
+                        "distance reaches before the start of the output",
+                    ));
+                }
+
+                // Copied one byte at a time because the source may overlap what is written.
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+            _ => return Err(String::from("invalid literal/length code")),
+        }
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].iter_mut().for_each(|l| *l = 8);
+    lengths[144..256].iter_mut().for_each(|l| *l = 9);
+    lengths[256..280].iter_mut().for_each(|l| *l = 7);
+    lengths[280..].iter_mut().for_each(|l| *l = 8);
+
+    // Fixed codes are always complete, so these can not fail.
+    (
+        Huffman::new(&lengths).unwrap(),
+        Huffman::new(&[5; 30]).unwrap(),
+    )
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_length_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_length_lengths)?;
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_lengths.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| String::from("repeat code with no previous length"))?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(String::from("code lengths overflow the alphabets"));
+    }
+    if lengths[256] == 0 {
+        return Err(String::from("block has no end-of-block code"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+/// A canonical Huffman code, decoded one bit at a time.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; 16],
+    /// Symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, String> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = left * 2 - i32::from(count);
+            if left < 0 {
+                return Err(String::from("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(String::from("invalid Huffman code"))
+    }
+}
+
+/// Reads bits least significant first, as DEFLATE packs them.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            position: 0,
+            bit: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| String::from("compressed data is truncated"))?;
+            value |= u32::from((byte >> self.bit) & 1) << i;
+
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.position += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.position += 1;
+        }
+    }
+
+    /// Reads whole bytes; only valid when aligned.
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or_else(|| String::from("stored block is truncated"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    /// Index of the first byte that has not been fully read.
+    fn byte_position(&self) -> usize {
+        self.position + (self.bit != 0) as usize
+    }
+}
+
+pub(super) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_blocks() {
+        let data = [
+            0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, b'h', b'e', b'l', b'l', b'o', 0x06, 0x2c,
+            0x02, 0x15,
+        ];
+
+        assert_eq!(b"hello".to_vec(), zlib_decompress(&data).unwrap());
+    }
+
+    #[test]
+    fn fixed_huffman_with_back_references() {
+        // zlib.compress(b"abcabcabcabcabcabc")
+        let data = [
+            0x78, 0x9c, 0x4b, 0x4c, 0x4a, 0x4e, 0x44, 0x45, 0x00, 0x41, 0x7c, 0x06, 0xe5,
+        ];
+
+        assert_eq!(
+            b"abcabcabcabcabcabc".to_vec(),
+            zlib_decompress(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn dynamic_huffman() {
+        // 400 pseudo-random bytes from a skewed alphabet, compressed with zlib level 9.
+        let mut x: u32 = 1;
+        let expected: Vec<u8> = (0..400)
+            .map(|_| {
+                x = x.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fff_ffff;
+                b"aaaabbc d"[((x >> 16) % 9) as usize]
+            })
+            .collect();
+        let data = [
+            0x78, 0xda, 0x3d, 0x91, 0x81, 0x11, 0xc5, 0x30, 0x08, 0x42, 0x57, 0x61, 0x35, 0x81,
+            0xfd, 0x67, 0xf8, 0xa0, 0xf9, 0xed, 0x5d, 0xda, 0x88, 0xe4, 0xa9, 0xa9, 0xa1, 0x99,
+            0xe9, 0x22, 0xb3, 0x46, 0x9a, 0x4f, 0xa8, 0x64, 0xe4, 0x8b, 0xc4, 0x5c, 0x65, 0x53,
+            0xb4, 0xa2, 0x79, 0x40, 0x8a, 0x1a, 0x33, 0x4f, 0x64, 0xd5, 0xea, 0xda, 0xb2, 0x29,
+            0x60, 0xe2, 0x48, 0x8c, 0x3f, 0xdf, 0x2b, 0x0d, 0x50, 0x43, 0xa2, 0x10, 0x9c, 0x8c,
+            0x8b, 0x21, 0xc4, 0xc5, 0x17, 0xf1, 0xd5, 0x8a, 0x27, 0xe5, 0xd8, 0x4e, 0x42, 0x4c,
+            0x49, 0xc6, 0xec, 0xd4, 0x97, 0xbd, 0xec, 0xd7, 0xf5, 0xd6, 0xbf, 0x23, 0x1b, 0x56,
+            0xa0, 0x21, 0x60, 0x29, 0xb8, 0xcc, 0x36, 0xd5, 0x8a, 0xd9, 0x50, 0xaf, 0xd3, 0xf5,
+            0xc5, 0x51, 0x0b, 0x32, 0xbd, 0x83, 0x87, 0xc9, 0x07, 0x9a, 0x1d, 0x9f, 0x7d, 0xa1,
+            0xe7, 0xb1, 0x62, 0x0c, 0x19, 0xea, 0xf2, 0xd2, 0xa1, 0x3b, 0x66, 0x65, 0xdf, 0x1d,
+            0x6c, 0x7e, 0x6f, 0xb4, 0x84, 0x77, 0x73, 0x9d, 0x35, 0x87, 0x63, 0x10, 0x3a, 0x28,
+            0x0e, 0x37, 0xbd, 0x50, 0x49, 0xf9, 0x17, 0x3f, 0x78, 0x75, 0x8f, 0x1f,
+        ];
+
+        assert_eq!(expected, zlib_decompress(&data).unwrap());
+    }
+
+    #[test]
+    fn rejects_corrupt_streams() {
+        let mut bad_checksum = [
+            0x78, 0x9c, 0x4b, 0x4c, 0x4a, 0x4e, 0x44, 0x45, 0x00, 0x41, 0x7c, 0x06, 0xe5,
+        ];
+        bad_checksum[12] ^= 1;
+
+        assert!(zlib_decompress(&bad_checksum).is_err());
+        assert!(zlib_decompress(&bad_checksum[..8]).is_err());
+        assert!(zlib_decompress(&[0x79, 0x9c, 0x4b, 0x4c, 0x4a, 0x4e]).is_err());
+    }
+}
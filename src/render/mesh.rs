@@ -0,0 +1,124 @@
+use super::buffer::{ArrayBuffer, ElementArrayBuffer, ElementIndex, VertexArray};
+use super::{Error, Program, VertexLayout};
+use gl::types::*;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Topology {
+    pub fn gl_mode(self) -> GLenum {
+        match self {
+            Topology::Points => gl::POINTS,
+            Topology::Lines => gl::LINES,
+            Topology::LineStrip => gl::LINE_STRIP,
+            Topology::LineLoop => gl::LINE_LOOP,
+            Topology::Triangles => gl::TRIANGLES,
+            Topology::TriangleStrip => gl::TRIANGLE_STRIP,
+            Topology::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+/// Vertices of type `V`, optional indices and the VAO tying them together.
+pub struct Mesh<V>
+where
+    V: VertexLayout,
+{
+    vao: VertexArray,
+    _vbo: ArrayBuffer,
+    ibo: Option<ElementArrayBuffer>,
+    vertex_count: usize,
+    topology: Topology,
+    _vertex_marker: PhantomData<V>,
+}
+
+impl<V> Mesh<V>
+where
+    V: VertexLayout,
+{
+    pub fn new(vertices: &[V], topology: Topology) -> Mesh<V> {
+        Mesh::build(vertices, None::<&[u32]>, topology)
+    }
+
+    pub fn with_indices<I: ElementIndex>(
+        vertices: &[V],
+        indices: &[I],
+        topology: Topology,
+    ) -> Mesh<V> {
+        Mesh::build(vertices, Some(indices), topology)
+    }
+
+    fn build<I: ElementIndex>(
+        vertices: &[V],
+        indices: Option<&[I]>,
+        topology: Topology,
+    ) -> Mesh<V> {
+        let vao = VertexArray::new();
+        vao.bind();
+
+        let vbo = ArrayBuffer::new();
+        vbo.bind();
+        vbo.buffer_static_data(vertices);
+        V::vertex_attrib_pointers();
+
+        // The element array binding is part of the VAO state, so the IBO has to be bound
+        // while the VAO is.
+        let ibo = indices.map(|indices| {
+            let ibo = ElementArrayBuffer::new();
+            ibo.bind();
+            ibo.buffer_static_indices(indices);
+            ibo
+        });
+
+        vao.unbind();
+        vbo.unbind();
+
+        Mesh {
+            vao,
+            _vbo: vbo,
+            ibo,
+            vertex_count: vertices.len(),
+            topology,
+            _vertex_marker: PhantomData,
+        }
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn index_count(&self) -> Option<usize> {
+        self.ibo.as_ref().map(|ibo| ibo.index_count())
+    }
+
+    /// Checks that `program`'s vertex inputs can be fed from `V`.
+    pub fn check_program(&self, program: &Program) -> Result<(), Error> {
+        program.check_vertex_attribs(&V::vertex_attribs())
+    }
+
+    /// Draws the mesh with whatever program is bound.
+    pub fn draw(&self) {
+        self.vao.bind();
+
+        let mode = self.topology.gl_mode();
+        match self.ibo {
+            Some(ref ibo) => ibo.draw_elements(mode),
+            None => unsafe {
+                gl::DrawArrays(mode, 0, self.vertex_count as GLsizei);
+            },
+        }
+    }
+}
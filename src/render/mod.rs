@@ -1,13 +1,55 @@
+mod error;
 mod shader;
+mod reflection;
+mod preprocessor;
+mod compile_log;
+mod program_cache;
+mod program_binary;
 mod window;
 mod vertex;
 mod buffer;
+mod mesh;
+pub mod primitives;
+pub mod obj;
+mod json;
+pub mod gltf;
+mod inflate;
+mod deflate;
+mod png;
+mod hdr;
+pub mod image;
+mod texture;
+mod cubemap;
+mod texture_array;
+mod framebuffer;
+mod render_state;
+mod ring_buffer;
+mod block_layout;
 mod triangle;
 mod quad;
+#[cfg(test)]
+mod golden;
 
 pub use triangle::*;
 pub use quad::*;
-pub use shader::{Shader, Program, Error};
+pub use error::Error;
+pub use shader::{Shader, Program};
+pub use reflection::ActiveVariable;
+pub use compile_log::{Diagnostic, Severity};
+pub use program_cache::ProgramCache;
+pub use program_binary::ProgramBinaryCache;
 pub use window::Window;
-pub use vertex::Vertex;
-pub use buffer::{VertexArray, VertexBufferObject, IndexBufferObject, ArrayBuffer, ElementArrayBuffer};
+pub use vertex::{Vertex, MeshVertex, VertexLayout};
+pub use mesh::{Mesh, Topology};
+pub use image::{Image, PixelFormat};
+pub use texture::{Texture2D, TextureOptions, InternalFormat, Filter, Wrap};
+pub use cubemap::{TextureCube, CubeFace};
+pub use texture_array::{Texture2DArray, Texture3D};
+pub use framebuffer::{Framebuffer, Attachment, Renderbuffer, BufferMask};
+pub use render_state::{RenderState, DepthState, ClipDepth, CompareFunc, BlendState, BlendEquation, BlendFactor, BlendOp, CullMode, FrontFace,
+                       PolygonMode, StencilState, StencilFace, StencilOp, ColorMask, Scissor, ClearValues};
+pub use buffer::{VertexArray, VertexBufferObject, IndexBufferObject, ArrayBuffer, ElementArrayBuffer, ElementIndex, MappedRange};
+pub use buffer::{UniformBuffer, ShaderStorageBuffer, AtomicCounterBuffer, TransformFeedbackBuffer, DrawIndirectBuffer,
+                 DispatchIndirectBuffer, CopyReadBuffer, CopyWriteBuffer, PixelPackBuffer, PixelUnpackBuffer, TextureBuffer};
+pub use block_layout::{Std140, Std430};
+pub use ring_buffer::{IndexRingBuffer, RingBuffer, VertexRingBuffer};
@@ -0,0 +1,584 @@
+//! Wavefront OBJ models and their MTL material libraries.
+
+use super::primitives::MeshData;
+use super::{Error, MeshVertex};
+use crate::resources::{self, Resources};
+use crate::utils::{Vec2, Vec3, Vec4};
+
+use std::collections::HashMap;
+
+/// A material from an MTL library. Texture maps are resource names.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Vec3,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    pub emissive: Vec3,
+    pub shininess: f32,
+    pub opacity: f32,
+    pub optical_density: f32,
+    pub illumination: u32,
+    pub ambient_map: Option<String>,
+    pub diffuse_map: Option<String>,
+    pub specular_map: Option<String>,
+    pub normal_map: Option<String>,
+    pub opacity_map: Option<String>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Material {
+        Material {
+            name: String::from(name),
+            ambient: Vec3::zero(),
+            diffuse: Vec3::splat(1.0),
+            specular: Vec3::zero(),
+            emissive: Vec3::zero(),
+            shininess: 0.0,
+            opacity: 1.0,
+            optical_density: 1.0,
+            illumination: 2,
+            ambient_map: None,
+            diffuse_map: None,
+            specular_map: None,
+            normal_map: None,
+            opacity_map: None,
+        }
+    }
+}
+
+/// The triangles of one object or group that share a material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMesh {
+    pub name: String,
+    /// Index into `Model::materials`.
+    pub material: Option<usize>,
+    pub data: MeshData,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Model {
+    pub meshes: Vec<ObjMesh>,
+    pub materials: Vec<Material>,
+}
+
+impl Model {
+    /// Loads an OBJ file and the MTL libraries it references, relative to its directory.
+    pub fn from_res(res: &Resources, name: &str) -> Result<Model, Error> {
+        let load = |file: &str| {
+            res.load_string(file).map_err(|e| Error::ResourceLoad {
+                name: String::from(file),
+                inner: e,
+            })
+        };
+
+        parse(name, &load(name)?, load)
+    }
+
+    pub fn material(&self, mesh: &ObjMesh) -> Option<&Material> {
+        mesh.material.map(|i| &self.materials[i])
+    }
+}
+
+/// Parses OBJ `source`, loading `mtllib` files with `load`.
+///
+/// Polygons are triangulated, corners with the same position, UV and normal indices share
+/// a vertex, and missing normals and all tangents are generated.
+pub fn parse<F>(name: &str, source: &str, mut load: F) -> Result<Model, Error>
+where
+    F: FnMut(&str) -> Result<String, Error>,
+{
+    let mut model = Model::default();
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut builder = MeshBuilder::new(String::new(), None);
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| Error::ModelParse {
+            name: String::from(name),
+            line: i + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let rest: Vec<&str> = words.collect();
+
+        match keyword {
+            "v" => positions.push(parse_vec3(&rest).map_err(error)?),
+            "vt" => {
+                let values = parse_floats(&rest, 1).map_err(error)?;
+                uvs.push(Vec2::new(values[0], values.get(1).cloned().unwrap_or(0.0)));
+            }
+            "vn" => normals.push(parse_vec3(&rest).map_err(error)?.normalize()),
+            "f" => {
+                if rest.len() < 3 {
+                    return Err(error(String::from("a face needs at least 3 corners")));
+                }
+
+                let corners = rest
+                    .iter()
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                let points: Vec<Vec3> = corners.iter().map(|c| positions[c.0]).collect();
+                for triangle in triangulate(&points) {
+                    for &corner in &triangle {
+                        builder.corner(corners[corner], &positions, &uvs, &normals);
+                    }
+                }
+            }
+            "o" | "g" => {
+                let material = builder.material;
+                let next = MeshBuilder::new(rest.join(" "), material);
+                builder.finish_into(&mut model.meshes, next);
+            }
+            "usemtl" => {
+                let material_name = rest.join(" ");
+                let material = model
+                    .materials
+                    .iter()
+                    .position(|m| m.name == material_name)
+                    .ok_or_else(|| error(format!("unknown material {}", material_name)))?;
+                let next = MeshBuilder::new(builder.name.clone(), Some(material));
+                builder.finish_into(&mut model.meshes, next);
+            }
+            "mtllib" => {
+                for library in rest {
+                    let library = resources::sibling_name(name, library);
+                    let source = load(&library)?;
+                    model.materials.extend(parse_mtl(&library, &source)?);
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry are not supported.
+            _ => {}
+        }
+    }
+
+    builder.finish_into(&mut model.meshes, MeshBuilder::new(String::new(), None));
+    Ok(model)
+}
+
+/// Parses an MTL material library. Texture map names are resolved relative to `name`.
+pub fn parse_mtl(name: &str, source: &str) -> Result<Vec<Material>, Error> {
+    let mut materials: Vec<Material> = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| Error::ModelParse {
+            name: String::from(name),
+            line: i + 1,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let rest: Vec<&str> = words.collect();
+
+        if keyword == "newmtl" {
+            materials.push(Material::new(&rest.join(" ")));
+            continue;
+        }
+
+        let material = materials
+            .last_mut()
+            .ok_or_else(|| error(format!("{} before any newmtl", keyword)))?;
+        // Map statements may carry options such as `-bm 1.0`, the file name comes last.
+        let map = || rest.last().map(|file| resources::sibling_name(name, file));
+
+        match keyword {
+            "Ka" => material.ambient = parse_color(&rest).map_err(error)?,
+            "Kd" => material.diffuse = parse_color(&rest).map_err(error)?,
+            "Ks" => material.specular = parse_color(&rest).map_err(error)?,
+            "Ke" => material.emissive = parse_color(&rest).map_err(error)?,
+            "Ns" => material.shininess = parse_floats(&rest, 1).map_err(error)?[0],
+            "d" => material.opacity = parse_floats(&rest, 1).map_err(error)?[0],
+            "Tr" => material.opacity = 1.0 - parse_floats(&rest, 1).map_err(error)?[0],
+            "Ni" => material.optical_density = parse_floats(&rest, 1).map_err(error)?[0],
+            "illum" => {
+                material.illumination = rest
+                    .first()
+                    .and_then(|s| s.parse().ok())
+                    .ok_or_else(|| error(String::from("expected an illumination model")))?
+            }
+            "map_Ka" => material.ambient_map = map(),
+            "map_Kd" => material.diffuse_map = map(),
+            "map_Ks" => material.specular_map = map(),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_map = map(),
+            "map_d" => material.opacity_map = map(),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+type CornerKey = (usize, Option<usize>, Option<usize>);
+
+struct MeshBuilder {
+    name: String,
+    material: Option<usize>,
+    data: MeshData,
+    vertices: HashMap<CornerKey, u32>,
+}
+
+impl MeshBuilder {
+    fn new(name: String, material: Option<usize>) -> MeshBuilder {
+        MeshBuilder {
+            name,
+            material,
+            data: MeshData::new(),
+            vertices: HashMap::new(),
+        }
+    }
+
+    fn corner(&mut self, key: CornerKey, positions: &[Vec3], uvs: &[Vec2], normals: &[Vec3]) {
+        let data = &mut self.data;
+        let index = *self.vertices.entry(key).or_insert_with(|| {
+            data.vertices.push(MeshVertex::new(
+                positions[key.0],
+                key.2.map_or(Vec3::zero(), |n| normals[n]),
+                key.1.map_or(Vec2::zero(), |t| uvs[t]),
+                Vec4::zero(),
+            ));
+            (data.vertices.len() - 1) as u32
+        });
+        data.indices.push(index);
+    }
+
+    /// Adds the mesh to `meshes` if it has any triangles and replaces it with `next`.
+    fn finish_into(&mut self, meshes: &mut Vec<ObjMesh>, next: MeshBuilder) {
+        let builder = std::mem::replace(self, next);
+        if builder.data.indices.is_empty() {
+            return;
+        }
+
+        let mut data = builder.data;
+        data.generate_normals();
+        data.generate_tangents();
+        meshes.push(ObjMesh {
+            name: builder.name,
+            material: builder.material,
+            data,
+        });
+    }
+}
+
+fn parse_floats(words: &[&str], min: usize) -> Result<Vec<f32>, String> {
+    let values = words
+        .iter()
+        .map(|w| w.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid number: {}", e))?;
+
+    if values.len() < min {
+        return Err(format!("expected {} numbers, found {}", min, values.len()));
+    }
+    Ok(values)
+}
+
+fn parse_vec3(words: &[&str]) -> Result<Vec3, String> {
+    // Positions may carry a fourth weight component, which is ignored.
+    let values = parse_floats(words, 3)?;
+    Ok(Vec3::new(values[0], values[1], values[2]))
+}
+
+fn parse_color(words: &[&str]) -> Result<Vec3, String> {
+    let values = parse_floats(words, 1)?;
+    match values.len() {
+        1 | 2 => Ok(Vec3::splat(values[0])),
+        _ => Ok(Vec3::new(values[0], values[1], values[2])),
+    }
+}
+
+/// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`, resolving 1-based and negative indices.
+fn parse_corner(
+    corner: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+) -> Result<CornerKey, String> {
+    let mut parts = corner.split('/');
+    let resolve = |part: Option<&str>, count: usize, kind: &str| -> Result<Option<usize>, String> {
+        let part = match part {
+            Some(part) if !part.is_empty() => part,
+            _ => return Ok(None),
+        };
+        let index: i64 = part
+            .parse()
+            .map_err(|_| format!("invalid {} index {}", kind, part))?;
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(format!(
+                "{} index {} is out of range, there are {}",
+                kind, index, count
+            ));
+        }
+        Ok(Some(resolved as usize))
+    };
+
+    let position = resolve(parts.next(), positions, "position")?
+        .ok_or_else(|| format!("face corner {} has no position", corner))?;
+    let uv = resolve(parts.next(), uvs, "texture coordinate")?;
+    let normal = resolve(parts.next(), normals, "normal")?;
+    Ok((position, uv, normal))
+}
+
+/// Splits a planar polygon into triangles by ear clipping, keeping its winding.
+///
+/// Falls back to a fan for degenerate polygons, where no ear can be found.
+fn triangulate(points: &[Vec3]) -> Vec<[usize; 3]> {
+    let fan = |remaining: &[usize]| -> Vec<[usize; 3]> {
+        (1..remaining.len() - 1)
+            .map(|i| [remaining[0], remaining[i], remaining[i + 1]])
+            .collect()
+    };
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if points.len() == 3 {
+        return fan(&remaining);
+    }
+
+    // Newell's method gives a normal that follows the polygon's winding even if it is concave.
+    let mut normal = Vec3::zero();
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    if normal.length_squared() <= f32::EPSILON * f32::EPSILON {
+        return fan(&remaining);
+    }
+    let normal = normal.normalize();
+
+    // Project onto the polygon's plane, in which it is counter-clockwise.
+    let axis = if normal.x.abs() < 0.9 {
+        Vec3::new(1.0, 0.0, 0.0)
+    } else {
+        Vec3::new(0.0, 1.0, 0.0)
+    };
+    let u = axis.cross(normal).normalize();
+    let v = normal.cross(u);
+    let flat: Vec<Vec2> = points
+        .iter()
+        .map(|p| Vec2::new(p.dot(u), p.dot(v)))
+        .collect();
+
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (pa, pb, pc) = (flat[a], flat[b], flat[c]);
+            if (pb - pa).cross(pc - pb) <= 0.0 {
+                return false;
+            }
+
+            remaining
+                .iter()
+                .filter(|&&p| p != a && p != b && p != c)
+                .all(|&p| !in_triangle(flat[p], pa, pb, pc))
+        });
+
+        match ear {
+            Some(i) => {
+                triangles.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            }
+            None => {
+                triangles.extend(fan(&remaining));
+                return triangles;
+            }
+        }
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    (b - a).cross(p - a) >= 0.0 && (c - b).cross(p - b) >= 0.0 && (a - c).cross(p - c) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_files(name: &str) -> Result<String, Error> {
+        Err(Error::ModelParse {
+            name: String::from(name),
+            line: 0,
+            message: String::from("no files in this test"),
+        })
+    }
+
+    #[test]
+    fn shares_vertices_between_corners() {
+        let source = "\
+            v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+            vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+            vn 0 0 1\n\
+            f 1/1/1 2/2/1 3/3/1 4/4/1\n";
+
+        let model = parse("quad.obj", source, no_files).unwrap();
+
+        assert_eq!(1, model.meshes.len());
+        let data = &model.meshes[0].data;
+        assert_eq!(4, data.vertices.len());
+        assert_eq!(6, data.indices.len());
+        let corner = data
+            .vertices
+            .iter()
+            .find(|v| v.pos == Vec3::new(1.0, 1.0, 0.0))
+            .unwrap();
+        assert_eq!(Vec2::new(1.0, 1.0), corner.uv);
+        assert_eq!(Vec4::new(1.0, 0.0, 0.0, 1.0), data.vertices[0].tangent);
+    }
+
+    #[test]
+    fn triangulates_concave_polygons() {
+        // An arrow pointing right, concave at (1, 1).
+        let points: Vec<Vec3> = [
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, -1.0),
+            (4.0, 1.0),
+            (2.0, 3.0),
+            (2.0, 2.0),
+            (0.0, 2.0),
+            (1.0, 1.0),
+        ]
+        .iter()
+        .map(|&(x, y)| Vec3::new(x, y, 0.0))
+        .collect();
+
+        let triangles = triangulate(&points);
+
+        assert_eq!(points.len() - 2, triangles.len());
+        let mut area = 0.0;
+        for &[a, b, c] in &triangles {
+            let cross = (points[b] - points[a]).cross(points[c] - points[a]);
+            assert!(cross.z > 0.0, "triangle {:?} is flipped", [a, b, c]);
+            area += cross.z * 0.5;
+        }
+        assert!((area - 7.0).abs() < 1e-5, "area was {}", area);
+    }
+
+    #[test]
+    fn negative_indices_and_generated_normals() {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 0 -1\nf -3 -2 -1\n";
+
+        let model = parse("tri.obj", source, no_files).unwrap();
+
+        let data = &model.meshes[0].data;
+        assert_eq!(vec![0, 1, 2], data.indices);
+        assert!(data
+            .vertices
+            .iter()
+            .all(|v| v.normal == Vec3::new(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn splits_meshes_by_object_and_material() {
+        let mtl = "\
+            newmtl wood\n\
+            Kd 0.5 0.25 0.125\n\
+            Ns 32\n\
+            d 0.5\n\
+            map_Kd textures/wood.png\n\
+            map_Bump -bm 0.5 textures\\wood_normal.png\n\
+            newmtl metal\n\
+            Ks 1\n";
+        let source = "\
+            mtllib crate.mtl\n\
+            v 0 0 0\nv 1 0 0\nv 1 1 0\n\
+            o box\n\
+            usemtl wood\n\
+            f 1 2 3\n\
+            usemtl metal\n\
+            f 1 2 3\n\
+            o lid\n\
+            f 3 2 1\n";
+
+        let mut loaded = Vec::new();
+        let model = parse("models/crate.obj", source, |name| {
+            loaded.push(String::from(name));
+            Ok(String::from(mtl))
+        })
+        .unwrap();
+
+        assert_eq!(vec!["models/crate.mtl"], loaded);
+        assert_eq!(2, model.materials.len());
+        let wood = &model.materials[0];
+        assert_eq!(Vec3::new(0.5, 0.25, 0.125), wood.diffuse);
+        assert_eq!(32.0, wood.shininess);
+        assert_eq!(0.5, wood.opacity);
+        assert_eq!(
+            Some(String::from("models/textures/wood.png")),
+            wood.diffuse_map
+        );
+        assert_eq!(
+            Some(String::from("models/textures/wood_normal.png")),
+            wood.normal_map
+        );
+        assert_eq!(Vec3::splat(1.0), model.materials[1].specular);
+
+        let meshes: Vec<(&str, Option<&str>)> = model
+            .meshes
+            .iter()
+            .map(|m| (m.name.as_str(), model.material(m).map(|m| m.name.as_str())))
+            .collect();
+        assert_eq!(
+            vec![
+                ("box", Some("wood")),
+                ("box", Some("metal")),
+                ("lid", Some("metal"))
+            ],
+            meshes
+        );
+    }
+
+    #[test]
+    fn reports_line_numbers() {
+        match parse("bad.obj", "v 0 0 0\n\nf 1 2 3\n", no_files) {
+            Err(Error::ModelParse {
+                name,
+                line,
+                message,
+            }) => {
+                assert_eq!("bad.obj", name);
+                assert_eq!(3, line);
+                assert_eq!("position index 2 is out of range, there are 1", message);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+
+        match parse_mtl("bad.mtl", "Kd 1 1 1\n") {
+            Err(Error::ModelParse { line, .. }) => assert_eq!(1, line),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,676 @@
+//! PNG decoding for every standard color type and bit depth, interlaced or not, and
+//! encoding of 8 and 16-bit images.
+
+use super::image::{image_size, Image, PixelFormat};
+use super::{deflate, inflate};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// The Adam7 passes as `(x0, y0, dx, dy)`.
+const PASSES: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+pub fn is_png(data: &[u8]) -> bool {
+    data.starts_with(SIGNATURE)
+}
+
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            0 | 3 => 1,
+            4 => 2,
+            2 => 3,
+            _ => 4,
+        }
+    }
+
+    /// Bytes per complete pixel, rounded up, as used by the filters.
+    fn filter_stride(&self) -> usize {
+        (self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    fn row_bytes(&self, width: usize) -> usize {
+        (width * self.channels() * self.bit_depth as usize).div_ceil(8)
+    }
+
+    /// Bytes of filtered data for `width` by `height` pixels, each row led by its filter type.
+    fn filtered_size(&self, width: usize, height: usize) -> Result<usize, String> {
+        if width == 0 || height == 0 {
+            return Ok(0);
+        }
+        let row_bytes = image_size(width, self.channels() * self.bit_depth as usize, 1)?;
+        image_size(row_bytes.div_ceil(8) + 1, height, 1)
+    }
+
+    /// Bytes of filtered data for the whole image, over all passes if it is interlaced.
+    fn data_size(&self) -> Result<usize, String> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        if !self.interlaced {
+            return self.filtered_size(width, height);
+        }
+        PASSES.iter().try_fold(0usize, |total, &(x0, y0, dx, dy)| {
+            let pass =
+                self.filtered_size((width + dx - 1 - x0) / dx, (height + dy - 1 - y0) / dy)?;
+            total
+                .checked_add(pass)
+                .ok_or_else(|| String::from("image is too large"))
+        })
+    }
+}
+
+/// Decodes a PNG file.
+///
+/// Palettes are expanded to RGB(A), a `tRNS` color key becomes an alpha channel, depths below
+/// 8 bits are scaled up to 8 and 16-bit images stay 16-bit.
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    if !is_png(data) {
+        return Err(String::from("not a PNG file"));
+    }
+
+    let mut header = None;
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut transparency: Option<Vec<u8>> = None;
+    let mut compressed = Vec::new();
+
+    let mut offset = SIGNATURE.len();
+    loop {
+        let (kind, body) = chunk(data, offset)?;
+        offset += 12 + body.len();
+
+        match kind {
+            b"IHDR" => header = Some(read_header(body)?),
+            b"PLTE" => palette = body.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect(),
+            b"tRNS" => transparency = Some(body.to_vec()),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // Ancillary chunks have a lowercase first letter and can be skipped.
+            _ if kind[0].is_ascii_lowercase() => {}
+            _ => {
+                return Err(format!(
+                    "unsupported critical chunk {}",
+                    String::from_utf8_lossy(kind)
+                ))
+            }
+        }
+    }
+
+    let header = header.ok_or_else(|| String::from("missing IHDR chunk"))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(String::from("palette image has no PLTE chunk"));
+    }
+
+    let raw = inflate::zlib_decompress(&compressed)?;
+    // Checked before anything the size of the image is allocated, so the header's dimensions
+    // cannot make decoding allocate more than the data fills.
+    if raw.len() < header.data_size()? {
+        return Err(String::from("image data is truncated"));
+    }
+    let samples = if header.interlaced {
+        deinterlace(&header, &raw)?
+    } else {
+        let mut raw = raw;
+        let width = header.width as usize;
+        let height = header.height as usize;
+        unfilter(&header, &mut raw, width, height)?;
+        unpack_rows(&header, &raw, width, height)
+    };
+
+    Ok(expand(&header, samples, &palette, transparency.as_deref()))
+}
+
+fn chunk(data: &[u8], offset: usize) -> Result<(&[u8], &[u8]), String> {
+    let truncated = || String::from("file is truncated");
+    let length = data
+        .get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .ok_or_else(truncated)?;
+    let kind = data.get(offset + 4..offset + 8).ok_or_else(truncated)?;
+    let body = data
+        .get(offset + 8..offset + 8 + length)
+        .ok_or_else(truncated)?;
+    let crc = data
+        .get(offset + 8 + length..offset + 12 + length)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(truncated)?;
+
+    if crc != crc32(&data[offset + 4..offset + 8 + length]) {
+        return Err(format!(
+            "CRC mismatch in {} chunk",
+            String::from_utf8_lossy(kind)
+        ));
+    }
+    Ok((kind, body))
+}
+
+fn read_header(body: &[u8]) -> Result<Header, String> {
+    if body.len() != 13 {
+        return Err(String::from("IHDR chunk has the wrong size"));
+    }
+    let header = Header {
+        width: u32::from_be_bytes([body[0], body[1], body[2], body[3]]),
+        height: u32::from_be_bytes([body[4], body[5], body[6], body[7]]),
+        bit_depth: body[8],
+        color_type: body[9],
+        interlaced: body[12] == 1,
+    };
+
+    let valid_depth = match header.color_type {
+        0 => [1, 2, 4, 8, 16].contains(&header.bit_depth),
+        3 => [1, 2, 4, 8].contains(&header.bit_depth),
+        2 | 4 | 6 => [8, 16].contains(&header.bit_depth),
+        _ => return Err(format!("invalid color type {}", header.color_type)),
+    };
+    if !valid_depth {
+        return Err(format!(
+            "invalid bit depth {} for color type {}",
+            header.bit_depth, header.color_type
+        ));
+    }
+    if header.width == 0 || header.height == 0 {
+        return Err(String::from("image has no pixels"));
+    }
+    // Enough for the samples while decoding and for the largest pixels they expand to.
+    image_size(
+        header.width as usize,
+        header.height as usize,
+        (header.channels() + 1) * 2,
+    )?;
+    if body[10] != 0 || body[11] != 0 || body[12] > 1 {
+        return Err(String::from(
+            "unknown compression, filter or interlace method",
+        ));
+    }
+    Ok(header)
+}
+
+/// Undoes the per-row filters in place, leaving each row's filter type byte in front of it.
+fn unfilter(header: &Header, data: &mut [u8], width: usize, height: usize) -> Result<(), String> {
+    let row_bytes = header.row_bytes(width);
+    let stride = header.filter_stride();
+    if data.len() < header.filtered_size(width, height)? {
+        return Err(String::from("image data is truncated"));
+    }
+
+    for y in 0..height {
+        let (before, rest) = data.split_at_mut(y * (row_bytes + 1));
+        let previous = if y == 0 {
+            None
+        } else {
+            Some(&before[before.len() - row_bytes..])
+        };
+        let (filter, row) = rest[..row_bytes + 1].split_at_mut(1);
+
+        for x in 0..row_bytes {
+            let a = if x >= stride { row[x - stride] } else { 0 };
+            let b = previous.map_or(0, |p| p[x]);
+            let c = match previous {
+                Some(p) if x >= stride => p[x - stride],
+                _ => 0,
+            };
+
+            let predicted = match filter[0] {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                4 => paeth(a, b, c),
+                other => return Err(format!("invalid filter type {}", other)),
+            };
+            row[x] = row[x].wrapping_add(predicted);
+        }
+    }
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = i16::from(a) + i16::from(b) - i16::from(c);
+    let (pa, pb, pc) = (
+        (p - i16::from(a)).abs(),
+        (p - i16::from(b)).abs(),
+        (p - i16::from(c)).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Splits unfiltered rows into one `u16` per sample, top row first.
+fn unpack_rows(header: &Header, data: &[u8], width: usize, height: usize) -> Vec<u16> {
+    let row_bytes = header.row_bytes(width);
+    let samples_per_row = width * header.channels();
+    let depth = header.bit_depth as usize;
+    let mut samples = Vec::with_capacity(samples_per_row * height);
+
+    for row in data.chunks(row_bytes + 1).take(height) {
+        let row = &row[1..];
+        for i in 0..samples_per_row {
+            let sample = match depth {
+                16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]),
+                8 => u16::from(row[i]),
+                _ => {
+                    let bit = i * depth;
+                    let shift = 8 - depth - bit % 8;
+                    u16::from((row[bit / 8] >> shift) & ((1 << depth) - 1) as u8)
+                }
+            };
+            samples.push(sample);
+        }
+    }
+    samples
+}
+
+/// Reassembles the seven Adam7 passes into one image.
+fn deinterlace(header: &Header, data: &[u8]) -> Result<Vec<u16>, String> {
+    let width = header.width as usize;
+    let height = header.height as usize;
+    let channels = header.channels();
+    let mut samples = vec![0; width * height * channels];
+    let mut offset = 0;
+
+    for &(x0, y0, dx, dy) in &PASSES {
+        let pass_width = (width + dx - 1 - x0) / dx;
+        let pass_height = (height + dy - 1 - y0) / dy;
+        if pass_width == 0 || pass_height == 0 {
+            continue;
+        }
+
+        let size = (header.row_bytes(pass_width) + 1) * pass_height;
+        let mut pass = data
+            .get(offset..offset + size)
+            .ok_or_else(|| String::from("image data is truncated"))?
+            .to_vec();
+        offset += size;
+
+        unfilter(header, &mut pass, pass_width, pass_height)?;
+        let pass = unpack_rows(header, &pass, pass_width, pass_height);
+        for y in 0..pass_height {
+            for x in 0..pass_width {
+                let source = (y * pass_width + x) * channels;
+                let target = ((y0 + y * dy) * width + x0 + x * dx) * channels;
+                samples[target..target + channels]
+                    .copy_from_slice(&pass[source..source + channels]);
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// Turns samples into pixels of a `PixelFormat`, flipping rows to bottom-first.
+fn expand(
+    header: &Header,
+    samples: Vec<u16>,
+    palette: &[[u8; 3]],
+    transparency: Option<&[u8]>,
+) -> Image {
+    let width = header.width as usize;
+    let depth = header.bit_depth;
+    let channels = header.channels();
+
+    // Color keys are compared against raw samples, before scaling.
+    let key: Option<Vec<u16>> = match (header.color_type, transparency) {
+        (0, Some(t)) if t.len() >= 2 => Some(vec![u16::from_be_bytes([t[0], t[1]])]),
+        (2, Some(t)) if t.len() >= 6 => Some(
+            t[..6]
+                .chunks(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    let (format, pixel_bytes) = match header.color_type {
+        3 if transparency.is_some() => (PixelFormat::Rgba8, 4),
+        3 => (PixelFormat::Rgb8, 3),
+        _ => {
+            let out_channels = channels + key.is_some() as usize;
+            let format = PixelFormat::with_channels(out_channels, depth == 16);
+            (format, format.bytes_per_pixel())
+        }
+    };
+
+    let scale = |sample: u16| -> u8 {
+        match depth {
+            1 => (sample * 0xff) as u8,
+            2 => (sample * 0x55) as u8,
+            4 => (sample * 0x11) as u8,
+            _ => sample as u8,
+        }
+    };
+
+    let mut data = Vec::with_capacity(width * header.height as usize * pixel_bytes);
+    for row in samples.chunks(width * channels).rev() {
+        for pixel in row.chunks(channels) {
+            if header.color_type == 3 {
+                let index = pixel[0] as usize;
+                data.extend_from_slice(&palette.get(index).cloned().unwrap_or([0; 3]));
+                if let Some(alpha) = transparency {
+                    data.push(alpha.get(index).cloned().unwrap_or(0xff));
+                }
+                continue;
+            }
+
+            let opaque = key.as_ref().map(|key| key.as_slice() != pixel);
+            if depth == 16 {
+                for &sample in pixel {
+                    data.extend_from_slice(&sample.to_ne_bytes());
+                }
+                if let Some(opaque) = opaque {
+                    data.extend_from_slice(&(if opaque { 0xffffu16 } else { 0 }).to_ne_bytes());
+                }
+            } else {
+                data.extend(pixel.iter().map(|&s| scale(s)));
+                if let Some(opaque) = opaque {
+                    data.push(if opaque { 0xff } else { 0 });
+                }
+            }
+        }
+    }
+
+    Image::new(header.width, header.height, format, data)
+}
+
+/// Encodes an 8 or 16-bit image, choosing each row's filter by the smallest sum of absolute
+/// filtered values.
+///
+/// Panics for float images, which PNG can't store.
+pub fn encode(image: &Image) -> Vec<u8> {
+    let format = image.format();
+    let depth = match format.bytes_per_channel() {
+        1 => 8,
+        2 => 16,
+        _ => panic!("PNG can't store {:?} pixels", format),
+    };
+    let color_type = match format.channels() {
+        1 => 0,
+        2 => 4,
+        3 => 2,
+        _ => 6,
+    };
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width().to_be_bytes());
+    header.extend_from_slice(&image.height().to_be_bytes());
+    header.extend_from_slice(&[depth, color_type, 0, 0, 0]);
+
+    // PNG stores rows top first and samples big-endian.
+    let row_bytes = image.width() as usize * format.bytes_per_pixel();
+    let stride = format.bytes_per_pixel();
+    let mut filtered = Vec::with_capacity((row_bytes + 1) * image.height() as usize);
+    let mut previous = vec![0u8; row_bytes];
+    for row in image.data().chunks(row_bytes).rev() {
+        let mut row = row.to_vec();
+        if depth == 16 {
+            for sample in row.chunks_mut(2) {
+                let value = u16::from_ne_bytes([sample[0], sample[1]]);
+                sample.copy_from_slice(&value.to_be_bytes());
+            }
+        }
+        filter_row(&row, &previous, stride, &mut filtered);
+        previous = row;
+    }
+
+    let mut out = SIGNATURE.to_vec();
+    push_chunk(&mut out, b"IHDR", &header);
+    push_chunk(&mut out, b"IDAT", &deflate::zlib_compress(&filtered));
+    push_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Appends the filter type byte and the filtered `row` that compresses best.
+fn filter_row(row: &[u8], previous: &[u8], stride: usize, out: &mut Vec<u8>) {
+    let filtered = |filter: u8| -> Vec<u8> {
+        (0..row.len())
+            .map(|x| {
+                let a = if x >= stride { row[x - stride] } else { 0 };
+                let b = previous[x];
+                let c = if x >= stride { previous[x - stride] } else { 0 };
+                let predicted = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((u16::from(a) + u16::from(b)) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                row[x].wrapping_sub(predicted)
+            })
+            .collect()
+    };
+    // Filtered bytes are treated as signed, so small negative values count as small.
+    let cost = |bytes: &[u8]| -> u64 {
+        bytes
+            .iter()
+            .map(|&b| u64::from((b as i8).unsigned_abs()))
+            .sum()
+    };
+
+    let (filter, bytes) = (0..5)
+        .map(|filter| (filter, filtered(filter)))
+        .min_by_key(|(_, bytes)| cost(bytes))
+        .unwrap();
+    out.push(filter);
+    out.extend_from_slice(&bytes);
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(body);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a PNG from a header and the filtered rows, stored uncompressed.
+    fn png(
+        width: u32,
+        height: u32,
+        depth: u8,
+        color_type: u8,
+        interlaced: bool,
+        extra: &[(&[u8; 4], &[u8])],
+        rows: &[u8],
+    ) -> Vec<u8> {
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[depth, color_type, 0, 0, interlaced as u8]);
+
+        // A single stored deflate block inside a zlib wrapper.
+        let mut zlib = vec![0x78, 0x01, 0x01];
+        zlib.extend_from_slice(&(rows.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(rows.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(rows);
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in rows {
+            a = (a + u32::from(byte)) % 65521;
+            b = (b + a) % 65521;
+        }
+        zlib.extend_from_slice(&(b << 16 | a).to_be_bytes());
+
+        let mut out = SIGNATURE.to_vec();
+        push_chunk(&mut out, b"IHDR", &ihdr);
+        for (kind, body) in extra {
+            push_chunk(&mut out, kind, body);
+        }
+        push_chunk(&mut out, b"IDAT", &zlib);
+        push_chunk(&mut out, b"IEND", &[]);
+        out
+    }
+
+    #[test]
+    fn crc_of_iend() {
+        assert_eq!(0xae42_6082, crc32(b"IEND"));
+    }
+
+    #[test]
+    fn decodes_filtered_rgba_rows_bottom_first() {
+        // Row 0 uses Sub, row 1 uses Up, with Paeth on a third row.
+        let rows = [
+            1, 10, 20, 30, 255, 5, 5, 5, 0, //
+            2, 1, 1, 1, 0, 1, 1, 1, 0, //
+            4, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        let image = decode(&png(2, 3, 8, 6, false, &[], &rows)).unwrap();
+
+        assert_eq!(
+            (2, 3, PixelFormat::Rgba8),
+            (image.width(), image.height(), image.format())
+        );
+        assert_eq!(&[10, 20, 30, 255], image.pixel(0, 2));
+        assert_eq!(&[15, 25, 35, 255], image.pixel(1, 2));
+        assert_eq!(&[11, 21, 31, 255], image.pixel(0, 1));
+        assert_eq!(&[16, 26, 36, 255], image.pixel(1, 1));
+        assert_eq!(&[11, 21, 31, 255], image.pixel(0, 0));
+    }
+
+    #[test]
+    fn expands_palettes_with_transparency() {
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let alpha = [128];
+        // Four 2-bit indices packed into one byte.
+        let rows = [0, 0b00_01_10_00];
+
+        let image = decode(&png(
+            4,
+            1,
+            2,
+            3,
+            false,
+            &[(b"PLTE", &palette), (b"tRNS", &alpha)],
+            &rows,
+        ))
+        .unwrap();
+
+        assert_eq!(PixelFormat::Rgba8, image.format());
+        assert_eq!(
+            &[255, 0, 0, 128, 0, 255, 0, 255, 0, 0, 255, 255, 255, 0, 0, 128][..],
+            image.data()
+        );
+    }
+
+    #[test]
+    fn scales_low_bit_grayscale_and_keys_transparency() {
+        let rows = [0, 0b1011_0000];
+        let key = [0, 1];
+
+        let image = decode(&png(4, 1, 1, 0, false, &[(b"tRNS", &key)], &rows)).unwrap();
+
+        assert_eq!(PixelFormat::Rg8, image.format());
+        assert_eq!(&[255, 0, 0, 255, 255, 0, 255, 0][..], image.data());
+    }
+
+    #[test]
+    fn keeps_sixteen_bits() {
+        let rows = [0, 0x12, 0x34];
+
+        let image = decode(&png(1, 1, 16, 0, false, &[], &rows)).unwrap();
+
+        assert_eq!(PixelFormat::R16, image.format());
+        assert_eq!(0x1234u16.to_ne_bytes(), image.data());
+    }
+
+    #[test]
+    fn deinterlaces_adam7() {
+        // A 3x3 grayscale image has pixels in passes 1, 2 (none), 3 (none), 4, 5, 6 and 7.
+        let rows = [
+            0, 1, // pass 1: (0, 0)
+            0, 3, // pass 4: (2, 0)
+            0, 7, 9, // pass 5: (0, 2), (2, 2)
+            0, 2, 0, 8, // pass 6: (1, 0), (1, 2)
+            0, 4, 5, 6, // pass 7: row 1
+        ];
+
+        let image = decode(&png(3, 3, 8, 0, true, &[], &rows)).unwrap();
+
+        assert_eq!(&[7, 8, 9, 4, 5, 6, 1, 2, 3][..], image.data());
+    }
+
+    #[test]
+    fn rejects_corrupt_files() {
+        let mut file = png(1, 1, 8, 0, false, &[], &[0, 0]);
+        assert!(decode(&file[..file.len() - 4]).is_err());
+
+        let last = file.len() - 20;
+        file[last] ^= 0xff;
+        assert!(decode(&file).is_err());
+        assert!(decode(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn rejects_huge_dimensions_before_allocating() {
+        let huge = png(0x7fff_ffff, 0x7fff_ffff, 16, 6, true, &[], &[0, 0]);
+        let large = png(100_000, 100_000, 8, 0, true, &[], &[0, 0]);
+
+        assert_eq!(
+            Err(String::from("image is too large")),
+            decode(&huge).map(|_| ())
+        );
+        assert_eq!(
+            Err(String::from("image data is truncated")),
+            decode(&large).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn encoded_images_decode_to_the_same_pixels() {
+        let gradient: Vec<u8> = (0..16 * 8 * 4).map(|i| (i * 7 % 256) as u8).collect();
+        let wide: Vec<u8> = (0..5 * 3 * 2)
+            .flat_map(|i: u16| (i * 2000).to_ne_bytes().to_vec())
+            .collect();
+        let images = [
+            Image::new(16, 8, PixelFormat::Rgba8, gradient),
+            Image::new(3, 2, PixelFormat::Rgb8, (0..18).collect()),
+            Image::new(5, 3, PixelFormat::Rg16, wide),
+            Image::new(1, 1, PixelFormat::R8, vec![200]),
+        ];
+
+        for image in images.iter() {
+            assert_eq!(*image, decode(&encode(image)).unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn refuses_float_images() {
+        encode(&Image::new(1, 1, PixelFormat::R32F, vec![0; 4]));
+    }
+}
@@ -0,0 +1,422 @@
+use super::Error;
+
+/// Shader source with every `#include` expanded.
+///
+/// `files[n]` is the resource that GLSL source string number `n` in `#line` directives and
+/// driver logs refers to, `files[0]` being the shader itself. `contents[n]` is its text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreprocessedSource {
+    pub source: String,
+    pub files: Vec<String>,
+    pub contents: Vec<String>,
+}
+
+/// Expands `#include "name"` directives, resolving `name` with `load`, and adds a
+/// `#define NAME VALUE` line for each of `defines`.
+///
+/// The defines go right after the `#version` line, wherever it came from, and the `#version`
+/// line is moved to the top if anything came before it. Without one they go at the top.
+///
+/// Directives inside comments are ignored, but conditionals are not evaluated, so an
+/// `#include` inside `#if 0` or `#ifdef` is still loaded and expanded.
+pub fn preprocess<F>(
+    name: &str,
+    defines: &[(&str, &str)],
+    mut load: F,
+) -> Result<PreprocessedSource, Error>
+where
+    F: FnMut(&str) -> Result<String, Error>,
+{
+    let mut result = PreprocessedSource {
+        source: String::new(),
+        files: Vec::new(),
+        contents: Vec::new(),
+    };
+    let mut stack = Vec::new();
+    let mut version_found = false;
+
+    expand(
+        name,
+        defines,
+        &mut load,
+        &mut stack,
+        &mut version_found,
+        &mut result,
+    )?;
+
+    if !version_found && !defines.is_empty() {
+        let header = define_lines(defines) + "#line 1 0\n";
+        result.source.insert_str(0, &header);
+    }
+    Ok(result)
+}
+
+fn expand<F>(
+    name: &str,
+    defines: &[(&str, &str)],
+    load: &mut F,
+    stack: &mut Vec<String>,
+    version_found: &mut bool,
+    result: &mut PreprocessedSource,
+) -> Result<(), Error>
+where
+    F: FnMut(&str) -> Result<String, Error>,
+{
+    if stack.iter().any(|n| n == name) {
+        let mut chain = stack.clone();
+        chain.push(String::from(name));
+        return Err(Error::IncludeCycle {
+            name: stack[0].clone(),
+            chain,
+        });
+    }
+
+    let source = load(name)?;
+    let index = match result.files.iter().position(|f| f == name) {
+        Some(index) => index,
+        None => {
+            result.files.push(String::from(name));
+            result.contents.push(source.clone());
+            result.files.len() - 1
+        }
+    };
+
+    if !stack.is_empty() {
+        result.source.push_str(&format!("#line 1 {}\n", index));
+    }
+    stack.push(String::from(name));
+
+    let mut in_comment = false;
+    for (i, line) in source.lines().enumerate() {
+        let started_in_comment = in_comment;
+        let code = strip_comments(line, &mut in_comment);
+        match parse_include(&code) {
+            Some(Ok(include)) => {
+                // The directive replaces its whole line, so a comment closed or opened on it
+                // has to be closed or reopened around the included text.
+                if started_in_comment {
+                    result.source.push_str("*/\n");
+                }
+                expand(include, defines, load, stack, version_found, result)?;
+                if in_comment {
+                    result
+                        .source
+                        .push_str(&format!("#line {} {}\n/*\n", i + 1, index));
+                } else {
+                    result
+                        .source
+                        .push_str(&format!("#line {} {}\n", i + 2, index));
+                }
+            }
+            Some(Err(())) => {
+                return Err(Error::InvalidInclude {
+                    name: String::from(name),
+                    line: i + 1,
+                });
+            }
+            None if !*version_found && is_version(&code) => {
+                *version_found = true;
+                let header = format!("{}\n{}", line, define_lines(defines));
+                if result.source.is_empty() {
+                    result.source.push_str(&header);
+                    if !defines.is_empty() {
+                        result
+                            .source
+                            .push_str(&format!("#line {} {}\n", i + 2, index));
+                    }
+                } else {
+                    // `#version` has to come first, and everything before it started at line 1
+                    // of the shader itself.
+                    result.source.insert_str(0, &(header + "#line 1 0\n"));
+                    result
+                        .source
+                        .push_str(&format!("#line {} {}\n", i + 2, index));
+                }
+            }
+            None => {
+                result.source.push_str(line);
+                result.source.push('\n');
+            }
+        }
+    }
+
+    stack.pop();
+    Ok(())
+}
+
+/// Replaces the comments in `line` with spaces, given whether it starts inside a block
+/// comment, and updates that for the next line.
+fn strip_comments(line: &str, in_comment: &mut bool) -> String {
+    let mut code = String::with_capacity(line.len());
+    let mut rest = line;
+    while !rest.is_empty() {
+        if *in_comment {
+            match rest.find("*/") {
+                Some(end) => {
+                    rest = &rest[end + 2..];
+                    *in_comment = false;
+                    code.push(' ');
+                }
+                None => break,
+            }
+        } else {
+            let line_comment = rest.find("//");
+            match rest.find("/*") {
+                Some(start) if line_comment.is_none_or(|l| start < l) => {
+                    code.push_str(&rest[..start]);
+                    rest = &rest[start + 2..];
+                    *in_comment = true;
+                }
+                _ => {
+                    code.push_str(&rest[..line_comment.unwrap_or(rest.len())]);
+                    break;
+                }
+            }
+        }
+    }
+    code
+}
+
+fn is_version(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|rest| rest.trim_start().starts_with("version"))
+}
+
+fn define_lines(defines: &[(&str, &str)]) -> String {
+    defines
+        .iter()
+        .map(|(name, value)| format!("#define {} {}\n", name, value))
+        .collect()
+}
+
+fn parse_include(line: &str) -> Option<Result<&str, ()>> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim();
+
+    Some(
+        rest.strip_prefix('"')
+            .and_then(|r| r.strip_suffix('"'))
+            .filter(|path| !path.is_empty())
+            .ok_or(()),
+    )
+}
+
+/// Replaces source string numbers at the start of driver log lines with file names.
+pub fn annotate_log(log: &str, files: &[String]) -> String {
+    log.lines()
+        .map(|line| annotate_log_line(line, files))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn annotate_log_line(line: &str, files: &[String]) -> String {
+    let prefix_len = ["ERROR: ", "WARNING: "]
+        .iter()
+        .find(|p| line.starts_with(*p))
+        .map_or(0, |p| p.len());
+
+    let rest = &line[prefix_len..];
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || !(rest[digits..].starts_with(':') || rest[digits..].starts_with('(')) {
+        return String::from(line);
+    }
+
+    match rest[..digits]
+        .parse::<usize>()
+        .ok()
+        .and_then(|i| files.get(i))
+    {
+        Some(file) => format!("{}{}{}", &line[..prefix_len], file, &rest[digits..]),
+        None => String::from(line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn loader<'a>(
+        files: &'a HashMap<&str, &str>,
+    ) -> impl FnMut(&str) -> Result<String, Error> + 'a {
+        move |name| {
+            files.get(name).map(|s| String::from(*s)).ok_or_else(|| {
+                Error::CanNotDetermineShaderTypeForResource {
+                    name: String::from(name),
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn expands_includes_with_line_directives() {
+        let mut files = HashMap::new();
+        files.insert(
+            "main.frag",
+            "#version 330 core\n#include \"lib/math.glsl\"\nvoid main() {}\n",
+        );
+        files.insert("lib/math.glsl", "float sq(float x) { return x * x; }\n");
+
+        let result = preprocess("main.frag", &[], loader(&files)).unwrap();
+
+        assert_eq!(vec!["main.frag", "lib/math.glsl"], result.files);
+        assert_eq!(
+            "#version 330 core\n\
+             #line 1 1\n\
+             float sq(float x) { return x * x; }\n\
+             #line 3 0\n\
+             void main() {}\n",
+            result.source
+        );
+    }
+
+    #[test]
+    fn nested_includes_share_file_numbers() {
+        let mut files = HashMap::new();
+        files.insert("a", "#include \"b\"\n#include \"c\"\n");
+        files.insert("b", "#include \"c\"\n");
+        files.insert("c", "c\n");
+
+        let result = preprocess("a", &[], loader(&files)).unwrap();
+
+        assert_eq!(vec!["a", "b", "c"], result.files);
+        assert_eq!(
+            "#line 1 1\n#line 1 2\nc\n#line 2 1\n#line 2 0\n#line 1 2\nc\n#line 3 0\n",
+            result.source
+        );
+    }
+
+    #[test]
+    fn ignores_includes_in_comments() {
+        let mut files = HashMap::new();
+        files.insert(
+            "a",
+            "// #include \"missing\"\n/* start\n#include \"missing\"\n*/ #include \"b\" /*\nc */\n",
+        );
+        files.insert("b", "b\n");
+
+        let result = preprocess("a", &[], loader(&files)).unwrap();
+
+        assert_eq!(vec!["a", "b"], result.files);
+        assert_eq!(
+            "// #include \"missing\"\n/* start\n#include \"missing\"\n*/\n#line 1 1\nb\n#line 4 0\n/*\nc */\n",
+            result.source
+        );
+    }
+
+    #[test]
+    fn strips_comments() {
+        let mut in_comment = false;
+        assert_eq!("a ", strip_comments("a // b", &mut in_comment));
+        assert_eq!("a   c", strip_comments("a /* b */ c", &mut in_comment));
+        assert_eq!("a ", strip_comments("a /* b", &mut in_comment));
+        assert!(in_comment);
+        assert_eq!("", strip_comments("#include \"x\"", &mut in_comment));
+        assert_eq!("  d", strip_comments("*/ d", &mut in_comment));
+        assert!(!in_comment);
+    }
+
+    #[test]
+    fn detects_include_cycles() {
+        let mut files = HashMap::new();
+        files.insert("a", "#include \"b\"\n");
+        files.insert("b", "#include \"a\"\n");
+
+        match preprocess("a", &[], loader(&files)) {
+            Err(Error::IncludeCycle { name, chain }) => {
+                assert_eq!("a", name);
+                assert_eq!(vec!["a", "b", "a"], chain);
+            }
+            other => panic!("expected include cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_includes() {
+        let mut files = HashMap::new();
+        files.insert("a", "\n#include <b>\n");
+
+        match preprocess("a", &[], loader(&files)) {
+            Err(Error::InvalidInclude { name, line }) => {
+                assert_eq!("a", name);
+                assert_eq!(2, line);
+            }
+            other => panic!("expected invalid include, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn annotates_driver_logs() {
+        let files = vec![String::from("main.frag"), String::from("lib.glsl")];
+
+        assert_eq!(
+            "lib.glsl:3(12): error: `x' undeclared",
+            annotate_log("1:3(12): error: `x' undeclared", &files)
+        );
+        assert_eq!(
+            "main.frag(7) : error C0000: syntax error",
+            annotate_log("0(7) : error C0000: syntax error", &files)
+        );
+        assert_eq!(
+            "ERROR: lib.glsl:4: 'y' : undeclared identifier",
+            annotate_log("ERROR: 1:4: 'y' : undeclared identifier", &files)
+        );
+        assert_eq!("9:1(1): error", annotate_log("9:1(1): error", &files));
+        assert_eq!("error: no number", annotate_log("error: no number", &files));
+    }
+
+    #[test]
+    fn inserts_defines_after_version() {
+        let mut files = HashMap::new();
+        files.insert(
+            "main.frag",
+            "// header\n#version 330 core\nvoid main() {}\n",
+        );
+        files.insert("plain.frag", "void main() {}\n");
+        let defines = [("USE_TEXTURE", "1"), ("MAX_LIGHTS", "4")];
+
+        assert_eq!(
+            "#version 330 core\n#line 1 0\n// header\n#line 3 0\nvoid main() {}\n",
+            preprocess("main.frag", &[], loader(&files)).unwrap().source
+        );
+        assert_eq!(
+            "#version 330 core\n\
+             #define USE_TEXTURE 1\n\
+             #define MAX_LIGHTS 4\n\
+             #line 1 0\n\
+             // header\n\
+             #line 3 0\n\
+             void main() {}\n",
+            preprocess("main.frag", &defines, loader(&files))
+                .unwrap()
+                .source
+        );
+        assert_eq!(
+            "#define A \n#line 1 0\nvoid main() {}\n",
+            preprocess("plain.frag", &[("A", "")], loader(&files))
+                .unwrap()
+                .source
+        );
+    }
+
+    #[test]
+    fn inserts_defines_after_an_included_version() {
+        let mut files = HashMap::new();
+        files.insert("main.frag", "#include \"version.glsl\"\nvoid main() {}\n");
+        files.insert("version.glsl", "#version 330 core\n");
+
+        let result = preprocess("main.frag", &[("A", "1")], loader(&files)).unwrap();
+
+        assert_eq!(
+            "#version 330 core\n\
+             #define A 1\n\
+             #line 1 0\n\
+             #line 1 1\n\
+             #line 2 1\n\
+             #line 2 0\n\
+             void main() {}\n",
+            result.source
+        );
+    }
+}
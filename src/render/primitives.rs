@@ -0,0 +1,619 @@
+//! Procedural meshes centered on the origin with +Y up.
+//!
+//! Every generator returns a `MeshData` with counter-clockwise front faces, unit normals,
+//! UVs in `[0, 1]` and tangents pointing along increasing `u`. The one exception is the
+//! icosphere, whose triangles across the UV seam reach past `u = 1` and rely on the texture
+//! repeating.
+
+use super::{Mesh, MeshVertex, Topology};
+use crate::utils::{Vec2, Vec3};
+
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// Vertices and triangle list indices of a generated mesh.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn new() -> MeshData {
+        MeshData::default()
+    }
+
+    /// Uploads the data into a new triangle list `Mesh`.
+    pub fn to_mesh(&self) -> Mesh<MeshVertex> {
+        Mesh::with_indices(&self.vertices, &self.indices, Topology::Triangles)
+    }
+
+    /// Replaces zero normals with the area-weighted average of the adjacent face normals.
+    pub fn generate_normals(&mut self) {
+        let missing: Vec<bool> = self
+            .vertices
+            .iter()
+            .map(|v| v.normal == Vec3::zero())
+            .collect();
+        if !missing.contains(&true) {
+            return;
+        }
+
+        for tri in self.indices.chunks(3) {
+            let [a, b, c] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+            let face = (self.vertices[b].pos - self.vertices[a].pos)
+                .cross(self.vertices[c].pos - self.vertices[a].pos);
+            for &i in &[a, b, c] {
+                if missing[i] {
+                    self.vertices[i].normal += face;
+                }
+            }
+        }
+
+        for (vertex, _) in self.vertices.iter_mut().zip(missing).filter(|(_, m)| *m) {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+
+    /// Recomputes every tangent from the positions and UVs of the adjacent triangles.
+    pub fn generate_tangents(&mut self) {
+        let mut tangents = vec![Vec3::zero(); self.vertices.len()];
+        let mut bitangents = vec![Vec3::zero(); self.vertices.len()];
+
+        for tri in self.indices.chunks(3) {
+            let [a, b, c] = [tri[0] as usize, tri[1] as usize, tri[2] as usize];
+            let (va, vb, vc) = (&self.vertices[a], &self.vertices[b], &self.vertices[c]);
+            let (e1, e2) = (vb.pos - va.pos, vc.pos - va.pos);
+            let (d1, d2) = (vb.uv - va.uv, vc.uv - va.uv);
+            let det = d1.cross(d2);
+            if det.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let tangent = (e1 * d2.y - e2 * d1.y) / det;
+            let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+            for &i in &[a, b, c] {
+                tangents[i] += tangent;
+                bitangents[i] += bitangent;
+            }
+        }
+
+        for ((vertex, tangent), bitangent) in self.vertices.iter_mut().zip(tangents).zip(bitangents)
+        {
+            let normal = vertex.normal;
+            let mut t = (tangent - normal * normal.dot(tangent)).normalize();
+            if t == Vec3::zero() {
+                // No usable UVs, so any direction perpendicular to the normal will do.
+                let axis = if normal.x.abs() < 0.9 {
+                    Vec3::new(1.0, 0.0, 0.0)
+                } else {
+                    Vec3::new(0.0, 1.0, 0.0)
+                };
+                t = (axis - normal * normal.dot(axis)).normalize();
+            }
+            let w = if normal.cross(t).dot(bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            vertex.tangent = t.extend(w);
+        }
+    }
+
+    fn push(&mut self, pos: Vec3, normal: Vec3, uv: Vec2, tangent: Vec3) -> u32 {
+        self.vertices
+            .push(MeshVertex::new(pos, normal, uv, tangent.extend(1.0)));
+        (self.vertices.len() - 1) as u32
+    }
+
+    /// Adds a triangle unless two of its corners coincide, as they do at the poles of
+    /// surfaces of revolution.
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let pos = |i: u32| self.vertices[i as usize].pos;
+        let (pa, pb, pc) = (pos(a), pos(b), pos(c));
+        if (pb - pa).cross(pc - pa).length_squared() <= f32::EPSILON * f32::EPSILON {
+            return;
+        }
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    /// Adds a `u_segments` by `v_segments` grid of quads.
+    ///
+    /// `f(u, v)` gives the position, normal and tangent at `(u, v)`. The front faces are
+    /// counter-clockwise when `cross(d/du, d/dv)` points the same way as the normal.
+    fn grid<F>(&mut self, u_segments: u32, v_segments: u32, f: F)
+    where
+        F: Fn(f32, f32) -> (Vec3, Vec3, Vec3),
+    {
+        let base = self.vertices.len() as u32;
+        for j in 0..=v_segments {
+            let v = j as f32 / v_segments as f32;
+            for i in 0..=u_segments {
+                let u = i as f32 / u_segments as f32;
+                let (pos, normal, tangent) = f(u, v);
+                self.push(pos, normal, Vec2::new(u, v), tangent);
+            }
+        }
+
+        let row = u_segments + 1;
+        for j in 0..v_segments {
+            for i in 0..u_segments {
+                let a = base + j * row + i;
+                let b = a + 1;
+                let c = b + row;
+                let d = a + row;
+                self.triangle(a, b, c);
+                self.triangle(a, c, d);
+            }
+        }
+    }
+
+    /// Sweeps a profile around the Y axis.
+    ///
+    /// Each profile point is `(radius, y, normal)`, where `normal.x` is the outward component
+    /// and `normal.y` the vertical one. Points must run from bottom to top, and `v` is spread
+    /// along the profile by arc length.
+    fn lathe(&mut self, profile: &[(f32, f32, Vec2)], segments: u32) {
+        let mut lengths = vec![0.0];
+        for pair in profile.windows(2) {
+            let step = Vec2::new(pair[1].0 - pair[0].0, pair[1].1 - pair[0].1).length();
+            lengths.push(lengths[lengths.len() - 1] + step);
+        }
+        let total = lengths[lengths.len() - 1].max(f32::EPSILON);
+
+        let base = self.vertices.len() as u32;
+        for (&(radius, y, normal), length) in profile.iter().zip(&lengths) {
+            for i in 0..=segments {
+                let u = i as f32 / segments as f32;
+                let (sin, cos) = (u * 2.0 * PI).sin_cos();
+                let out = Vec3::new(sin, 0.0, cos);
+                self.push(
+                    Vec3::new(radius * sin, y, radius * cos),
+                    (out * normal.x + Vec3::new(0.0, normal.y, 0.0)).normalize(),
+                    Vec2::new(u, length / total),
+                    Vec3::new(cos, 0.0, -sin),
+                );
+            }
+        }
+
+        let row = segments + 1;
+        for j in 0..profile.len() as u32 - 1 {
+            for i in 0..segments {
+                let a = base + j * row + i;
+                let b = a + 1;
+                let c = b + row;
+                let d = a + row;
+                self.triangle(a, b, c);
+                self.triangle(a, c, d);
+            }
+        }
+    }
+
+    /// Adds a flat disc at height `y`, facing up or down.
+    fn disc(&mut self, radius: f32, y: f32, up: bool, segments: u32) {
+        let normal = Vec3::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+        let tangent = Vec3::new(1.0, 0.0, 0.0);
+        // Facing up, v runs towards -Z like on `plane`; facing down it runs towards +Z.
+        let v_sign = if up { -0.5 } else { 0.5 };
+
+        let center = self.push(Vec3::new(0.0, y, 0.0), normal, Vec2::new(0.5, 0.5), tangent);
+        for i in 0..=segments {
+            let (sin, cos) = (i as f32 / segments as f32 * 2.0 * PI).sin_cos();
+            self.push(
+                Vec3::new(radius * sin, y, radius * cos),
+                normal,
+                Vec2::new(0.5 + 0.5 * sin, 0.5 + v_sign * cos),
+                tangent,
+            );
+        }
+
+        for i in 0..segments {
+            let a = center + 1 + i;
+            if up {
+                self.triangle(center, a, a + 1);
+            } else {
+                self.triangle(center, a + 1, a);
+            }
+        }
+    }
+}
+
+/// An axis-aligned cube with edges of length `size` and one UV square per face.
+pub fn cube(size: f32) -> MeshData {
+    let half = size * 0.5;
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    let y = Vec3::new(0.0, 1.0, 0.0);
+    let z = Vec3::new(0.0, 0.0, 1.0);
+
+    // (normal, u direction), with v running along cross(normal, u).
+    let faces = [(x, -z), (-x, z), (y, x), (-y, x), (z, x), (-z, -x)];
+
+    let mut data = MeshData::new();
+    for &(normal, u_dir) in &faces {
+        let v_dir = normal.cross(u_dir);
+        data.grid(1, 1, |u, v| {
+            let pos = (normal + u_dir * (2.0 * u - 1.0) + v_dir * (2.0 * v - 1.0)) * half;
+            (pos, normal, u_dir)
+        });
+    }
+    data
+}
+
+/// A `width` by `depth` plane in the XZ plane facing +Y, split into `x_segments` by
+/// `z_segments` quads.
+pub fn plane(width: f32, depth: f32, x_segments: u32, z_segments: u32) -> MeshData {
+    let mut data = MeshData::new();
+    data.grid(x_segments.max(1), z_segments.max(1), |u, v| {
+        let pos = Vec3::new((u - 0.5) * width, 0.0, (0.5 - v) * depth);
+        (pos, Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0))
+    });
+    data
+}
+
+/// A sphere built from `segments` meridians and `rings` parallels.
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    let rings = rings.max(2);
+    let profile: Vec<_> = (0..=rings)
+        .map(|j| {
+            let (sin, cos) = (j as f32 / rings as f32 * PI).sin_cos();
+            (radius * sin, -radius * cos, Vec2::new(sin, -cos))
+        })
+        .collect();
+
+    let mut data = MeshData::new();
+    data.lathe(&profile, segments.max(3));
+    data
+}
+
+/// A sphere made by splitting each face of an icosahedron into four, `subdivisions` times.
+///
+/// Vertices along the UV seam are duplicated so textures wrap cleanly.
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+    let mut points: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .iter()
+    .map(|&p| Vec3::from(p).normalize())
+    .collect();
+
+    let mut faces: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32, points: &mut Vec<Vec3>| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push(((points[a as usize] + points[b as usize]) * 0.5).normalize());
+                (points.len() - 1) as u32
+            })
+        };
+
+        faces = faces
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let ab = midpoint(a, b, &mut points);
+                let bc = midpoint(b, c, &mut points);
+                let ca = midpoint(c, a, &mut points);
+                vec![[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut data = MeshData::new();
+    for p in &points {
+        let angle = p.x.atan2(p.z);
+        data.push(
+            *p * radius,
+            *p,
+            Vec2::new(
+                angle / (2.0 * PI) + 0.5,
+                p.y.clamp(-1.0, 1.0).asin() / PI + 0.5,
+            ),
+            Vec3::new(angle.cos(), 0.0, -angle.sin()),
+        );
+    }
+
+    // Triangles that straddle the seam get copies of their low-u corners with u + 1.
+    let mut wrapped = HashMap::new();
+    for face in &mut faces {
+        let us: Vec<f32> = face
+            .iter()
+            .map(|&i| data.vertices[i as usize].uv.x)
+            .collect();
+        let max_u = us.iter().cloned().fold(0.0, f32::max);
+        for (index, u) in face.iter_mut().zip(us) {
+            if max_u - u > 0.5 {
+                *index = *wrapped.entry(*index).or_insert_with(|| {
+                    let mut vertex = data.vertices[*index as usize];
+                    vertex.uv.x += 1.0;
+                    data.vertices.push(vertex);
+                    (data.vertices.len() - 1) as u32
+                });
+            }
+        }
+    }
+
+    // The longitude of a pole is arbitrary, so each triangle touching one gets its own copy
+    // with the average `u` and tangent of its other two corners.
+    for face in &mut faces {
+        for k in 0..3 {
+            let pole = data.vertices[face[k] as usize];
+            if pole.normal.y.abs() < 1.0 - 1e-6 {
+                continue;
+            }
+
+            let others = [
+                data.vertices[face[(k + 1) % 3] as usize],
+                data.vertices[face[(k + 2) % 3] as usize],
+            ];
+            let mut vertex = pole;
+            vertex.uv.x = (others[0].uv.x + others[1].uv.x) * 0.5;
+            vertex.tangent = (others[0].tangent.truncate() + others[1].tangent.truncate())
+                .normalize()
+                .extend(1.0);
+            data.vertices.push(vertex);
+            face[k] = (data.vertices.len() - 1) as u32;
+        }
+    }
+
+    for &[a, b, c] in &faces {
+        data.triangle(a, b, c);
+    }
+    data
+}
+
+/// A capped cylinder of `height` along the Y axis.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> MeshData {
+    let half = height * 0.5;
+    let segments = segments.max(3);
+    let side = Vec2::new(1.0, 0.0);
+
+    let mut data = MeshData::new();
+    data.lathe(&[(radius, -half, side), (radius, half, side)], segments);
+    data.disc(radius, half, true, segments);
+    data.disc(radius, -half, false, segments);
+    data
+}
+
+/// A cone of `height` along the Y axis with its tip at the top and a capped base.
+pub fn cone(radius: f32, height: f32, segments: u32) -> MeshData {
+    let half = height * 0.5;
+    let segments = segments.max(3);
+    let side = Vec2::new(height, radius).normalize();
+
+    let mut data = MeshData::new();
+    data.lathe(&[(radius, -half, side), (0.0, half, side)], segments);
+    data.disc(radius, -half, false, segments);
+    data
+}
+
+/// A torus around the Y axis, `major_radius` from its center to the middle of the tube.
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> MeshData {
+    let minor_segments = minor_segments.max(3);
+    // Starts on the outer equator and goes over the top, so the profile runs "upwards"
+    // as far as the lathe's winding is concerned.
+    let profile: Vec<_> = (0..=minor_segments)
+        .map(|j| {
+            let (sin, cos) = (j as f32 / minor_segments as f32 * 2.0 * PI).sin_cos();
+            (
+                major_radius + minor_radius * cos,
+                minor_radius * sin,
+                Vec2::new(cos, sin),
+            )
+        })
+        .collect();
+
+    let mut data = MeshData::new();
+    data.lathe(&profile, major_segments.max(3));
+    data
+}
+
+/// A cylinder of `height` along the Y axis with hemispheres of `rings` parallels on both
+/// ends, so the total height is `height + 2 * radius`.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let half = height * 0.5;
+    let rings = rings.max(1);
+
+    let hemisphere = |center: f32, from: f32| {
+        (0..=rings).map(move |j| {
+            let angle = from + j as f32 / rings as f32 * PI * 0.5;
+            let (sin, cos) = angle.sin_cos();
+            (radius * sin, center - radius * cos, Vec2::new(sin, -cos))
+        })
+    };
+    let profile: Vec<_> = hemisphere(-half, 0.0)
+        .chain(hemisphere(half, PI * 0.5))
+        .collect();
+
+    let mut data = MeshData::new();
+    data.lathe(&profile, segments.max(3));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all() -> Vec<(&'static str, MeshData)> {
+        vec![
+            ("cube", cube(2.0)),
+            ("plane", plane(2.0, 1.0, 4, 3)),
+            ("uv_sphere", uv_sphere(1.0, 16, 8)),
+            ("icosphere", icosphere(1.0, 2)),
+            ("cylinder", cylinder(0.5, 2.0, 12)),
+            ("cone", cone(0.5, 1.0, 12)),
+            ("torus", torus(1.0, 0.25, 16, 8)),
+            ("capsule", capsule(0.5, 1.0, 12, 4)),
+        ]
+    }
+
+    #[test]
+    fn vertices_are_well_formed() {
+        for (name, data) in all() {
+            assert!(!data.indices.is_empty(), "{} has no triangles", name);
+            assert_eq!(0, data.indices.len() % 3, "{}", name);
+            assert!(
+                data.indices
+                    .iter()
+                    .all(|&i| (i as usize) < data.vertices.len()),
+                "{} has out of range indices",
+                name
+            );
+
+            for v in &data.vertices {
+                let tangent = v.tangent.truncate();
+                assert!((v.normal.length() - 1.0).abs() < 1e-4, "{}: {:?}", name, v);
+                assert!((tangent.length() - 1.0).abs() < 1e-4, "{}: {:?}", name, v);
+                assert!(v.normal.dot(tangent).abs() < 1e-4, "{}: {:?}", name, v);
+                if name != "icosphere" {
+                    assert!(v.uv.x >= 0.0 && v.uv.x <= 1.0 + 1e-6, "{}: {:?}", name, v);
+                }
+                assert!(v.uv.y >= 0.0 && v.uv.y <= 1.0 + 1e-6, "{}: {:?}", name, v);
+            }
+        }
+    }
+
+    #[test]
+    fn triangles_face_along_their_normals() {
+        for (name, data) in all() {
+            for tri in data.indices.chunks(3) {
+                let v: Vec<&MeshVertex> = tri.iter().map(|&i| &data.vertices[i as usize]).collect();
+                let face = (v[1].pos - v[0].pos).cross(v[2].pos - v[0].pos);
+                let normal = v[0].normal + v[1].normal + v[2].normal;
+                assert!(
+                    face.dot(normal) > 0.0,
+                    "{} triangle {:?} is wound backwards",
+                    name,
+                    tri
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_follow_increasing_u() {
+        for (name, data) in all() {
+            for tri in data.indices.chunks(3) {
+                let v: Vec<&MeshVertex> = tri.iter().map(|&i| &data.vertices[i as usize]).collect();
+                let (e1, e2) = (v[1].pos - v[0].pos, v[2].pos - v[0].pos);
+                let (d1, d2) = (v[1].uv - v[0].uv, v[2].uv - v[0].uv);
+                let det = d1.cross(d2);
+                if det.abs() < 1e-6 {
+                    continue;
+                }
+                let tangent = (e1 * d2.y - e2 * d1.y) / det;
+                let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+                let expected = v[0].normal.cross(v[0].tangent.truncate()) * v[0].tangent.w;
+
+                assert!(
+                    tangent.dot(v[0].tangent.truncate()) > 0.0,
+                    "{} triangle {:?}",
+                    name,
+                    tri
+                );
+                assert!(bitangent.dot(expected) > 0.0, "{} triangle {:?}", name, tri);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_tangents_match_analytic_ones() {
+        for (name, data) in all() {
+            // The icosphere's pole and seam copies make its averaged tangents differ.
+            if name == "icosphere" {
+                continue;
+            }
+            let mut generated = data.clone();
+            generated.generate_tangents();
+
+            for (a, b) in data.vertices.iter().zip(&generated.vertices) {
+                assert!(
+                    a.tangent.truncate().dot(b.tangent.truncate()) > 0.5,
+                    "{}: {:?} vs {:?}",
+                    name,
+                    a,
+                    b
+                );
+                assert_eq!(a.tangent.w, b.tangent.w, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_normals_only_fill_missing_ones() {
+        let mut data = plane(1.0, 1.0, 1, 1);
+        data.vertices[0].normal = Vec3::zero();
+        data.vertices[1].normal = Vec3::new(1.0, 0.0, 0.0);
+        data.generate_normals();
+
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), data.vertices[0].normal);
+        assert_eq!(Vec3::new(1.0, 0.0, 0.0), data.vertices[1].normal);
+    }
+
+    #[test]
+    fn cube_has_four_vertices_per_face() {
+        let data = cube(1.0);
+
+        assert_eq!(24, data.vertices.len());
+        assert_eq!(36, data.indices.len());
+        assert!(data
+            .vertices
+            .iter()
+            .all(|v| v.pos.abs().max(Vec3::splat(0.5)) == Vec3::splat(0.5)));
+    }
+
+    #[test]
+    fn capsule_spans_its_full_height() {
+        let data = capsule(0.5, 1.0, 8, 4);
+        let min = data
+            .vertices
+            .iter()
+            .map(|v| v.pos.y)
+            .fold(f32::MAX, f32::min);
+        let max = data
+            .vertices
+            .iter()
+            .map(|v| v.pos.y)
+            .fold(f32::MIN, f32::max);
+
+        assert!((min + 1.0).abs() < 1e-5);
+        assert!((max - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,149 @@
+use gl::types::*;
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// On-disk cache of linked program binaries.
+///
+/// Entries are keyed by a hash of the driver and the preprocessed stage sources, so any
+/// change to either makes the old entry unreachable.
+pub struct ProgramBinaryCache {
+    dir: PathBuf,
+}
+
+impl ProgramBinaryCache {
+    pub fn new(dir: &Path) -> ProgramBinaryCache {
+        ProgramBinaryCache { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether the current context can save and load program binaries at all.
+    pub fn is_supported() -> bool {
+        if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+            return false;
+        }
+
+        let mut formats: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+        formats > 0
+    }
+
+    pub fn load(&self, key: u64) -> Option<(GLenum, Vec<u8>)> {
+        let data = fs::read(self.entry_path(key)).ok()?;
+        if data.len() < 4 {
+            return None;
+        }
+
+        let format = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        Some((format, data[4..].to_vec()))
+    }
+
+    pub fn store(&self, key: u64, format: GLenum, binary: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let mut data = Vec::with_capacity(binary.len() + 4);
+        data.extend_from_slice(&format.to_le_bytes());
+        data.extend_from_slice(binary);
+        fs::write(self.entry_path(key), data)
+    }
+
+    pub fn remove(&self, key: u64) -> io::Result<()> {
+        fs::remove_file(self.entry_path(key))
+    }
+
+    fn entry_path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", key))
+    }
+}
+
+/// Vendor, renderer and version of the current context.
+pub fn driver_string() -> String {
+    [gl::VENDOR, gl::RENDERER, gl::VERSION]
+        .iter()
+        .map(|&name| unsafe {
+            let s = gl::GetString(name);
+            if s.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(s as *const GLchar)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// FNV-1a hash of the driver string and each stage's kind and source.
+pub fn cache_key(driver: &str, stages: &[(GLenum, &str)]) -> u64 {
+    let mut hash = Fnv1a::new();
+    hash.write(driver.as_bytes());
+    for &(kind, source) in stages {
+        hash.write(&[0]);
+        hash.write(&kind.to_le_bytes());
+        hash.write(source.as_bytes());
+    }
+    hash.finish()
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= u64::from(b);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_depends_on_driver_and_sources() {
+        let stages = [(gl::VERTEX_SHADER, "void main() {}")];
+        let key = cache_key("Mesa", &stages);
+
+        assert_eq!(key, cache_key("Mesa", &stages));
+        assert_ne!(key, cache_key("NVIDIA", &stages));
+        assert_ne!(
+            key,
+            cache_key("Mesa", &[(gl::FRAGMENT_SHADER, "void main() {}")])
+        );
+        assert_ne!(
+            key,
+            cache_key("Mesa", &[(gl::VERTEX_SHADER, "void main() { }")])
+        );
+        assert_eq!(0xaf63_dc4c_8601_ec8c, cache_key("a", &[]));
+    }
+
+    #[test]
+    fn store_and_load_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("program_binary_{}", std::process::id()));
+        let cache = ProgramBinaryCache::new(&dir);
+
+        assert_eq!(None, cache.load(42));
+        cache.store(42, 0x8741, &[1, 2, 3]).unwrap();
+        assert_eq!(Some((0x8741, vec![1, 2, 3])), cache.load(42));
+        cache.remove(42).unwrap();
+        assert_eq!(None, cache.load(42));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
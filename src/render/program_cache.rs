@@ -0,0 +1,135 @@
+use super::{Error, Program, ProgramBinaryCache};
+use crate::resources::Resources;
+
+use std::collections::{BTreeMap, HashMap};
+
+type ProgramKey = (String, BTreeMap<String, String>);
+
+/// Programs loaded from resources, one per name and set of defines.
+#[derive(Default)]
+pub struct ProgramCache {
+    programs: HashMap<ProgramKey, Program>,
+    binary_cache: Option<ProgramBinaryCache>,
+}
+
+impl ProgramCache {
+    pub fn new() -> ProgramCache {
+        ProgramCache {
+            programs: HashMap::new(),
+            binary_cache: None,
+        }
+    }
+
+    /// A cache that loads programs through `binary_cache` when the driver supports it.
+    pub fn with_binary_cache(binary_cache: ProgramBinaryCache) -> ProgramCache {
+        ProgramCache {
+            programs: HashMap::new(),
+            binary_cache: Some(binary_cache),
+        }
+    }
+
+    /// Returns the program for `name` built with `defines`, loading it on first use.
+    ///
+    /// The order of `defines` does not matter; if a name repeats, the last value wins.
+    pub fn get_or_load(
+        &mut self,
+        res: &Resources,
+        name: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<&Program, Error> {
+        let key = program_key(name, defines);
+
+        if !self.programs.contains_key(&key) {
+            let defines: Vec<(&str, &str)> = key
+                .1
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect();
+            let program = match self.binary_cache {
+                Some(ref cache) => Program::from_res_cached(res, name, &defines, cache)?,
+                None => Program::from_res_with_defines(res, name, &defines)?,
+            };
+            self.programs.insert(key.clone(), program);
+        }
+
+        Ok(&self.programs[&key])
+    }
+
+    pub fn get(&self, name: &str, defines: &[(&str, &str)]) -> Option<&Program> {
+        self.programs.get(&program_key(name, defines))
+    }
+
+    /// Every resource used by a cached program, for handing to a `ResourceWatcher`.
+    pub fn sources(&self) -> Vec<&String> {
+        let mut sources: Vec<&String> = self
+            .programs
+            .values()
+            .flat_map(|p| p.sources().iter())
+            .collect();
+        sources.sort();
+        sources.dedup();
+        sources
+    }
+
+    /// Reloads the programs built from any of the `changed` resources.
+    ///
+    /// Programs that fail to rebuild are kept as they were and their errors returned. The
+    /// rebuilt binaries go into the binary cache, if there is one.
+    pub fn reload_changed(&mut self, res: &Resources, changed: &[String]) -> Vec<Error> {
+        let binary_cache = self.binary_cache.as_ref();
+        self.programs
+            .values_mut()
+            .filter(|p| p.sources().iter().any(|s| changed.contains(s)))
+            .filter_map(|p| match binary_cache {
+                Some(cache) => p.reload_cached(res, cache).err(),
+                None => p.reload(res).err(),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.programs.clear();
+    }
+}
+
+fn program_key(name: &str, defines: &[(&str, &str)]) -> ProgramKey {
+    let defines = defines
+        .iter()
+        .map(|&(k, v)| (String::from(k), String::from(v)))
+        .collect();
+
+    (String::from(name), defines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_ignores_define_order() {
+        assert_eq!(
+            program_key("shaders/lit", &[("A", "1"), ("B", "2")]),
+            program_key("shaders/lit", &[("B", "2"), ("A", "1")])
+        );
+        assert_eq!(
+            program_key("shaders/lit", &[("A", "2")]),
+            program_key("shaders/lit", &[("A", "1"), ("A", "2")])
+        );
+        assert_ne!(
+            program_key("shaders/lit", &[("A", "1")]),
+            program_key("shaders/lit", &[])
+        );
+        assert_ne!(
+            program_key("shaders/lit", &[]),
+            program_key("shaders/unlit", &[])
+        );
+    }
+}
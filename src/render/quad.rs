@@ -1,59 +1,72 @@
 use super::*;
 use crate::resources::Resources;
+use crate::utils::Mat4;
 
 pub struct Quad {
     program: Program,
-    _vbo: VertexBufferObject,
-    vao: VertexArray,
-    ibo: IndexBufferObject,
+    mesh: Mesh<Vertex>,
+    texture: Option<Texture2D>,
 }
 
 impl Quad {
+    /// A quad colored by its vertices.
     pub fn new(res: &Resources) -> Result<Quad, Error> {
-        let program = Program::from_res(res, "shaders/triangle")?;
+        Quad::build(res, "shaders/triangle", None)
+    }
+
+    /// A quad showing `texture` over its whole area.
+    pub fn textured(res: &Resources, texture: Texture2D) -> Result<Quad, Error> {
+        Quad::build(res, "shaders/textured", Some(texture))
+    }
 
+    fn build(res: &Resources, program_name: &str, texture: Option<Texture2D>) -> Result<Quad, Error> {
         let vertices: Vec<Vertex> = vec![
-            Vertex::new((-0.5,  0.5, 0.0).into(), (1.0, 0.0, 1.0).into()),
-            Vertex::new((-0.5, -0.5, 0.0).into(), (1.0, 0.0, 0.0).into()),
-            Vertex::new(( 0.5, -0.5, 0.0).into(), (0.0, 1.0, 0.0).into()),
-            Vertex::new(( 0.5,  0.5, 0.0).into(), (0.0, 0.0, 1.0).into()),
+            Vertex::with_uv((-0.5,  0.5, 0.0).into(), (1.0, 0.0, 1.0).into(), (0.0, 1.0).into()),
+            Vertex::with_uv((-0.5, -0.5, 0.0).into(), (1.0, 0.0, 0.0).into(), (0.0, 0.0).into()),
+            Vertex::with_uv(( 0.5, -0.5, 0.0).into(), (0.0, 1.0, 0.0).into(), (1.0, 0.0).into()),
+            Vertex::with_uv(( 0.5,  0.5, 0.0).into(), (0.0, 0.0, 1.0).into(), (1.0, 1.0).into()),
         ];
 
-        let indices: Vec<u32> = vec![
+        let indices: Vec<u8> = vec![
             0, 1, 2,
             0, 2, 3,
         ];
 
-        let vbo = ArrayBuffer::new();
-        vbo.bind();
-        vbo.buffer_static_data(&vertices);
+        let mesh = Mesh::with_indices(&vertices, &indices, Topology::Triangles);
+        let program = Program::from_res(res, program_name)?;
+        mesh.check_program(&program)?;
 
-        let vao = VertexArray::new();
-        vao.bind();
-        Vertex::vertex_attrib_pointers();
-        vbo.unbind();
-        vao.unbind();
+        Ok(Quad { program, mesh, texture })
+    }
 
-        let ibo = ElementArrayBuffer::new();
-        ibo.bind();
-        ibo.buffer_static_data(&indices);
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
 
-        Ok(Quad {
-            program,
-            _vbo: vbo,
-            vao,
-            ibo,
-        })
+    pub fn mesh(&self) -> &Mesh<Vertex> {
+        &self.mesh
     }
 
-    pub fn render(&self) {
-        self.program.bind();
-        self.ibo.bind();
-        self.vao.bind();
+    pub fn texture(&self) -> Option<&Texture2D> {
+        self.texture.as_ref()
+    }
+
+    /// Reloads the shaders, keeping the current program if they fail to build or no longer
+    /// match `Vertex`.
+    pub fn reload(&mut self, res: &Resources) -> Result<(), Error> {
+        self.program.reload_for::<Vertex>(res)
+    }
 
-        unsafe {
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as *const gl::types::GLvoid);
+    pub fn render(&self, view_projection: &Mat4) -> Result<(), Error> {
+        self.program.bind();
+        self.program.set_mat4("ViewProjection", view_projection)?;
+        if let Some(ref texture) = self.texture {
+            texture.bind_to_unit(0);
+            self.program.set_sampler("Texture", 0)?;
         }
+        self.mesh.draw();
+
+        Ok(())
     }
 }
 
@@ -0,0 +1,168 @@
+use gl::types::*;
+
+/// An active uniform or vertex attribute as reported by the linked program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveVariable {
+    pub name: String,
+    pub location: GLint,
+    pub gl_type: GLenum,
+    pub size: GLint,
+}
+
+pub fn active_uniforms(program_id: GLuint) -> Vec<ActiveVariable> {
+    query_active(
+        program_id,
+        gl::ACTIVE_UNIFORMS,
+        gl::ACTIVE_UNIFORM_MAX_LENGTH,
+        gl::GetActiveUniform,
+        gl::GetUniformLocation,
+    )
+}
+
+pub fn active_attributes(program_id: GLuint) -> Vec<ActiveVariable> {
+    query_active(
+        program_id,
+        gl::ACTIVE_ATTRIBUTES,
+        gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+        gl::GetActiveAttrib,
+        gl::GetAttribLocation,
+    )
+}
+
+type GetActiveFn =
+    unsafe fn(GLuint, GLuint, GLsizei, *mut GLsizei, *mut GLint, *mut GLenum, *mut GLchar);
+type GetLocationFn = unsafe fn(GLuint, *const GLchar) -> GLint;
+
+fn query_active(
+    program_id: GLuint,
+    count_param: GLenum,
+    max_len_param: GLenum,
+    get_active: GetActiveFn,
+    get_location: GetLocationFn,
+) -> Vec<ActiveVariable> {
+    let mut count: GLint = 0;
+    let mut max_len: GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program_id, count_param, &mut count);
+        gl::GetProgramiv(program_id, max_len_param, &mut max_len);
+    }
+
+    (0..count)
+        .map(|index| {
+            let mut buffer = vec![0u8; max_len.max(1) as usize + 1];
+            let mut len: GLsizei = 0;
+            let mut size: GLint = 0;
+            let mut gl_type: GLenum = 0;
+            let location = unsafe {
+                get_active(
+                    program_id,
+                    index as GLuint,
+                    max_len,
+                    &mut len,
+                    &mut size,
+                    &mut gl_type,
+                    buffer.as_mut_ptr() as *mut GLchar,
+                );
+                get_location(program_id, buffer.as_ptr() as *const GLchar)
+            };
+            buffer.truncate(len as usize);
+
+            ActiveVariable {
+                name: String::from_utf8_lossy(&buffer).into_owned(),
+                location,
+                gl_type,
+                size,
+            }
+        })
+        .collect()
+}
+
+/// Number of locations, components per location and component type of an attribute type.
+pub fn attribute_layout(gl_type: GLenum) -> Option<(GLint, GLint, GLenum)> {
+    Some(match gl_type {
+        gl::FLOAT => (1, 1, gl::FLOAT),
+        gl::FLOAT_VEC2 => (1, 2, gl::FLOAT),
+        gl::FLOAT_VEC3 => (1, 3, gl::FLOAT),
+        gl::FLOAT_VEC4 => (1, 4, gl::FLOAT),
+        gl::INT => (1, 1, gl::INT),
+        gl::INT_VEC2 => (1, 2, gl::INT),
+        gl::INT_VEC3 => (1, 3, gl::INT),
+        gl::INT_VEC4 => (1, 4, gl::INT),
+        gl::UNSIGNED_INT => (1, 1, gl::UNSIGNED_INT),
+        gl::UNSIGNED_INT_VEC2 => (1, 2, gl::UNSIGNED_INT),
+        gl::UNSIGNED_INT_VEC3 => (1, 3, gl::UNSIGNED_INT),
+        gl::UNSIGNED_INT_VEC4 => (1, 4, gl::UNSIGNED_INT),
+        gl::FLOAT_MAT2 => (2, 2, gl::FLOAT),
+        gl::FLOAT_MAT3 => (3, 3, gl::FLOAT),
+        gl::FLOAT_MAT4 => (4, 4, gl::FLOAT),
+        gl::FLOAT_MAT2x3 => (2, 3, gl::FLOAT),
+        gl::FLOAT_MAT2x4 => (2, 4, gl::FLOAT),
+        gl::FLOAT_MAT3x2 => (3, 2, gl::FLOAT),
+        gl::FLOAT_MAT3x4 => (3, 4, gl::FLOAT),
+        gl::FLOAT_MAT4x2 => (4, 2, gl::FLOAT),
+        gl::FLOAT_MAT4x3 => (4, 3, gl::FLOAT),
+        _ => return None,
+    })
+}
+
+pub fn gl_type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_MULTISAMPLE => "sampler2DMS",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute_layout_vectors_and_matrices() {
+        assert_eq!(Some((1, 3, gl::FLOAT)), attribute_layout(gl::FLOAT_VEC3));
+        assert_eq!(Some((1, 2, gl::INT)), attribute_layout(gl::INT_VEC2));
+        assert_eq!(Some((4, 4, gl::FLOAT)), attribute_layout(gl::FLOAT_MAT4));
+        assert_eq!(Some((2, 3, gl::FLOAT)), attribute_layout(gl::FLOAT_MAT2x3));
+        assert_eq!(None, attribute_layout(gl::SAMPLER_2D));
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!("vec3", gl_type_name(gl::FLOAT_VEC3));
+        assert_eq!("sampler2D", gl_type_name(gl::SAMPLER_2D));
+        assert_eq!("unknown", gl_type_name(0));
+    }
+}
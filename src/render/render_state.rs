@@ -0,0 +1,819 @@
+use super::BufferMask;
+use crate::utils::Vec4;
+
+use gl::types::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunc {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            CompareFunc::Never => gl::NEVER,
+            CompareFunc::Less => gl::LESS,
+            CompareFunc::Equal => gl::EQUAL,
+            CompareFunc::LessEqual => gl::LEQUAL,
+            CompareFunc::Greater => gl::GREATER,
+            CompareFunc::NotEqual => gl::NOTEQUAL,
+            CompareFunc::GreaterEqual => gl::GEQUAL,
+            CompareFunc::Always => gl::ALWAYS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthState {
+    pub func: CompareFunc,
+    /// Whether fragments that pass update the depth buffer.
+    pub write: bool,
+}
+
+impl Default for DepthState {
+    fn default() -> DepthState {
+        DepthState {
+            func: CompareFunc::Less,
+            write: true,
+        }
+    }
+}
+
+impl DepthState {
+    /// Keeps the nearest fragments when depth decreases with distance, as with
+    /// `Mat4::perspective_reverse_z`.
+    pub fn reverse_z() -> DepthState {
+        DepthState {
+            func: CompareFunc::Greater,
+            write: true,
+        }
+    }
+}
+
+/// The range of clip-space depth that is mapped to the depth buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipDepth {
+    /// GL's default: -1 at the near plane to 1 at the far plane.
+    NegativeOneToOne,
+    /// 0 to 1, which keeps the precision of floating-point depth buffers near 0 and is what
+    /// `Mat4::perspective_reverse_z` needs. Requires GL 4.5 or `ARB_clip_control`.
+    ZeroToOne,
+}
+
+impl ClipDepth {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            ClipDepth::NegativeOneToOne => gl::NEGATIVE_ONE_TO_ONE,
+            ClipDepth::ZeroToOne => gl::ZERO_TO_ONE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            BlendFactor::Zero => gl::ZERO,
+            BlendFactor::One => gl::ONE,
+            BlendFactor::SrcColor => gl::SRC_COLOR,
+            BlendFactor::OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+            BlendFactor::DstColor => gl::DST_COLOR,
+            BlendFactor::OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+            BlendFactor::SrcAlpha => gl::SRC_ALPHA,
+            BlendFactor::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            BlendFactor::DstAlpha => gl::DST_ALPHA,
+            BlendFactor::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+            BlendFactor::ConstantColor => gl::CONSTANT_COLOR,
+            BlendFactor::OneMinusConstantColor => gl::ONE_MINUS_CONSTANT_COLOR,
+            BlendFactor::ConstantAlpha => gl::CONSTANT_ALPHA,
+            BlendFactor::OneMinusConstantAlpha => gl::ONE_MINUS_CONSTANT_ALPHA,
+            BlendFactor::SrcAlphaSaturate => gl::SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendOp {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            BlendOp::Add => gl::FUNC_ADD,
+            BlendOp::Subtract => gl::FUNC_SUBTRACT,
+            BlendOp::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            BlendOp::Min => gl::MIN,
+            BlendOp::Max => gl::MAX,
+        }
+    }
+}
+
+/// `op(src * source, dst * destination)`, for either the color or the alpha channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendEquation {
+    pub op: BlendOp,
+    pub src: BlendFactor,
+    pub dst: BlendFactor,
+}
+
+impl BlendEquation {
+    pub fn add(src: BlendFactor, dst: BlendFactor) -> BlendEquation {
+        BlendEquation {
+            op: BlendOp::Add,
+            src,
+            dst,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlendState {
+    pub color: BlendEquation,
+    pub alpha: BlendEquation,
+    /// Used by the `Constant*` factors.
+    pub constant: Vec4,
+}
+
+impl BlendState {
+    /// Classic transparency for colors that are not premultiplied by alpha.
+    pub fn alpha() -> BlendState {
+        BlendState {
+            color: BlendEquation::add(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+            alpha: BlendEquation::add(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            constant: Vec4::zero(),
+        }
+    }
+
+    /// Transparency for colors already multiplied by their alpha.
+    pub fn premultiplied() -> BlendState {
+        BlendState {
+            color: BlendEquation::add(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            alpha: BlendEquation::add(BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            constant: Vec4::zero(),
+        }
+    }
+
+    /// Adds the source onto the destination, e.g. for light accumulation and particles.
+    pub fn additive() -> BlendState {
+        BlendState {
+            color: BlendEquation::add(BlendFactor::One, BlendFactor::One),
+            alpha: BlendEquation::add(BlendFactor::One, BlendFactor::One),
+            constant: Vec4::zero(),
+        }
+    }
+}
+
+/// Which faces culling discards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl CullMode {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            CullMode::Front => gl::FRONT,
+            CullMode::Back => gl::BACK,
+            CullMode::FrontAndBack => gl::FRONT_AND_BACK,
+        }
+    }
+}
+
+/// The winding order of front faces, as seen on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl FrontFace {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            FrontFace::CounterClockwise => gl::CCW,
+            FrontFace::Clockwise => gl::CW,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+    Fill,
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            StencilOp::Keep => gl::KEEP,
+            StencilOp::Zero => gl::ZERO,
+            StencilOp::Replace => gl::REPLACE,
+            StencilOp::Increment => gl::INCR,
+            StencilOp::IncrementWrap => gl::INCR_WRAP,
+            StencilOp::Decrement => gl::DECR,
+            StencilOp::DecrementWrap => gl::DECR_WRAP,
+            StencilOp::Invert => gl::INVERT,
+        }
+    }
+}
+
+/// The stencil test and update for one face.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilFace {
+    /// Compares `reference & read_mask` with `stored & read_mask`.
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    pub write_mask: u32,
+    /// Applied when the stencil test fails.
+    pub fail: StencilOp,
+    /// Applied when the stencil test passes but the depth test fails.
+    pub depth_fail: StencilOp,
+    /// Applied when both tests pass.
+    pub pass: StencilOp,
+}
+
+impl Default for StencilFace {
+    fn default() -> StencilFace {
+        StencilFace {
+            func: CompareFunc::Always,
+            reference: 0,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub front: StencilFace,
+    pub back: StencilFace,
+}
+
+impl StencilState {
+    /// The same test and update for front and back faces.
+    pub fn both(face: StencilFace) -> StencilState {
+        StencilState {
+            front: face,
+            back: face,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ColorMask {
+    pub const ALL: ColorMask = ColorMask {
+        red: true,
+        green: true,
+        blue: true,
+        alpha: true,
+    };
+    pub const NONE: ColorMask = ColorMask {
+        red: false,
+        green: false,
+        blue: false,
+        alpha: false,
+    };
+}
+
+/// A rectangle in window coordinates, from the bottom-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scissor {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Fixed-function state for a draw call. `None` disables a test or stage.
+///
+/// The default matches GL's initial state: nothing enabled, filled polygons and every color
+/// channel written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    pub depth: Option<DepthState>,
+    pub clip_depth: ClipDepth,
+    pub blend: Option<BlendState>,
+    pub cull: Option<CullMode>,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+    pub stencil: Option<StencilState>,
+    pub color_mask: ColorMask,
+    pub scissor: Option<Scissor>,
+}
+
+impl Default for RenderState {
+    fn default() -> RenderState {
+        RenderState {
+            depth: None,
+            clip_depth: ClipDepth::NegativeOneToOne,
+            blend: None,
+            cull: None,
+            front_face: FrontFace::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
+            stencil: None,
+            color_mask: ColorMask::ALL,
+            scissor: None,
+        }
+    }
+}
+
+/// What `Window::clear` fills each buffer with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearValues {
+    pub color: Vec4,
+    pub depth: f32,
+    pub stencil: i32,
+}
+
+impl Default for ClearValues {
+    fn default() -> ClearValues {
+        ClearValues {
+            color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            depth: 1.0,
+            stencil: 0,
+        }
+    }
+}
+
+/// One GL state call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Enable(GLenum, bool),
+    DepthFunc(GLenum),
+    DepthMask(bool),
+    ClipControl(GLenum),
+    BlendEquation(GLenum, GLenum),
+    BlendFunc([GLenum; 4]),
+    BlendColor(Vec4),
+    CullFace(GLenum),
+    FrontFace(GLenum),
+    PolygonMode(GLenum),
+    StencilFunc(GLenum, GLenum, i32, u32),
+    StencilOp(GLenum, [GLenum; 3]),
+    StencilMask(GLenum, u32),
+    ColorMask(ColorMask),
+    Scissor(Scissor),
+    ClearColor(Vec4),
+    ClearDepth(f32),
+    ClearStencil(i32),
+}
+
+impl Command {
+    fn execute(self) {
+        let boolean = |b: bool| if b { gl::TRUE } else { gl::FALSE };
+        unsafe {
+            match self {
+                Command::Enable(capability, true) => gl::Enable(capability),
+                Command::Enable(capability, false) => gl::Disable(capability),
+                Command::DepthFunc(func) => gl::DepthFunc(func),
+                Command::DepthMask(write) => gl::DepthMask(boolean(write)),
+                // Contexts without glClipControl are always in the default range.
+                Command::ClipControl(depth) if !gl::ClipControl::is_loaded() => assert_eq!(
+                    depth,
+                    gl::NEGATIVE_ONE_TO_ONE,
+                    "ClipDepth::ZeroToOne needs GL 4.5 or ARB_clip_control"
+                ),
+                Command::ClipControl(depth) => gl::ClipControl(gl::LOWER_LEFT, depth),
+                Command::BlendEquation(color, alpha) => gl::BlendEquationSeparate(color, alpha),
+                Command::BlendFunc([src, dst, src_alpha, dst_alpha]) => {
+                    gl::BlendFuncSeparate(src, dst, src_alpha, dst_alpha)
+                }
+                Command::BlendColor(c) => gl::BlendColor(c.x, c.y, c.z, c.w),
+                Command::CullFace(face) => gl::CullFace(face),
+                Command::FrontFace(winding) => gl::FrontFace(winding),
+                Command::PolygonMode(mode) => gl::PolygonMode(gl::FRONT_AND_BACK, mode),
+                Command::StencilFunc(face, func, reference, mask) => {
+                    gl::StencilFuncSeparate(face, func, reference, mask)
+                }
+                Command::StencilOp(face, [fail, depth_fail, pass]) => {
+                    gl::StencilOpSeparate(face, fail, depth_fail, pass)
+                }
+                Command::StencilMask(face, mask) => gl::StencilMaskSeparate(face, mask),
+                Command::ColorMask(mask) => gl::ColorMask(
+                    boolean(mask.red),
+                    boolean(mask.green),
+                    boolean(mask.blue),
+                    boolean(mask.alpha),
+                ),
+                Command::Scissor(rect) => gl::Scissor(
+                    rect.x,
+                    rect.y,
+                    rect.width as GLsizei,
+                    rect.height as GLsizei,
+                ),
+                Command::ClearColor(c) => gl::ClearColor(c.x, c.y, c.z, c.w),
+                Command::ClearDepth(depth) => gl::ClearDepth(f64::from(depth)),
+                Command::ClearStencil(stencil) => gl::ClearStencil(stencil),
+            }
+        }
+    }
+}
+
+/// Records `value` in `slot`, queuing `command` unless the GL state already holds it.
+fn update<T: PartialEq + Copy>(
+    slot: &mut Option<T>,
+    value: T,
+    commands: &mut Vec<Command>,
+    command: impl FnOnce(T) -> Command,
+) {
+    if *slot != Some(value) {
+        *slot = Some(value);
+        commands.push(command(value));
+    }
+}
+
+#[derive(Debug, Default)]
+struct StencilFaceCache {
+    func: Option<(CompareFunc, i32, u32)>,
+    ops: Option<[StencilOp; 3]>,
+    write_mask: Option<u32>,
+}
+
+impl StencilFaceCache {
+    fn update(&mut self, gl_face: GLenum, face: &StencilFace, commands: &mut Vec<Command>) {
+        update(
+            &mut self.func,
+            (face.func, face.reference, face.read_mask),
+            commands,
+            |(func, reference, mask)| {
+                Command::StencilFunc(gl_face, func.gl_enum(), reference, mask)
+            },
+        );
+        update(
+            &mut self.ops,
+            [face.fail, face.depth_fail, face.pass],
+            commands,
+            |ops| {
+                Command::StencilOp(
+                    gl_face,
+                    [ops[0].gl_enum(), ops[1].gl_enum(), ops[2].gl_enum()],
+                )
+            },
+        );
+        update(&mut self.write_mask, face.write_mask, commands, |mask| {
+            Command::StencilMask(gl_face, mask)
+        });
+    }
+}
+
+/// The GL state last set through it, so that only the calls that change something are made.
+///
+/// Everything starts out unknown, so the first `apply` sets it all. State changed behind the
+/// cache's back has to be followed by `invalidate`.
+#[derive(Debug, Default)]
+pub(super) struct StateCache {
+    depth_test: Option<bool>,
+    depth_func: Option<CompareFunc>,
+    depth_write: Option<bool>,
+    clip_depth: Option<ClipDepth>,
+    blend: Option<bool>,
+    blend_equation: Option<(BlendOp, BlendOp)>,
+    blend_func: Option<[BlendFactor; 4]>,
+    blend_color: Option<Vec4>,
+    cull: Option<bool>,
+    cull_face: Option<CullMode>,
+    front_face: Option<FrontFace>,
+    polygon_mode: Option<PolygonMode>,
+    stencil_test: Option<bool>,
+    stencil_front: StencilFaceCache,
+    stencil_back: StencilFaceCache,
+    color_mask: Option<ColorMask>,
+    scissor_test: Option<bool>,
+    scissor: Option<Scissor>,
+    clear_color: Option<Vec4>,
+    clear_depth: Option<f32>,
+    clear_stencil: Option<i32>,
+}
+
+impl StateCache {
+    /// Makes `state` current.
+    pub(super) fn apply(&mut self, state: &RenderState) {
+        self.changes(state).into_iter().for_each(Command::execute);
+    }
+
+    /// Clears `buffers` of the bound framebuffer to `values`.
+    ///
+    /// The write masks of the buffers being cleared are enabled first; the scissor box still
+    /// applies.
+    pub(super) fn clear(&mut self, buffers: BufferMask, values: &ClearValues) {
+        self.clear_changes(buffers, values)
+            .into_iter()
+            .for_each(Command::execute);
+        unsafe {
+            gl::Clear(buffers.gl_bits());
+        }
+    }
+
+    /// Enables the write masks `buffers` need to be cleared, e.g. before `glClearBuffer*`.
+    pub(super) fn enable_writes(&mut self, buffers: BufferMask) {
+        self.write_changes(buffers)
+            .into_iter()
+            .for_each(Command::execute);
+    }
+
+    /// Forgets everything, so the next `apply` sets all state again.
+    pub(super) fn invalidate(&mut self) {
+        *self = StateCache::default();
+    }
+
+    fn changes(&mut self, state: &RenderState) -> Vec<Command> {
+        let mut commands = Vec::new();
+        let c = &mut commands;
+
+        // Parameters of disabled stages are left alone, so toggling one back on with the same
+        // settings costs a single call.
+        update(&mut self.depth_test, state.depth.is_some(), c, |on| {
+            Command::Enable(gl::DEPTH_TEST, on)
+        });
+        if let Some(depth) = state.depth {
+            update(&mut self.depth_func, depth.func, c, |func| {
+                Command::DepthFunc(func.gl_enum())
+            });
+            update(&mut self.depth_write, depth.write, c, Command::DepthMask);
+        }
+        update(&mut self.clip_depth, state.clip_depth, c, |depth| {
+            Command::ClipControl(depth.gl_enum())
+        });
+
+        update(&mut self.blend, state.blend.is_some(), c, |on| {
+            Command::Enable(gl::BLEND, on)
+        });
+        if let Some(blend) = state.blend {
+            update(
+                &mut self.blend_equation,
+                (blend.color.op, blend.alpha.op),
+                c,
+                |(color, alpha)| Command::BlendEquation(color.gl_enum(), alpha.gl_enum()),
+            );
+            let factors = [
+                blend.color.src,
+                blend.color.dst,
+                blend.alpha.src,
+                blend.alpha.dst,
+            ];
+            update(&mut self.blend_func, factors, c, |f| {
+                Command::BlendFunc([
+                    f[0].gl_enum(),
+                    f[1].gl_enum(),
+                    f[2].gl_enum(),
+                    f[3].gl_enum(),
+                ])
+            });
+            update(
+                &mut self.blend_color,
+                blend.constant,
+                c,
+                Command::BlendColor,
+            );
+        }
+
+        update(&mut self.cull, state.cull.is_some(), c, |on| {
+            Command::Enable(gl::CULL_FACE, on)
+        });
+        if let Some(cull) = state.cull {
+            update(&mut self.cull_face, cull, c, |face| {
+                Command::CullFace(face.gl_enum())
+            });
+        }
+        update(&mut self.front_face, state.front_face, c, |winding| {
+            Command::FrontFace(winding.gl_enum())
+        });
+        update(&mut self.polygon_mode, state.polygon_mode, c, |mode| {
+            Command::PolygonMode(mode.gl_enum())
+        });
+
+        update(&mut self.stencil_test, state.stencil.is_some(), c, |on| {
+            Command::Enable(gl::STENCIL_TEST, on)
+        });
+        if let Some(stencil) = state.stencil {
+            self.stencil_front.update(gl::FRONT, &stencil.front, c);
+            self.stencil_back.update(gl::BACK, &stencil.back, c);
+        }
+
+        update(
+            &mut self.color_mask,
+            state.color_mask,
+            c,
+            Command::ColorMask,
+        );
+
+        update(&mut self.scissor_test, state.scissor.is_some(), c, |on| {
+            Command::Enable(gl::SCISSOR_TEST, on)
+        });
+        if let Some(scissor) = state.scissor {
+            update(&mut self.scissor, scissor, c, Command::Scissor);
+        }
+
+        commands
+    }
+
+    fn write_changes(&mut self, buffers: BufferMask) -> Vec<Command> {
+        let mut commands = Vec::new();
+        let c = &mut commands;
+        if buffers.color {
+            update(&mut self.color_mask, ColorMask::ALL, c, Command::ColorMask);
+        }
+        if buffers.depth {
+            update(&mut self.depth_write, true, c, Command::DepthMask);
+        }
+        if buffers.stencil {
+            // Clears use the front face's stencil write mask.
+            update(&mut self.stencil_front.write_mask, !0, c, |mask| {
+                Command::StencilMask(gl::FRONT, mask)
+            });
+        }
+        commands
+    }
+
+    fn clear_changes(&mut self, buffers: BufferMask, values: &ClearValues) -> Vec<Command> {
+        let mut commands = self.write_changes(buffers);
+        let c = &mut commands;
+        if buffers.color {
+            update(&mut self.clear_color, values.color, c, Command::ClearColor);
+        }
+        if buffers.depth {
+            update(&mut self.clear_depth, values.depth, c, Command::ClearDepth);
+        }
+        if buffers.stencil {
+            update(
+                &mut self.clear_stencil,
+                values.stencil,
+                c,
+                Command::ClearStencil,
+            );
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_apply_sets_everything_and_repeats_set_nothing() {
+        let mut cache = StateCache::default();
+        let state = RenderState {
+            depth: Some(DepthState::default()),
+            blend: Some(BlendState::alpha()),
+            ..RenderState::default()
+        };
+
+        let first = cache.changes(&state);
+        assert!(first.contains(&Command::Enable(gl::DEPTH_TEST, true)));
+        assert!(first.contains(&Command::DepthFunc(gl::LESS)));
+        assert!(first.contains(&Command::Enable(gl::STENCIL_TEST, false)));
+        assert!(cache.changes(&state).is_empty());
+
+        cache.invalidate();
+        assert_eq!(first, cache.changes(&state));
+    }
+
+    #[test]
+    fn only_changed_state_is_set() {
+        let mut cache = StateCache::default();
+        let opaque = RenderState {
+            depth: Some(DepthState::default()),
+            cull: Some(CullMode::Back),
+            ..RenderState::default()
+        };
+        let transparent = RenderState {
+            depth: Some(DepthState {
+                write: false,
+                ..DepthState::default()
+            }),
+            blend: Some(BlendState::premultiplied()),
+            ..opaque
+        };
+        cache.changes(&opaque);
+
+        let to_transparent = cache.changes(&transparent);
+        assert_eq!(5, to_transparent.len());
+        assert_eq!(Command::DepthMask(false), to_transparent[0]);
+        assert_eq!(Command::Enable(gl::BLEND, true), to_transparent[1]);
+
+        // Blend settings are kept while blending is off.
+        cache.changes(&opaque);
+        assert_eq!(
+            vec![Command::DepthMask(false), Command::Enable(gl::BLEND, true)],
+            cache.changes(&transparent)
+        );
+    }
+
+    #[test]
+    fn clip_depth_is_set_like_other_state() {
+        let mut cache = StateCache::default();
+        let state = RenderState {
+            depth: Some(DepthState::reverse_z()),
+            clip_depth: ClipDepth::ZeroToOne,
+            ..RenderState::default()
+        };
+
+        let first = cache.changes(&RenderState::default());
+        let reverse_z = cache.changes(&state);
+
+        assert!(first.contains(&Command::ClipControl(gl::NEGATIVE_ONE_TO_ONE)));
+        assert_eq!(
+            vec![
+                Command::Enable(gl::DEPTH_TEST, true),
+                Command::DepthFunc(gl::GREATER),
+                Command::DepthMask(true),
+                Command::ClipControl(gl::ZERO_TO_ONE),
+            ],
+            reverse_z
+        );
+    }
+
+    #[test]
+    fn clearing_enables_the_masks_it_needs() {
+        let mut cache = StateCache::default();
+        cache.changes(&RenderState {
+            depth: Some(DepthState {
+                func: CompareFunc::LessEqual,
+                write: false,
+            }),
+            color_mask: ColorMask::NONE,
+            ..RenderState::default()
+        });
+
+        let values = ClearValues::default();
+        let changes = cache.clear_changes(BufferMask::COLOR | BufferMask::DEPTH, &values);
+        assert_eq!(
+            vec![
+                Command::ColorMask(ColorMask::ALL),
+                Command::DepthMask(true),
+                Command::ClearColor(values.color),
+                Command::ClearDepth(1.0),
+            ],
+            changes
+        );
+        assert!(cache
+            .clear_changes(BufferMask::COLOR | BufferMask::DEPTH, &values)
+            .is_empty());
+    }
+}
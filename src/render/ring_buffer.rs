@@ -0,0 +1,194 @@
+use super::buffer::{Buffer, BufferType, BufferTypeArray, BufferTypeElementArray};
+use gl::types::*;
+
+pub type VertexRingBuffer = RingBuffer<BufferTypeArray>;
+/// Indices pushed into it are drawn with `draw_elements_at` on its `buffer`, as the buffer
+/// does not track them as its index count.
+pub type IndexRingBuffer = RingBuffer<BufferTypeElementArray>;
+
+/// A persistently mapped buffer split into one region per frame in flight.
+///
+/// Each frame writes into its own region with `push`, and `end_frame` fences it. When the
+/// ring comes back around, `begin_frame` waits on that fence so the GPU is done reading the
+/// region before it is overwritten.
+pub struct RingBuffer<B>
+where
+    B: BufferType,
+{
+    buffer: Buffer<B>,
+    ptr: *mut u8,
+    region_size: usize,
+    fences: Vec<GLsync>,
+    region: usize,
+    offset: usize,
+}
+
+impl<B> RingBuffer<B>
+where
+    B: BufferType,
+{
+    /// Creates a ring of `frames` regions of `region_size` bytes each.
+    ///
+    /// Returns `None` if the context has no `glBufferStorage`, refuses the mapping, or the
+    /// total size overflows. Leaves the buffer bound.
+    pub fn new(region_size: usize, frames: usize) -> Option<RingBuffer<B>> {
+        assert!(frames > 0, "a ring buffer needs at least one frame");
+
+        if !gl::BufferStorage::is_loaded() || !gl::FenceSync::is_loaded() {
+            return None;
+        }
+
+        let size = region_size.checked_mul(frames)?;
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+
+        let buffer = Buffer::new();
+        buffer.bind();
+        buffer.buffer_storage(size, flags);
+
+        let ptr = unsafe { gl::MapBufferRange(B::BUFFER_TYPE, 0, size as GLsizeiptr, flags) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        Some(RingBuffer {
+            buffer,
+            ptr: ptr as *mut u8,
+            region_size,
+            fences: vec![::std::ptr::null(); frames],
+            region: 0,
+            offset: 0,
+        })
+    }
+
+    pub fn buffer(&self) -> &Buffer<B> {
+        &self.buffer
+    }
+
+    pub fn region_size(&self) -> usize {
+        self.region_size
+    }
+
+    pub fn frames(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// Bytes still free in the current frame's region.
+    pub fn remaining(&self) -> usize {
+        self.region_size - self.offset
+    }
+
+    /// Moves on to the next region, waiting for the GPU to finish with it first.
+    pub fn begin_frame(&mut self) {
+        self.region = (self.region + 1) % self.fences.len();
+        self.offset = 0;
+
+        let fence = ::std::mem::replace(&mut self.fences[self.region], ::std::ptr::null());
+        if fence.is_null() {
+            return;
+        }
+
+        unsafe {
+            let mut flags = 0;
+            loop {
+                match gl::ClientWaitSync(fence, flags, 1_000_000) {
+                    gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED | gl::WAIT_FAILED => break,
+                    _ => flags = gl::SYNC_FLUSH_COMMANDS_BIT,
+                }
+            }
+            gl::DeleteSync(fence);
+        }
+    }
+
+    /// Fences the current region once the draws reading it have been issued.
+    pub fn end_frame(&mut self) {
+        let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        let old = ::std::mem::replace(&mut self.fences[self.region], fence);
+        if !old.is_null() {
+            unsafe {
+                gl::DeleteSync(old);
+            }
+        }
+    }
+
+    /// Copies `data` into the current region and returns its byte offset in the buffer.
+    pub fn push<T: Copy>(&mut self, data: &[T]) -> Option<usize> {
+        self.push_aligned(data, ::std::mem::align_of::<T>())
+    }
+
+    /// Like `push`, with the offset in the buffer rounded up to `align` bytes, e.g.
+    /// `GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT` for uniform blocks.
+    ///
+    /// Returns `None` if the data does not fit in what is left of the region.
+    pub fn push_aligned<T: Copy>(&mut self, data: &[T], align: usize) -> Option<usize> {
+        let size = ::std::mem::size_of_val(data);
+        let align = align.max(::std::mem::align_of::<T>());
+        let offset = place(
+            self.region * self.region_size,
+            self.offset,
+            self.region_size,
+            size,
+            align,
+        )?;
+        unsafe {
+            ::std::ptr::copy_nonoverlapping(data.as_ptr() as *const u8, self.ptr.add(offset), size);
+        }
+        self.offset = offset + size - self.region * self.region_size;
+
+        Some(offset)
+    }
+}
+
+impl<B> Drop for RingBuffer<B>
+where
+    B: BufferType,
+{
+    fn drop(&mut self) {
+        for fence in &self.fences {
+            if !fence.is_null() {
+                unsafe {
+                    gl::DeleteSync(*fence);
+                }
+            }
+        }
+    }
+}
+
+fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// Finds where `size` bytes go in the region starting at `base`, `used` bytes of which are
+/// taken. The returned offset is aligned within the whole buffer, not just the region, since
+/// regions need not be a multiple of `align` long.
+fn place(base: usize, used: usize, region_size: usize, size: usize, align: usize) -> Option<usize> {
+    let offset = align_up(base.checked_add(used)?, align);
+    if offset.checked_add(size)? > base + region_size {
+        return None;
+    }
+    Some(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_up_rounds_to_multiples() {
+        assert_eq!(0, align_up(0, 256));
+        assert_eq!(256, align_up(1, 256));
+        assert_eq!(256, align_up(256, 256));
+        assert_eq!(12, align_up(9, 4));
+        assert_eq!(7, align_up(7, 1));
+    }
+
+    #[test]
+    fn placement_aligns_the_offset_in_the_buffer() {
+        // The second of two 1000-byte regions starts at 1000, which is not a multiple of 256.
+        assert_eq!(Some(1024), place(1000, 0, 1000, 64, 256));
+        assert_eq!(Some(1280), place(1000, 88, 1000, 64, 256));
+        assert_eq!(Some(1792), place(1000, 700, 1000, 208, 256));
+        assert_eq!(None, place(1000, 700, 1000, 209, 256));
+        assert_eq!(Some(0), place(0, 0, 1000, 1000, 256));
+        assert_eq!(None, place(0, 0, 1000, usize::MAX, 256));
+    }
+}
@@ -1,46 +1,186 @@
+use super::compile_log;
+use super::error::Error;
+use super::preprocessor::{self, PreprocessedSource};
+use super::program_binary::{self, ProgramBinaryCache};
+use super::reflection::{self, ActiveVariable};
+use super::VertexLayout;
 use crate::resources;
+use crate::utils::{Mat3, Mat4, Vec2, Vec3, Vec4};
 
 use gl::types::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 
 use resources::Resources;
 
-#[derive(Debug)]
-pub enum Error {
-    ResourceLoad {
-        name: String,
-        inner: resources::Error,
-    },
-    CanNotDetermineShaderTypeForResource {
-        name: String,
-    },
-    CompileError {
-        name: String,
-        message: String,
-    },
-    LinkError {
-        name: String,
-        message: String,
-    },
-}
-
 pub struct Program {
     id: GLuint,
+    name: String,
+    uniforms: Vec<ActiveVariable>,
+    attributes: Vec<ActiveVariable>,
+    uniform_locations: RefCell<HashMap<String, GLint>>,
+    sources: Vec<String>,
+    defines: Vec<(String, String)>,
 }
 
 impl Program {
+    /// Builds a program from every `name.<stage>` resource that exists, e.g. `name.vert`
+    /// and `name.frag`, or just `name.comp`.
     pub fn from_res(res: &Resources, name: &str) -> Result<Program, Error> {
-        const POSSIBLE_EXT: [&str; 2] = [".vert", ".frag"];
+        Program::from_res_with_defines(res, name, &[])
+    }
+
+    /// Like `from_res`, with `#define NAME VALUE` lines added after `#version` in every stage.
+    pub fn from_res_with_defines(
+        res: &Resources,
+        name: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Program, Error> {
+        Program::load(res, name, defines, None)
+    }
+
+    /// Like `from_res_with_defines`, but reuses a binary from `cache` when the driver and
+    /// preprocessed sources match, and stores a new one after compiling otherwise.
+    pub fn from_res_cached(
+        res: &Resources,
+        name: &str,
+        defines: &[(&str, &str)],
+        cache: &ProgramBinaryCache,
+    ) -> Result<Program, Error> {
+        Program::load(res, name, defines, Some(cache))
+    }
 
-        let shaders = POSSIBLE_EXT
+    fn load(
+        res: &Resources,
+        name: &str,
+        defines: &[(&str, &str)],
+        cache: Option<&ProgramBinaryCache>,
+    ) -> Result<Program, Error> {
+        let sources: Vec<String> = SHADER_EXTENSIONS
             .iter()
-            .map(|ext| Shader::from_res(res, &format!("{}{}", name, ext)))
-            .collect::<Result<Vec<Shader>, Error>>()?;
+            .map(|&(ext, _)| format!("{}{}", name, ext))
+            .filter(|source| res.exists(source))
+            .collect();
 
-        Program::from_shaders(&shaders[..], name)
+        if sources.is_empty() {
+            return Err(Error::NoShaderStagesFound {
+                name: String::from(name),
+            });
+        }
+        if sources.len() > 1 && sources.iter().any(|source| source.ends_with(".comp")) {
+            return Err(Error::ComputeWithGraphicsStages {
+                name: String::from(name),
+            });
+        }
+
+        let stages = sources
+            .iter()
+            .map(|source| Shader::preprocess_res(res, source, defines))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // The key needs GL string queries and hashing every source, so only compute it when
+        // there is a cache to look in.
+        let cache = cache
+            .filter(|_| ProgramBinaryCache::is_supported())
+            .map(|cache| {
+                let key = program_binary::cache_key(
+                    &program_binary::driver_string(),
+                    &stages
+                        .iter()
+                        .map(|(kind, preprocessed)| (*kind, preprocessed.source.as_str()))
+                        .collect::<Vec<_>>(),
+                );
+                (cache, key)
+            });
+
+        let cached = cache
+            .and_then(|(c, key)| c.load(key))
+            .and_then(|(format, binary)| Program::from_binary(name, format, &binary));
+
+        let mut program = match cached {
+            Some(program) => program,
+            None => {
+                let shaders = sources
+                    .iter()
+                    .zip(&stages)
+                    .map(|(source, (kind, preprocessed))| {
+                        Shader::from_preprocessed(source, *kind, preprocessed)
+                    })
+                    .collect::<Result<Vec<Shader>, Error>>()?;
+
+                let program = Program::link(&shaders[..], name, cache.is_some())?;
+                if let (Some((cache, key)), Some((format, binary))) = (cache, program.binary()) {
+                    // The cache is best effort, failing to write it only costs a recompile.
+                    let _ = cache.store(key, format, &binary);
+                }
+                program
+            }
+        };
+
+        program.defines = defines
+            .iter()
+            .map(|&(k, v)| (String::from(k), String::from(v)))
+            .collect();
+        // Stage files that do not exist yet are listed too, so creating one triggers a reload.
+        let candidates = SHADER_EXTENSIONS
+            .iter()
+            .map(|&(ext, _)| format!("{}{}", name, ext));
+        let files = stages
+            .iter()
+            .flat_map(|(_, preprocessed)| preprocessed.files.iter().cloned());
+        for source in files.chain(candidates) {
+            if !program.sources.contains(&source) {
+                program.sources.push(source);
+            }
+        }
+        Ok(program)
+    }
+
+    /// Rebuilds the program from its resources, keeping the current one if that fails.
+    pub fn reload(&mut self, res: &Resources) -> Result<(), Error> {
+        self.rebuild(res, None, None)
+    }
+
+    /// Like `reload`, also keeping the current program if the new one's vertex inputs can no
+    /// longer be fed from `V`.
+    pub fn reload_for<V: VertexLayout>(&mut self, res: &Resources) -> Result<(), Error> {
+        self.rebuild(res, None, Some(&V::vertex_attribs()))
+    }
+
+    /// Like `reload`, going through `cache` like `from_res_cached`.
+    pub fn reload_cached(
+        &mut self,
+        res: &Resources,
+        cache: &ProgramBinaryCache,
+    ) -> Result<(), Error> {
+        self.rebuild(res, Some(cache), None)
+    }
+
+    fn rebuild(
+        &mut self,
+        res: &Resources,
+        cache: Option<&ProgramBinaryCache>,
+        attribs: Option<&[(usize, i32, u32)]>,
+    ) -> Result<(), Error> {
+        let defines: Vec<(&str, &str)> = self
+            .defines
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let program = Program::load(res, &self.name, &defines, cache)?;
+        if let Some(attribs) = attribs {
+            program.check_vertex_attribs(attribs)?;
+        }
+        *self = program;
+        Ok(())
     }
 
     pub fn from_shaders(shaders: &[Shader], name: &str) -> Result<Program, Error> {
+        Program::link(shaders, name, false)
+    }
+
+    fn link(shaders: &[Shader], name: &str, retrievable: bool) -> Result<Program, Error> {
         let program_id = unsafe { gl::CreateProgram() };
 
         for shader in shaders {
@@ -49,6 +189,16 @@ impl Program {
             }
         }
 
+        if retrievable {
+            unsafe {
+                gl::ProgramParameteri(
+                    program_id,
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as GLint,
+                );
+            }
+        }
+
         unsafe {
             gl::LinkProgram(program_id);
         }
@@ -64,20 +214,17 @@ impl Program {
                 gl::GetProgramiv(program_id, gl::INFO_LOG_LENGTH, &mut len);
             }
 
-            let error = create_whitespace_cstring_with_len(len as usize);
+            let error = read_info_log(len, |len, written, buffer| unsafe {
+                gl::GetProgramInfoLog(program_id, len, written, buffer);
+            });
 
             unsafe {
-                gl::GetProgramInfoLog(
-                    program_id,
-                    len,
-                    std::ptr::null_mut(),
-                    error.as_ptr() as *mut GLchar,
-                );
+                gl::DeleteProgram(program_id);
             }
 
             return Err(Error::LinkError {
                 name: String::from(name),
-                message: error.to_string_lossy().into_owned(),
+                message: error,
             });
         }
 
@@ -87,7 +234,93 @@ impl Program {
             }
         }
 
-        Ok(Program { id: program_id })
+        Ok(Program::from_linked(program_id, name))
+    }
+
+    /// Loads a binary from `glGetProgramBinary`, or returns `None` if the driver rejects it.
+    pub fn from_binary(name: &str, format: GLenum, binary: &[u8]) -> Option<Program> {
+        let program_id = unsafe { gl::CreateProgram() };
+
+        let mut success: GLint = 0;
+        unsafe {
+            gl::ProgramBinary(
+                program_id,
+                format,
+                binary.as_ptr() as *const GLvoid,
+                binary.len() as GLsizei,
+            );
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+        }
+
+        if success == 0 {
+            unsafe {
+                gl::DeleteProgram(program_id);
+            }
+            return None;
+        }
+
+        Some(Program::from_linked(program_id, name))
+    }
+
+    /// The driver's binary for this program, if it has one to give.
+    pub fn binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        let mut len: GLint = 0;
+        unsafe {
+            gl::GetProgramiv(self.id, gl::PROGRAM_BINARY_LENGTH, &mut len);
+        }
+        if len <= 0 {
+            return None;
+        }
+
+        let mut binary = vec![0u8; len as usize];
+        let mut written: GLsizei = 0;
+        let mut format: GLenum = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                self.id,
+                len,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut GLvoid,
+            );
+        }
+        binary.truncate(written.max(0) as usize);
+
+        if binary.is_empty() {
+            None
+        } else {
+            Some((format, binary))
+        }
+    }
+
+    fn from_linked(program_id: GLuint, name: &str) -> Program {
+        Program {
+            id: program_id,
+            name: String::from(name),
+            uniforms: reflection::active_uniforms(program_id),
+            attributes: reflection::active_attributes(program_id),
+            uniform_locations: RefCell::new(HashMap::new()),
+            sources: Vec::new(),
+            defines: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    /// The resources this program was built from, followed by the `name.<stage>` files that do
+    /// not exist yet. Empty unless created by `from_res`.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
     }
 
     pub fn bind(&self) {
@@ -95,6 +328,339 @@ impl Program {
             gl::UseProgram(self.id);
         }
     }
+
+    pub fn uniforms(&self) -> &[ActiveVariable] {
+        &self.uniforms
+    }
+
+    pub fn attributes(&self) -> &[ActiveVariable] {
+        &self.attributes
+    }
+
+    /// Checks that every attribute the shader reads is provided with a matching layout.
+    ///
+    /// `attribs` is the `(location, component count, component type)` list produced by
+    /// `vertex_attribs()` on a type deriving `VertexAttribPointers`.
+    pub fn check_vertex_attribs(&self, attribs: &[(usize, i32, u32)]) -> Result<(), Error> {
+        for attribute in self
+            .attributes
+            .iter()
+            .filter(|a| !a.name.starts_with("gl_"))
+        {
+            let mismatch = |message: String| Error::VertexLayoutMismatch {
+                name: self.name.clone(),
+                attribute: attribute.name.clone(),
+                message,
+            };
+
+            let (locations, components, component_type) =
+                reflection::attribute_layout(attribute.gl_type).ok_or_else(|| {
+                    mismatch(format!(
+                        "unsupported attribute type {}",
+                        reflection::gl_type_name(attribute.gl_type)
+                    ))
+                })?;
+
+            for offset in 0..locations * attribute.size {
+                let location = (attribute.location + offset) as usize;
+                let &(_, provided, provided_type) = attribs
+                    .iter()
+                    .find(|&&(l, _, _)| l == location)
+                    .ok_or_else(|| {
+                        mismatch(format!(
+                            "shader reads location {} but the vertex type has no attribute there",
+                            location
+                        ))
+                    })?;
+
+                // GL fills missing float components with (0, 0, 1), e.g. a vec3 attribute can
+                // feed a vec4 input.
+                let fits = if component_type == gl::FLOAT {
+                    provided <= components
+                } else {
+                    provided == components
+                };
+                if !fits || provided_type != component_type {
+                    return Err(mismatch(format!(
+                        "shader expects {} x {} at location {} but the vertex type provides {} x {}",
+                        components,
+                        reflection::gl_type_name(component_type),
+                        location,
+                        provided,
+                        reflection::gl_type_name(provided_type)
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Binds the program and sets a `float` uniform.
+    ///
+    /// Every `set_*` method binds the program, as `glUniform*` only writes to the bound one.
+    /// Setting uniforms of another program between binding this one and drawing leaves that
+    /// other program bound.
+    pub fn set_f32(&self, uniform: &str, value: f32) -> Result<(), Error> {
+        let location = self.uniform_location(uniform, &[gl::FLOAT])?;
+        unsafe {
+            gl::Uniform1f(location, value);
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets an `int` or `bool` uniform.
+    pub fn set_i32(&self, uniform: &str, value: i32) -> Result<(), Error> {
+        let location = self.uniform_location(uniform, &[gl::INT, gl::BOOL])?;
+        unsafe {
+            gl::Uniform1i(location, value);
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets a `uint` uniform.
+    pub fn set_u32(&self, uniform: &str, value: u32) -> Result<(), Error> {
+        let location = self.uniform_location(uniform, &[gl::UNSIGNED_INT])?;
+        unsafe {
+            gl::Uniform1ui(location, value);
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets a `vec2` uniform.
+    pub fn set_vec2(&self, uniform: &str, value: Vec2) -> Result<(), Error> {
+        self.set_vec2_array(uniform, &[value])
+    }
+
+    /// Binds the program and sets a `vec3` uniform.
+    pub fn set_vec3(&self, uniform: &str, value: Vec3) -> Result<(), Error> {
+        self.set_vec3_array(uniform, &[value])
+    }
+
+    /// Binds the program and sets a `vec4` uniform.
+    pub fn set_vec4(&self, uniform: &str, value: Vec4) -> Result<(), Error> {
+        self.set_vec4_array(uniform, &[value])
+    }
+
+    /// Binds the program and sets a `mat3` uniform.
+    pub fn set_mat3(&self, uniform: &str, value: &Mat3) -> Result<(), Error> {
+        self.set_mat3_array(uniform, std::slice::from_ref(value))
+    }
+
+    /// Binds the program and sets a `mat4` uniform.
+    pub fn set_mat4(&self, uniform: &str, value: &Mat4) -> Result<(), Error> {
+        self.set_mat4_array(uniform, std::slice::from_ref(value))
+    }
+
+    /// Binds the program and points a sampler uniform at the given texture unit.
+    pub fn set_sampler(&self, uniform: &str, unit: u32) -> Result<(), Error> {
+        let location = self.uniform_location(uniform, SAMPLER_TYPES)?;
+        unsafe {
+            gl::Uniform1i(location, unit as GLint);
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of a `float` array, starting at
+    /// `uniform`, which may name an element such as `weights[2]`.
+    pub fn set_f32_array(&self, uniform: &str, values: &[f32]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::FLOAT], values.len())?;
+        unsafe {
+            gl::Uniform1fv(location, values.len() as GLsizei, values.as_ptr());
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of an `int` or `bool` array, like
+    /// `set_f32_array`.
+    pub fn set_i32_array(&self, uniform: &str, values: &[i32]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::INT, gl::BOOL], values.len())?;
+        unsafe {
+            gl::Uniform1iv(location, values.len() as GLsizei, values.as_ptr());
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of a `vec2` array, like `set_f32_array`.
+    pub fn set_vec2_array(&self, uniform: &str, values: &[Vec2]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::FLOAT_VEC2], values.len())?;
+        unsafe {
+            gl::Uniform2fv(
+                location,
+                values.len() as GLsizei,
+                values.as_ptr() as *const GLfloat,
+            );
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of a `vec3` array, like `set_f32_array`.
+    pub fn set_vec3_array(&self, uniform: &str, values: &[Vec3]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::FLOAT_VEC3], values.len())?;
+        unsafe {
+            gl::Uniform3fv(
+                location,
+                values.len() as GLsizei,
+                values.as_ptr() as *const GLfloat,
+            );
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of a `vec4` array, like `set_f32_array`.
+    pub fn set_vec4_array(&self, uniform: &str, values: &[Vec4]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::FLOAT_VEC4], values.len())?;
+        unsafe {
+            gl::Uniform4fv(
+                location,
+                values.len() as GLsizei,
+                values.as_ptr() as *const GLfloat,
+            );
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of a `mat3` array, like `set_f32_array`.
+    pub fn set_mat3_array(&self, uniform: &str, values: &[Mat3]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::FLOAT_MAT3], values.len())?;
+        unsafe {
+            gl::UniformMatrix3fv(
+                location,
+                values.len() as GLsizei,
+                gl::FALSE,
+                values.as_ptr() as *const GLfloat,
+            );
+        }
+        Ok(())
+    }
+
+    /// Binds the program and sets consecutive elements of a `mat4` array, like `set_f32_array`.
+    pub fn set_mat4_array(&self, uniform: &str, values: &[Mat4]) -> Result<(), Error> {
+        let location = self.uniform_array_location(uniform, &[gl::FLOAT_MAT4], values.len())?;
+        unsafe {
+            gl::UniformMatrix4fv(
+                location,
+                values.len() as GLsizei,
+                gl::FALSE,
+                values.as_ptr() as *const GLfloat,
+            );
+        }
+        Ok(())
+    }
+
+    /// Connects uniform block `block` to uniform buffer binding point `binding`.
+    pub fn bind_uniform_block(&self, block: &str, binding: u32) -> Result<(), Error> {
+        let index = self.block_index(block, gl::UNIFORM_BLOCK)?;
+        unsafe {
+            gl::UniformBlockBinding(self.id, index, binding);
+        }
+        Ok(())
+    }
+
+    /// Connects shader storage block `block` to shader storage buffer binding point `binding`.
+    pub fn bind_storage_block(&self, block: &str, binding: u32) -> Result<(), Error> {
+        let index = self.block_index(block, gl::SHADER_STORAGE_BLOCK)?;
+        unsafe {
+            gl::ShaderStorageBlockBinding(self.id, index, binding);
+        }
+        Ok(())
+    }
+
+    fn block_index(&self, block: &str, interface: GLenum) -> Result<GLuint, Error> {
+        let not_found = || Error::BlockNotFound {
+            name: self.name.clone(),
+            block: String::from(block),
+        };
+
+        let c_name = CString::new(block).map_err(|_| not_found())?;
+        let index = unsafe {
+            if interface == gl::UNIFORM_BLOCK {
+                gl::GetUniformBlockIndex(self.id, c_name.as_ptr())
+            } else {
+                gl::GetProgramResourceIndex(self.id, interface, c_name.as_ptr())
+            }
+        };
+
+        if index == gl::INVALID_INDEX {
+            return Err(not_found());
+        }
+        Ok(index)
+    }
+
+    /// Binds the program and returns the location of `uniform` after checking its GL type.
+    fn uniform_location(&self, uniform: &str, expected: &'static [GLenum]) -> Result<GLint, Error> {
+        self.uniform_array_location(uniform, expected, 1)
+    }
+
+    /// Like `uniform_location`, also checking that `len` values fit from `uniform` to the end
+    /// of its array, since the driver silently drops the rest.
+    fn uniform_array_location(
+        &self,
+        uniform: &str,
+        expected: &'static [GLenum],
+        len: usize,
+    ) -> Result<GLint, Error> {
+        let not_found = || Error::UniformNotFound {
+            name: self.name.clone(),
+            uniform: String::from(uniform),
+        };
+
+        let (actual, capacity) = self.uniform_type(uniform).ok_or_else(not_found)?;
+        if !expected.contains(&actual) {
+            return Err(Error::UniformTypeMismatch {
+                name: self.name.clone(),
+                uniform: String::from(uniform),
+                expected,
+                actual,
+            });
+        }
+        if len > capacity {
+            return Err(Error::UniformArrayTooLong {
+                name: self.name.clone(),
+                uniform: String::from(uniform),
+                capacity,
+                provided: len,
+            });
+        }
+
+        self.bind();
+
+        if let Some(&location) = self.uniform_locations.borrow().get(uniform) {
+            return Ok(location);
+        }
+
+        let c_name = CString::new(uniform).map_err(|_| not_found())?;
+        let location = unsafe { gl::GetUniformLocation(self.id, c_name.as_ptr()) };
+        if location < 0 {
+            return Err(not_found());
+        }
+
+        self.uniform_locations
+            .borrow_mut()
+            .insert(String::from(uniform), location);
+        Ok(location)
+    }
+
+    /// Looks up the type of an active uniform, accepting `arr`, `arr[0]` and `arr[n]` for arrays,
+    /// along with how many values fit from there to the end of the array.
+    fn uniform_type(&self, uniform: &str) -> Option<(GLenum, usize)> {
+        let (base, index) = match uniform.strip_suffix(']') {
+            Some(indexed) => {
+                let (base, index) = indexed.rsplit_once('[')?;
+                (base, index.parse::<usize>().ok()?)
+            }
+            None => (uniform, 0),
+        };
+        let array_name = format!("{}[0]", base);
+
+        self.uniforms
+            .iter()
+            .find(|u| u.name == uniform || u.name == array_name)
+            .and_then(|u| {
+                let capacity = (u.size.max(1) as usize).checked_sub(index)?;
+                Some((u.gl_type, capacity)).filter(|_| capacity > 0)
+            })
+    }
 }
 
 impl Drop for Program {
@@ -107,14 +673,29 @@ impl Drop for Program {
 
 pub struct Shader {
     id: GLuint,
+    sources: Vec<String>,
 }
 
 impl Shader {
     pub fn from_res(res: &Resources, name: &str) -> Result<Shader, Error> {
-        const POSSIBLE_EXT: [(&str, GLenum); 2] =
-            [(".vert", gl::VERTEX_SHADER), (".frag", gl::FRAGMENT_SHADER)];
+        Shader::from_res_with_defines(res, name, &[])
+    }
+
+    pub fn from_res_with_defines(
+        res: &Resources,
+        name: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Shader, Error> {
+        let (kind, preprocessed) = Shader::preprocess_res(res, name, defines)?;
+        Shader::from_preprocessed(name, kind, &preprocessed)
+    }
 
-        let kind = POSSIBLE_EXT
+    fn preprocess_res(
+        res: &Resources,
+        name: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<(GLenum, PreprocessedSource), Error> {
+        let kind = SHADER_EXTENSIONS
             .iter()
             .find(|&&(ext, _)| name.ends_with(ext))
             .map(|&(_, kind)| kind)
@@ -122,26 +703,61 @@ impl Shader {
                 name: String::from(name),
             })?;
 
-        let source = res.load_cstring(name).map_err(|e| Error::ResourceLoad {
-            name: String::from(name),
-            inner: e,
+        let preprocessed = preprocessor::preprocess(name, defines, |file| {
+            res.load_string(file).map_err(|e| Error::ResourceLoad {
+                name: String::from(file),
+                inner: e,
+            })
         })?;
 
-        Shader::from_source(&source, kind, name)
+        Ok((kind, preprocessed))
+    }
+
+    fn from_preprocessed(
+        name: &str,
+        kind: GLenum,
+        preprocessed: &PreprocessedSource,
+    ) -> Result<Shader, Error> {
+        let source =
+            CString::new(preprocessed.source.as_str()).map_err(|_| Error::ResourceLoad {
+                name: String::from(name),
+                inner: resources::Error::FileContainsNil,
+            })?;
+
+        let id = shader_from_source(&source, kind).map_err(|log| Error::CompileError {
+            name: String::from(name),
+            message: preprocessor::annotate_log(&log, &preprocessed.files),
+            diagnostics: compile_log::parse(&log, &preprocessed.files, &preprocessed.contents),
+        })?;
+        Ok(Shader {
+            id,
+            sources: preprocessed.files.clone(),
+        })
     }
 
-    fn from_source(source: &CStr, kind: GLenum, name: &str) -> Result<Shader, Error> {
+    pub fn from_source(source: &CStr, kind: GLenum, name: &str) -> Result<Shader, Error> {
         Ok(Shader {
-            id: shader_from_source(source, kind).map_err(|err| Error::CompileError {
+            id: shader_from_source(source, kind).map_err(|log| Error::CompileError {
                 name: String::from(name),
-                message: err,
+                diagnostics: compile_log::parse(
+                    &log,
+                    &[String::from(name)],
+                    &[source.to_string_lossy().into_owned()],
+                ),
+                message: log,
             })?,
+            sources: Vec::new(),
         })
     }
 
     pub fn id(&self) -> GLuint {
         self.id
     }
+
+    /// The resource this shader was loaded from followed by everything it includes.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
 }
 
 impl Drop for Shader {
@@ -170,20 +786,78 @@ fn shader_from_source(source: &CStr, kind: GLuint) -> Result<GLuint, String> {
             gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut len);
         }
 
-        let error = create_whitespace_cstring_with_len(len as usize);
+        let error = read_info_log(len, |len, written, buffer| unsafe {
+            gl::GetShaderInfoLog(id, len, written, buffer);
+        });
 
         unsafe {
-            gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut GLchar);
+            gl::DeleteShader(id);
         }
 
-        return Err(error.to_string_lossy().into_owned());
+        return Err(error);
     }
 
     Ok(id)
 }
 
-fn create_whitespace_cstring_with_len(len: usize) -> CString {
-    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
-    buffer.extend([b' '].iter().cycle().take(len as usize));
-    unsafe { CString::from_vec_unchecked(buffer) }
+const SHADER_EXTENSIONS: [(&str, GLenum); 6] = [
+    (".vert", gl::VERTEX_SHADER),
+    (".tesc", gl::TESS_CONTROL_SHADER),
+    (".tese", gl::TESS_EVALUATION_SHADER),
+    (".geom", gl::GEOMETRY_SHADER),
+    (".frag", gl::FRAGMENT_SHADER),
+    (".comp", gl::COMPUTE_SHADER),
+];
+
+const SAMPLER_TYPES: &[GLenum] = &[
+    gl::SAMPLER_1D,
+    gl::SAMPLER_2D,
+    gl::SAMPLER_3D,
+    gl::SAMPLER_CUBE,
+    gl::SAMPLER_1D_SHADOW,
+    gl::SAMPLER_2D_SHADOW,
+    gl::SAMPLER_1D_ARRAY,
+    gl::SAMPLER_2D_ARRAY,
+    gl::SAMPLER_2D_ARRAY_SHADOW,
+    gl::SAMPLER_2D_MULTISAMPLE,
+    gl::SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::SAMPLER_CUBE_SHADOW,
+    gl::SAMPLER_BUFFER,
+    gl::SAMPLER_2D_RECT,
+    gl::SAMPLER_2D_RECT_SHADOW,
+    gl::INT_SAMPLER_1D,
+    gl::INT_SAMPLER_2D,
+    gl::INT_SAMPLER_3D,
+    gl::INT_SAMPLER_CUBE,
+    gl::INT_SAMPLER_1D_ARRAY,
+    gl::INT_SAMPLER_2D_ARRAY,
+    gl::INT_SAMPLER_2D_MULTISAMPLE,
+    gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::INT_SAMPLER_BUFFER,
+    gl::INT_SAMPLER_2D_RECT,
+    gl::UNSIGNED_INT_SAMPLER_1D,
+    gl::UNSIGNED_INT_SAMPLER_2D,
+    gl::UNSIGNED_INT_SAMPLER_3D,
+    gl::UNSIGNED_INT_SAMPLER_CUBE,
+    gl::UNSIGNED_INT_SAMPLER_1D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE,
+    gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+    gl::UNSIGNED_INT_SAMPLER_BUFFER,
+    gl::UNSIGNED_INT_SAMPLER_2D_RECT,
+];
+
+fn read_info_log<F>(len: GLint, read: F) -> String
+where
+    F: FnOnce(GLsizei, *mut GLsizei, *mut GLchar),
+{
+    let mut buffer = vec![0u8; len.max(1) as usize];
+    let mut written: GLsizei = 0;
+    read(
+        buffer.len() as GLsizei,
+        &mut written,
+        buffer.as_mut_ptr() as *mut GLchar,
+    );
+    buffer.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&buffer).into_owned()
 }
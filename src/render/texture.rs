@@ -0,0 +1,556 @@
+use super::image::{Image, PixelFormat};
+use super::Error;
+use crate::resources::Resources;
+use crate::utils::Vec4;
+
+use gl::types::*;
+
+/// How a texture's texels are stored on the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InternalFormat {
+    R8,
+    Rg8,
+    Rgb8,
+    Rgba8,
+    /// sRGB-encoded color, decoded to linear when sampled.
+    Srgb8,
+    Srgb8Alpha8,
+    R16,
+    Rg16,
+    Rgb16,
+    Rgba16,
+    R16F,
+    Rg16F,
+    Rgb16F,
+    Rgba16F,
+    R32F,
+    Rg32F,
+    Rgb32F,
+    Rgba32F,
+    R11fG11fB10f,
+    Depth16,
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+    Depth32FStencil8,
+    Stencil8,
+}
+
+impl InternalFormat {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            InternalFormat::R8 => gl::R8,
+            InternalFormat::Rg8 => gl::RG8,
+            InternalFormat::Rgb8 => gl::RGB8,
+            InternalFormat::Rgba8 => gl::RGBA8,
+            InternalFormat::Srgb8 => gl::SRGB8,
+            InternalFormat::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+            InternalFormat::R16 => gl::R16,
+            InternalFormat::Rg16 => gl::RG16,
+            InternalFormat::Rgb16 => gl::RGB16,
+            InternalFormat::Rgba16 => gl::RGBA16,
+            InternalFormat::R16F => gl::R16F,
+            InternalFormat::Rg16F => gl::RG16F,
+            InternalFormat::Rgb16F => gl::RGB16F,
+            InternalFormat::Rgba16F => gl::RGBA16F,
+            InternalFormat::R32F => gl::R32F,
+            InternalFormat::Rg32F => gl::RG32F,
+            InternalFormat::Rgb32F => gl::RGB32F,
+            InternalFormat::Rgba32F => gl::RGBA32F,
+            InternalFormat::R11fG11fB10f => gl::R11F_G11F_B10F,
+            InternalFormat::Depth16 => gl::DEPTH_COMPONENT16,
+            InternalFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            InternalFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+            InternalFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+            InternalFormat::Depth32FStencil8 => gl::DEPTH32F_STENCIL8,
+            InternalFormat::Stencil8 => gl::STENCIL_INDEX8,
+        }
+    }
+
+    pub fn has_depth(self) -> bool {
+        matches!(
+            self,
+            InternalFormat::Depth16
+                | InternalFormat::Depth24
+                | InternalFormat::Depth32F
+                | InternalFormat::Depth24Stencil8
+                | InternalFormat::Depth32FStencil8
+        )
+    }
+
+    pub fn has_stencil(self) -> bool {
+        matches!(
+            self,
+            InternalFormat::Depth24Stencil8
+                | InternalFormat::Depth32FStencil8
+                | InternalFormat::Stencil8
+        )
+    }
+
+    pub fn is_color(self) -> bool {
+        !self.has_depth() && !self.has_stencil()
+    }
+
+    /// A pixel layout GL accepts when uploading to this format, or `None` for depth and
+    /// stencil formats, which images can't be uploaded to.
+    pub fn pixel_format(self) -> Option<PixelFormat> {
+        let pixels = match self {
+            InternalFormat::R8 => PixelFormat::R8,
+            InternalFormat::Rg8 => PixelFormat::Rg8,
+            InternalFormat::Rgb8 | InternalFormat::Srgb8 => PixelFormat::Rgb8,
+            InternalFormat::Rgba8 | InternalFormat::Srgb8Alpha8 => PixelFormat::Rgba8,
+            InternalFormat::R16 => PixelFormat::R16,
+            InternalFormat::Rg16 => PixelFormat::Rg16,
+            InternalFormat::Rgb16 => PixelFormat::Rgb16,
+            InternalFormat::Rgba16 => PixelFormat::Rgba16,
+            InternalFormat::R16F | InternalFormat::R32F => PixelFormat::R32F,
+            InternalFormat::Rg16F | InternalFormat::Rg32F => PixelFormat::Rg32F,
+            InternalFormat::Rgb16F | InternalFormat::Rgb32F | InternalFormat::R11fG11fB10f => {
+                PixelFormat::Rgb32F
+            }
+            InternalFormat::Rgba16F | InternalFormat::Rgba32F => PixelFormat::Rgba32F,
+            _ => return None,
+        };
+        Some(pixels)
+    }
+
+    /// The `format` and `type` to pass when allocating storage without uploading pixels.
+    pub(super) fn allocation_enums(self) -> (GLenum, GLenum) {
+        match self {
+            InternalFormat::Depth16 | InternalFormat::Depth24 | InternalFormat::Depth32F => {
+                (gl::DEPTH_COMPONENT, gl::FLOAT)
+            }
+            InternalFormat::Depth24Stencil8 => (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8),
+            InternalFormat::Depth32FStencil8 => {
+                (gl::DEPTH_STENCIL, gl::FLOAT_32_UNSIGNED_INT_24_8_REV)
+            }
+            InternalFormat::Stencil8 => (gl::STENCIL_INDEX, gl::UNSIGNED_BYTE),
+            _ => {
+                let pixels = self.pixel_format().unwrap();
+                (pixels.gl_format(), pixels.gl_type())
+            }
+        }
+    }
+}
+
+impl From<PixelFormat> for InternalFormat {
+    /// The format that stores `PixelFormat` without losing precision.
+    fn from(format: PixelFormat) -> InternalFormat {
+        match format {
+            PixelFormat::R8 => InternalFormat::R8,
+            PixelFormat::Rg8 => InternalFormat::Rg8,
+            PixelFormat::Rgb8 => InternalFormat::Rgb8,
+            PixelFormat::Rgba8 => InternalFormat::Rgba8,
+            PixelFormat::R16 => InternalFormat::R16,
+            PixelFormat::Rg16 => InternalFormat::Rg16,
+            PixelFormat::Rgb16 => InternalFormat::Rgb16,
+            PixelFormat::Rgba16 => InternalFormat::Rgba16,
+            PixelFormat::R32F => InternalFormat::R32F,
+            PixelFormat::Rg32F => InternalFormat::Rg32F,
+            PixelFormat::Rgb32F => InternalFormat::Rgb32F,
+            PixelFormat::Rgba32F => InternalFormat::Rgba32F,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl Filter {
+    fn gl_enum(self) -> GLenum {
+        match self {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+
+    /// `GL_TEXTURE_MIN_FILTER` value for filtering within a level with `self` and between
+    /// levels with `mipmap`.
+    fn min_filter_enum(self, mipmap: Option<Filter>) -> GLenum {
+        match (self, mipmap) {
+            (_, None) => self.gl_enum(),
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    ClampToBorder,
+}
+
+impl Wrap {
+    fn gl_enum(self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            Wrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+        }
+    }
+}
+
+/// Sampling and storage settings for creating a texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureOptions {
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// Filter between mip levels. Mipmaps are only generated when this is set.
+    pub mipmap_filter: Option<Filter>,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    /// Wrap mode along the third axis of cube maps and 3D textures.
+    pub wrap_r: Wrap,
+    /// Only used with `Wrap::ClampToBorder`.
+    pub border_color: Vec4,
+    /// Defaults to the format matching the image's pixels.
+    pub internal_format: Option<InternalFormat>,
+}
+
+impl Default for TextureOptions {
+    fn default() -> TextureOptions {
+        TextureOptions {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            wrap_r: Wrap::Repeat,
+            border_color: Vec4::zero(),
+            internal_format: None,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Unfiltered, unmipmapped and clamped, for pixel art and lookup tables.
+    pub fn nearest() -> TextureOptions {
+        TextureOptions {
+            min_filter: Filter::Nearest,
+            mag_filter: Filter::Nearest,
+            mipmap_filter: None,
+            wrap_s: Wrap::ClampToEdge,
+            wrap_t: Wrap::ClampToEdge,
+            wrap_r: Wrap::ClampToEdge,
+            ..TextureOptions::default()
+        }
+    }
+}
+
+/// Number of levels in a full mip chain for the largest dimension `size`.
+pub(super) fn mip_levels(size: u32) -> u32 {
+    32 - size.max(1).leading_zeros()
+}
+
+/// Sets the unpack alignment to 1 so rows of any width upload tightly packed.
+pub(super) fn unpack_tightly() {
+    unsafe {
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+    }
+}
+
+/// A texture name and the target it binds to, deleted on drop. Shared by the texture types.
+pub(super) struct TextureObject {
+    id: GLuint,
+    target: GLenum,
+}
+
+impl TextureObject {
+    /// Creates a texture, binds it and applies the sampling settings from `options`.
+    pub(super) fn new(target: GLenum, options: &TextureOptions) -> TextureObject {
+        let mut id: GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+        }
+
+        let texture = TextureObject { id, target };
+        texture.bind();
+        texture.set_filter(
+            options.min_filter,
+            options.mag_filter,
+            options.mipmap_filter,
+        );
+        texture.set_wrap(options.wrap_s, options.wrap_t, options.wrap_r);
+
+        let border = options.border_color;
+        let border = [border.x, border.y, border.z, border.w];
+        unsafe {
+            gl::TexParameterfv(target, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+        }
+        texture
+    }
+
+    /// Generates mipmaps if `options` asks for them and unbinds the texture.
+    pub(super) fn finish(&self, options: &TextureOptions) {
+        if options.mipmap_filter.is_some() {
+            self.generate_mipmaps();
+        }
+        self.unbind();
+    }
+
+    pub(super) fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub(super) fn target(&self) -> GLenum {
+        self.target
+    }
+
+    pub(super) fn bind(&self) {
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+        }
+    }
+
+    pub(super) fn bind_to_unit(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+        }
+        self.bind();
+    }
+
+    pub(super) fn unbind(&self) {
+        unsafe {
+            gl::BindTexture(self.target, 0);
+        }
+    }
+
+    pub(super) fn set_filter(&self, min: Filter, mag: Filter, mipmap: Option<Filter>) {
+        unsafe {
+            gl::TexParameteri(
+                self.target,
+                gl::TEXTURE_MIN_FILTER,
+                min.min_filter_enum(mipmap) as GLint,
+            );
+            gl::TexParameteri(self.target, gl::TEXTURE_MAG_FILTER, mag.gl_enum() as GLint);
+        }
+    }
+
+    pub(super) fn set_wrap(&self, s: Wrap, t: Wrap, r: Wrap) {
+        unsafe {
+            gl::TexParameteri(self.target, gl::TEXTURE_WRAP_S, s.gl_enum() as GLint);
+            gl::TexParameteri(self.target, gl::TEXTURE_WRAP_T, t.gl_enum() as GLint);
+            gl::TexParameteri(self.target, gl::TEXTURE_WRAP_R, r.gl_enum() as GLint);
+        }
+    }
+
+    pub(super) fn generate_mipmaps(&self) {
+        unsafe {
+            gl::GenerateMipmap(self.target);
+        }
+    }
+}
+
+impl Drop for TextureObject {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+pub struct Texture2D {
+    texture: TextureObject,
+    width: u32,
+    height: u32,
+    format: InternalFormat,
+}
+
+impl Texture2D {
+    /// Allocates a texture with undefined contents, e.g. to render into.
+    pub fn new(width: u32, height: u32, options: &TextureOptions) -> Texture2D {
+        let format = options.internal_format.unwrap_or(InternalFormat::Rgba8);
+        let texture = Texture2D::create(width, height, format, options);
+        let (pixels, kind) = format.allocation_enums();
+        texture.upload(pixels, kind, std::ptr::null());
+        texture.texture.finish(options);
+        texture
+    }
+
+    pub fn from_image(image: &Image, options: &TextureOptions) -> Texture2D {
+        let format = options
+            .internal_format
+            .unwrap_or_else(|| image.format().into());
+        let texture = Texture2D::create(image.width(), image.height(), format, options);
+        texture.upload(
+            image.format().gl_format(),
+            image.format().gl_type(),
+            image.data().as_ptr(),
+        );
+        texture.texture.finish(options);
+        texture
+    }
+
+    /// Loads an image resource into a texture.
+    pub fn from_res(
+        res: &Resources,
+        name: &str,
+        options: &TextureOptions,
+    ) -> Result<Texture2D, Error> {
+        Ok(Texture2D::from_image(&Image::from_res(res, name)?, options))
+    }
+
+    fn create(
+        width: u32,
+        height: u32,
+        format: InternalFormat,
+        options: &TextureOptions,
+    ) -> Texture2D {
+        Texture2D {
+            texture: TextureObject::new(gl::TEXTURE_2D, options),
+            width,
+            height,
+            format,
+        }
+    }
+
+    fn upload(&self, pixels: GLenum, kind: GLenum, data: *const u8) {
+        unpack_tightly();
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                self.format.gl_enum() as GLint,
+                self.width as GLsizei,
+                self.height as GLsizei,
+                0,
+                pixels,
+                kind,
+                data as *const GLvoid,
+            );
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.texture.id()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> InternalFormat {
+        self.format
+    }
+
+    /// Number of levels a full mip chain for this texture has.
+    pub fn mip_levels(&self) -> u32 {
+        mip_levels(self.width.max(self.height))
+    }
+
+    /// Binds the texture to the active texture unit.
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    /// Binds the texture to texture unit `unit`, leaving that unit active.
+    pub fn bind_to_unit(&self, unit: u32) {
+        self.texture.bind_to_unit(unit);
+    }
+
+    pub fn unbind(&self) {
+        self.texture.unbind();
+    }
+
+    /// Changes the filtering of the bound texture.
+    pub fn set_filter(&self, min: Filter, mag: Filter, mipmap: Option<Filter>) {
+        self.texture.set_filter(min, mag, mipmap);
+    }
+
+    /// Changes the wrap modes of the bound texture.
+    pub fn set_wrap(&self, s: Wrap, t: Wrap) {
+        self.texture.set_wrap(s, t, Wrap::Repeat);
+    }
+
+    /// Regenerates every mip level of the bound texture from level 0.
+    pub fn generate_mipmaps(&self) {
+        self.texture.generate_mipmaps();
+    }
+
+    /// Replaces part of level 0 of the bound texture with `image`, its bottom-left corner at
+    /// `(x, y)`.
+    ///
+    /// Mipmaps are not updated; call `generate_mipmaps` afterwards if the texture has them.
+    pub fn sub_image(&self, x: u32, y: u32, image: &Image) {
+        if x + image.width() > self.width || y + image.height() > self.height {
+            panic!(
+                "A {}x{} image at ({}, {}) does not fit in a {}x{} texture",
+                image.width(),
+                image.height(),
+                x,
+                y,
+                self.width,
+                self.height
+            );
+        }
+
+        unpack_tightly();
+        unsafe {
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as GLint,
+                y as GLint,
+                image.width() as GLsizei,
+                image.height() as GLsizei,
+                image.format().gl_format(),
+                image.format().gl_type(),
+                image.data().as_ptr() as *const GLvoid,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mip_chain_lengths() {
+        assert_eq!(1, mip_levels(1));
+        assert_eq!(2, mip_levels(2));
+        assert_eq!(9, mip_levels(256));
+        assert_eq!(9, mip_levels(511));
+        assert_eq!(10, mip_levels(512));
+    }
+
+    #[test]
+    fn min_filters_combine_with_mipmaps() {
+        assert_eq!(gl::LINEAR, Filter::Linear.min_filter_enum(None));
+        assert_eq!(
+            gl::NEAREST_MIPMAP_LINEAR,
+            Filter::Nearest.min_filter_enum(Some(Filter::Linear))
+        );
+        assert_eq!(
+            gl::LINEAR_MIPMAP_NEAREST,
+            Filter::Linear.min_filter_enum(Some(Filter::Nearest))
+        );
+    }
+
+    #[test]
+    fn internal_formats_accept_their_pixel_formats() {
+        for &format in &[PixelFormat::R8, PixelFormat::Rgb16, PixelFormat::Rgba32F] {
+            assert_eq!(Some(format), InternalFormat::from(format).pixel_format());
+        }
+        assert_eq!(
+            Some(PixelFormat::Rgba8),
+            InternalFormat::Srgb8Alpha8.pixel_format()
+        );
+        assert_eq!(None, InternalFormat::Depth24Stencil8.pixel_format());
+        assert!(InternalFormat::Depth24Stencil8.has_stencil());
+        assert!(!InternalFormat::Stencil8.has_depth());
+    }
+}
@@ -0,0 +1,476 @@
+use super::image::{self, Image, PixelFormat};
+use super::texture::{mip_levels, unpack_tightly, TextureObject};
+use super::{Error, Filter, InternalFormat, TextureOptions, Wrap};
+use crate::resources::Resources;
+
+use gl::types::*;
+
+/// A stack of same-sized 2D layers sampled with `sampler2DArray`, e.g. sprite animation
+/// frames or terrain materials.
+pub struct Texture2DArray {
+    texture: TextureObject,
+    width: u32,
+    height: u32,
+    layers: u32,
+    format: InternalFormat,
+}
+
+impl Texture2DArray {
+    /// Allocates an array texture with undefined contents.
+    pub fn new(width: u32, height: u32, layers: u32, options: &TextureOptions) -> Texture2DArray {
+        let format = options.internal_format.unwrap_or(InternalFormat::Rgba8);
+        let texture = TextureObject::new(gl::TEXTURE_2D_ARRAY, options);
+        allocate(&texture, format, width, height, layers);
+        texture.finish(options);
+        Texture2DArray {
+            texture,
+            width,
+            height,
+            layers,
+            format,
+        }
+    }
+
+    /// Builds an array with one layer per image. Panics if there are no images or their
+    /// sizes or formats differ.
+    pub fn from_images(images: &[Image], options: &TextureOptions) -> Texture2DArray {
+        let (width, height, format) = check_layers(images);
+        let format = options.internal_format.unwrap_or_else(|| format.into());
+        let texture = TextureObject::new(gl::TEXTURE_2D_ARRAY, options);
+        allocate(&texture, format, width, height, images.len() as u32);
+        for (layer, image) in images.iter().enumerate() {
+            upload_layer(gl::TEXTURE_2D_ARRAY, layer as u32, image);
+        }
+        texture.finish(options);
+        Texture2DArray {
+            texture,
+            width,
+            height,
+            layers: images.len() as u32,
+            format,
+        }
+    }
+
+    /// Loads one image resource per layer.
+    pub fn from_res_layers(
+        res: &Resources,
+        names: &[&str],
+        options: &TextureOptions,
+    ) -> Result<Texture2DArray, Error> {
+        Ok(Texture2DArray::from_images(
+            &load_layers(res, names)?,
+            options,
+        ))
+    }
+
+    /// Loads a sprite sheet of `columns` x `rows` equal cells, one layer per cell in reading
+    /// order from the top left.
+    pub fn from_res_grid(
+        res: &Resources,
+        name: &str,
+        columns: u32,
+        rows: u32,
+        options: &TextureOptions,
+    ) -> Result<Texture2DArray, Error> {
+        Ok(Texture2DArray::from_images(
+            &load_grid(res, name, columns, rows)?,
+            options,
+        ))
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.texture.id()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    pub fn format(&self) -> InternalFormat {
+        self.format
+    }
+
+    /// Number of levels a full mip chain has. Layers are never downsampled.
+    pub fn mip_levels(&self) -> u32 {
+        mip_levels(self.width.max(self.height))
+    }
+
+    /// Binds the array to the active texture unit.
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    /// Binds the array to texture unit `unit`, leaving that unit active.
+    pub fn bind_to_unit(&self, unit: u32) {
+        self.texture.bind_to_unit(unit);
+    }
+
+    pub fn unbind(&self) {
+        self.texture.unbind();
+    }
+
+    /// Changes the filtering of the bound array.
+    pub fn set_filter(&self, min: Filter, mag: Filter, mipmap: Option<Filter>) {
+        self.texture.set_filter(min, mag, mipmap);
+    }
+
+    /// Changes the wrap modes of the bound array.
+    pub fn set_wrap(&self, s: Wrap, t: Wrap) {
+        self.texture.set_wrap(s, t, Wrap::Repeat);
+    }
+
+    /// Regenerates every mip level of the bound array from level 0.
+    pub fn generate_mipmaps(&self) {
+        self.texture.generate_mipmaps();
+    }
+
+    /// Replaces level 0 of `layer` of the bound array with `image`.
+    ///
+    /// Mipmaps are not updated; call `generate_mipmaps` afterwards if the array has them.
+    pub fn sub_image(&self, layer: u32, image: &Image) {
+        check_sub_image(self.width, self.height, self.layers, layer, image);
+        upload_layer(gl::TEXTURE_2D_ARRAY, layer, image);
+    }
+}
+
+/// A volume texture sampled with `sampler3D`, e.g. color grading lookup tables.
+pub struct Texture3D {
+    texture: TextureObject,
+    width: u32,
+    height: u32,
+    depth: u32,
+    format: InternalFormat,
+}
+
+impl Texture3D {
+    /// Allocates a volume texture with undefined contents.
+    pub fn new(width: u32, height: u32, depth: u32, options: &TextureOptions) -> Texture3D {
+        let format = options.internal_format.unwrap_or(InternalFormat::Rgba8);
+        let texture = TextureObject::new(gl::TEXTURE_3D, options);
+        allocate(&texture, format, width, height, depth);
+        texture.finish(options);
+        Texture3D {
+            texture,
+            width,
+            height,
+            depth,
+            format,
+        }
+    }
+
+    /// Builds a volume with one slice per image, the first image at `r = 0`. Panics if there
+    /// are no images or their sizes or formats differ.
+    pub fn from_images(images: &[Image], options: &TextureOptions) -> Texture3D {
+        let (width, height, format) = check_layers(images);
+        let format = options.internal_format.unwrap_or_else(|| format.into());
+        let texture = TextureObject::new(gl::TEXTURE_3D, options);
+        allocate(&texture, format, width, height, images.len() as u32);
+        for (slice, image) in images.iter().enumerate() {
+            upload_layer(gl::TEXTURE_3D, slice as u32, image);
+        }
+        texture.finish(options);
+        Texture3D {
+            texture,
+            width,
+            height,
+            depth: images.len() as u32,
+            format,
+        }
+    }
+
+    /// Builds a volume from tightly packed texels, x varying fastest, then y, then z.
+    ///
+    /// Panics if `data` is not exactly `width * height * depth` pixels of `pixels`.
+    pub fn from_raw(
+        width: u32,
+        height: u32,
+        depth: u32,
+        pixels: PixelFormat,
+        data: &[u8],
+        options: &TextureOptions,
+    ) -> Texture3D {
+        let expected = width as usize * height as usize * depth as usize * pixels.bytes_per_pixel();
+        if data.len() != expected {
+            panic!(
+                "A {}x{}x{} {:?} volume needs {} bytes, got {}",
+                width,
+                height,
+                depth,
+                pixels,
+                expected,
+                data.len()
+            );
+        }
+
+        let format = options.internal_format.unwrap_or_else(|| pixels.into());
+        let texture = TextureObject::new(gl::TEXTURE_3D, options);
+        unpack_tightly();
+        unsafe {
+            gl::TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                format.gl_enum() as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                depth as GLsizei,
+                0,
+                pixels.gl_format(),
+                pixels.gl_type(),
+                data.as_ptr() as *const GLvoid,
+            );
+        }
+        texture.finish(options);
+        Texture3D {
+            texture,
+            width,
+            height,
+            depth,
+            format,
+        }
+    }
+
+    /// Loads one image resource per slice.
+    pub fn from_res_slices(
+        res: &Resources,
+        names: &[&str],
+        options: &TextureOptions,
+    ) -> Result<Texture3D, Error> {
+        Ok(Texture3D::from_images(&load_layers(res, names)?, options))
+    }
+
+    /// Loads a volume laid out as `columns` x `rows` slices in one image, in reading order
+    /// from the top left. A 256x16 color grading strip is 16 columns and 1 row.
+    pub fn from_res_grid(
+        res: &Resources,
+        name: &str,
+        columns: u32,
+        rows: u32,
+        options: &TextureOptions,
+    ) -> Result<Texture3D, Error> {
+        Ok(Texture3D::from_images(
+            &load_grid(res, name, columns, rows)?,
+            options,
+        ))
+    }
+
+    /// Loads a headerless resource of texels laid out as in `from_raw`.
+    pub fn from_res_raw(
+        res: &Resources,
+        name: &str,
+        width: u32,
+        height: u32,
+        depth: u32,
+        pixels: PixelFormat,
+        options: &TextureOptions,
+    ) -> Result<Texture3D, Error> {
+        let data = image::load(res, name)?;
+        let expected = width as usize * height as usize * depth as usize * pixels.bytes_per_pixel();
+        if data.len() != expected {
+            return Err(Error::ImageDecode {
+                name: String::from(name),
+                message: format!(
+                    "expected {} bytes for a {}x{}x{} {:?} volume, found {}",
+                    expected,
+                    width,
+                    height,
+                    depth,
+                    pixels,
+                    data.len()
+                ),
+            });
+        }
+        Ok(Texture3D::from_raw(
+            width, height, depth, pixels, &data, options,
+        ))
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.texture.id()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn format(&self) -> InternalFormat {
+        self.format
+    }
+
+    /// Number of levels a full mip chain for this volume has.
+    pub fn mip_levels(&self) -> u32 {
+        mip_levels(self.width.max(self.height).max(self.depth))
+    }
+
+    /// Binds the volume to the active texture unit.
+    pub fn bind(&self) {
+        self.texture.bind();
+    }
+
+    /// Binds the volume to texture unit `unit`, leaving that unit active.
+    pub fn bind_to_unit(&self, unit: u32) {
+        self.texture.bind_to_unit(unit);
+    }
+
+    pub fn unbind(&self) {
+        self.texture.unbind();
+    }
+
+    /// Changes the filtering of the bound volume.
+    pub fn set_filter(&self, min: Filter, mag: Filter, mipmap: Option<Filter>) {
+        self.texture.set_filter(min, mag, mipmap);
+    }
+
+    /// Changes the wrap modes of the bound volume.
+    pub fn set_wrap(&self, s: Wrap, t: Wrap, r: Wrap) {
+        self.texture.set_wrap(s, t, r);
+    }
+
+    /// Regenerates every mip level of the bound volume from level 0.
+    pub fn generate_mipmaps(&self) {
+        self.texture.generate_mipmaps();
+    }
+
+    /// Replaces level 0 of `slice` of the bound volume with `image`.
+    ///
+    /// Mipmaps are not updated; call `generate_mipmaps` afterwards if the volume has them.
+    pub fn sub_image(&self, slice: u32, image: &Image) {
+        check_sub_image(self.width, self.height, self.depth, slice, image);
+        upload_layer(gl::TEXTURE_3D, slice, image);
+    }
+}
+
+/// Returns the shared size and format of `images`, panicking if there are none or they differ.
+fn check_layers(images: &[Image]) -> (u32, u32, PixelFormat) {
+    let first = images
+        .first()
+        .expect("A layered texture needs at least one image");
+    let (width, height, format) = (first.width(), first.height(), first.format());
+    for (index, image) in images.iter().enumerate() {
+        if image.width() != width || image.height() != height || image.format() != format {
+            panic!(
+                "Layer {} is a {}x{} {:?} image, but layer 0 is {}x{} {:?}",
+                index,
+                image.width(),
+                image.height(),
+                image.format(),
+                width,
+                height,
+                format
+            );
+        }
+    }
+    (width, height, format)
+}
+
+fn check_sub_image(width: u32, height: u32, layers: u32, layer: u32, image: &Image) {
+    if layer >= layers || image.width() != width || image.height() != height {
+        panic!(
+            "Cannot replace layer {} of a {}x{}x{} texture with a {}x{} image",
+            layer,
+            width,
+            height,
+            layers,
+            image.width(),
+            image.height()
+        );
+    }
+}
+
+fn load_layers(res: &Resources, names: &[&str]) -> Result<Vec<Image>, Error> {
+    let mut images: Vec<Image> = Vec::with_capacity(names.len());
+    for &name in names {
+        let image = Image::from_res(res, name)?;
+        if let Some(first) = images.first() {
+            if image.width() != first.width()
+                || image.height() != first.height()
+                || image.format() != first.format()
+            {
+                return Err(Error::TextureSize {
+                    name: String::from(name),
+                    message: format!(
+                        "is a {}x{} {:?} image, but {} is {}x{} {:?}",
+                        image.width(),
+                        image.height(),
+                        image.format(),
+                        names[0],
+                        first.width(),
+                        first.height(),
+                        first.format()
+                    ),
+                });
+            }
+        }
+        images.push(image);
+    }
+    Ok(images)
+}
+
+fn load_grid(res: &Resources, name: &str, columns: u32, rows: u32) -> Result<Vec<Image>, Error> {
+    let image = Image::from_res(res, name)?;
+    image
+        .split_grid(columns, rows)
+        .ok_or_else(|| Error::TextureSize {
+            name: String::from(name),
+            message: format!(
+                "is {}x{}, which does not divide into {}x{} cells",
+                image.width(),
+                image.height(),
+                columns,
+                rows
+            ),
+        })
+}
+
+/// Allocates level 0 of the bound layered texture without uploading anything.
+fn allocate(texture: &TextureObject, format: InternalFormat, width: u32, height: u32, layers: u32) {
+    let (pixels, kind) = format.allocation_enums();
+    unsafe {
+        gl::TexImage3D(
+            texture.target(),
+            0,
+            format.gl_enum() as GLint,
+            width as GLsizei,
+            height as GLsizei,
+            layers as GLsizei,
+            0,
+            pixels,
+            kind,
+            std::ptr::null(),
+        );
+    }
+}
+
+fn upload_layer(target: GLenum, layer: u32, image: &Image) {
+    unpack_tightly();
+    unsafe {
+        gl::TexSubImage3D(
+            target,
+            0,
+            0,
+            0,
+            layer as GLint,
+            image.width() as GLsizei,
+            image.height() as GLsizei,
+            1,
+            image.format().gl_format(),
+            image.format().gl_type(),
+            image.data().as_ptr() as *const GLvoid,
+        );
+    }
+}
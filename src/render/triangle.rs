@@ -1,45 +1,46 @@
 use super::*;
 use crate::resources::Resources;
+use crate::utils::Mat4;
 
 pub struct Triangle {
     program: Program,
-    _vbo: ArrayBuffer,
-    vao: VertexArray,
+    mesh: Mesh<Vertex>,
 }
 
 impl Triangle {
     pub fn new(res: &Resources) -> Result<Triangle, Error> {
-        let program = Program::from_res(res, "shaders/triangle")?;
-
         let vertices: Vec<Vertex> = vec![
             Vertex::new((-0.5, -0.5, 0.0).into(), (1.0, 0.0, 0.0).into()),
             Vertex::new((0.5, -0.5, 0.0).into(), (0.0, 1.0, 0.0).into()),
             Vertex::new((0.0, 0.5, 0.0).into(), (0.0, 0.0, 1.0).into()),
         ];
 
-        let vbo = ArrayBuffer::new();
-        vbo.bind();
-        vbo.buffer_static_data(&vertices);
-
-        let vao = VertexArray::new();
-        vao.bind();
-        Vertex::vertex_attrib_pointers();
-        vbo.unbind();
-        vao.unbind();
-
-        Ok(Triangle {
-            program,
-            _vbo: vbo,
-            vao,
-        })
+        let mesh = Mesh::new(&vertices, Topology::Triangles);
+        let program = Program::from_res(res, "shaders/triangle")?;
+        mesh.check_program(&program)?;
+
+        Ok(Triangle { program, mesh })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn mesh(&self) -> &Mesh<Vertex> {
+        &self.mesh
+    }
+
+    /// Reloads the shaders, keeping the current program if they fail to build or no longer
+    /// match `Vertex`.
+    pub fn reload(&mut self, res: &Resources) -> Result<(), Error> {
+        self.program.reload_for::<Vertex>(res)
     }
 
-    pub fn render(&self) {
+    pub fn render(&self, view_projection: &Mat4) -> Result<(), Error> {
         self.program.bind();
-        self.vao.bind();
+        self.program.set_mat4("ViewProjection", view_projection)?;
+        self.mesh.draw();
 
-        unsafe {
-            gl::DrawArrays(gl::TRIANGLES, 0, 3);
-        }
+        Ok(())
     }
 }
@@ -1,4 +1,14 @@
-use crate::utils::Vec3;
+use crate::utils::{Vec2, Vec3, Vec4};
+
+/// A vertex type that can describe its attributes to GL, implemented by
+/// `#[derive(VertexAttribPointers)]`.
+pub trait VertexLayout: Copy {
+    /// Sets up the attribute pointers for the bound VAO and array buffer.
+    fn vertex_attrib_pointers();
+
+    /// `(location, component count, component type)` of every attribute.
+    fn vertex_attribs() -> Vec<(usize, i32, u32)>;
+}
 
 #[derive(Debug, Copy, Clone, VertexAttribPointers)]
 #[repr(C)]
@@ -7,10 +17,44 @@ pub struct Vertex {
     pub pos: Vec3,
     #[location = 1]
     pub clr: Vec3,
+    /// Texture coordinate, ignored by shaders that don't read location 2.
+    #[location = 2]
+    pub uv: Vec2,
 }
 
 impl Vertex {
     pub fn new(pos: Vec3, clr: Vec3) -> Vertex {
-        Vertex { pos, clr }
+        Vertex::with_uv(pos, clr, Vec2::zero())
+    }
+
+    pub fn with_uv(pos: Vec3, clr: Vec3, uv: Vec2) -> Vertex {
+        Vertex { pos, clr, uv }
+    }
+}
+
+/// Vertex with everything needed for lit, textured and normal-mapped meshes.
+///
+/// `tangent.w` is the handedness, so the bitangent is `cross(normal, tangent.xyz) * tangent.w`.
+#[derive(Debug, Copy, Clone, PartialEq, VertexAttribPointers)]
+#[repr(C)]
+pub struct MeshVertex {
+    #[location = 0]
+    pub pos: Vec3,
+    #[location = 1]
+    pub normal: Vec3,
+    #[location = 2]
+    pub uv: Vec2,
+    #[location = 3]
+    pub tangent: Vec4,
+}
+
+impl MeshVertex {
+    pub fn new(pos: Vec3, normal: Vec3, uv: Vec2, tangent: Vec4) -> MeshVertex {
+        MeshVertex {
+            pos,
+            normal,
+            uv,
+            tangent,
+        }
     }
 }
@@ -1,20 +1,38 @@
+use super::framebuffer;
+use super::image::Image;
+use super::render_state::StateCache;
+use super::{BufferMask, ClearValues, RenderState};
 use glfw::Context;
+use std::cell::RefCell;
 use std::sync::mpsc::Receiver;
 
 pub struct Window {
     inner: glfw::Window,
     events: Receiver<(f64, glfw::WindowEvent)>,
     glfw: glfw::Glfw,
+    state: RefCell<StateCache>,
 }
 
 impl Window {
     pub fn new(width: u32, height: u32) -> Window {
-        let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+        let glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+        Window::create(glfw, width, height, true).expect("Failed to create GLFW window.")
+    }
+
+    /// Creates a window that is never shown, for rendering offscreen, e.g. on CI.
+    ///
+    /// Returns `None` if no GL context can be created, such as on a machine without a
+    /// display. A GPU-less Linux machine can provide one with `xvfb-run` and Mesa's llvmpipe.
+    pub fn hidden(width: u32, height: u32) -> Option<Window> {
+        let glfw = glfw::init::<()>(None).ok()?;
+        Window::create(glfw, width, height, false)
+    }
 
+    fn create(mut glfw: glfw::Glfw, width: u32, height: u32, visible: bool) -> Option<Window> {
         glfw.window_hint(glfw::WindowHint::Resizable(false));
-        let (mut inner, events) = glfw
-            .create_window(width, height, "Window!", glfw::WindowMode::Windowed)
-            .expect("Failed to create GLFW window.");
+        glfw.window_hint(glfw::WindowHint::Visible(visible));
+        let (mut inner, events) =
+            glfw.create_window(width, height, "Window!", glfw::WindowMode::Windowed)?;
 
         inner.set_resizable(true);
         inner.make_current();
@@ -25,13 +43,17 @@ impl Window {
         gl::load_with(|s| inner.get_proc_address(s));
         unsafe {
             gl::Viewport(0, 0, width as i32, height as i32);
+            // Set once for the context rather than by `RenderState`: filtering cube maps across
+            // face edges is what every user of them wants.
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
         }
 
-        Window {
+        Some(Window {
             inner,
             events,
             glfw,
-        }
+            state: RefCell::new(StateCache::default()),
+        })
     }
 
     pub fn flush_messages<'a>(&'a mut self) -> impl Iterator<Item = glfw::WindowEvent> + 'a {
@@ -43,19 +65,64 @@ impl Window {
         self.inner.should_close()
     }
 
-    pub fn swap_buffers(&mut self) {
-        self.inner.swap_buffers();
+    /// Size of the default framebuffer in pixels, which differs from the window size on
+    /// high-DPI displays.
+    pub fn framebuffer_size(&self) -> (u32, u32) {
+        let (width, height) = self.inner.get_framebuffer_size();
+        (width as u32, height as u32)
     }
 
-    pub fn clear(&self) {
+    /// Binds the default framebuffer and resets the viewport to cover it, e.g. after
+    /// rendering offscreen with `Framebuffer::bind_with_viewport`.
+    pub fn bind_framebuffer(&self) {
+        let (width, height) = self.framebuffer_size();
         unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width as i32, height as i32);
         }
     }
 
-    pub fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+    /// Reads the back buffer, which holds what has been drawn since the last `swap_buffers`,
+    /// as RGBA8 pixels, bottom row first.
+    ///
+    /// Hidden windows may not keep their pixels on every platform; rendering into a
+    /// `Framebuffer` and reading that back is the reliable way to capture offscreen.
+    pub fn read_pixels(&self) -> Image {
+        let (width, height) = self.framebuffer_size();
         unsafe {
-            gl::ClearColor(r, g, b, a);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+            gl::ReadBuffer(gl::BACK);
         }
+        framebuffer::read_pixels(width, height)
+    }
+
+    pub fn swap_buffers(&mut self) {
+        self.inner.swap_buffers();
+    }
+
+    /// Makes `state` current for the following draw calls, skipping GL calls for settings
+    /// that are already in effect.
+    pub fn apply_state(&self, state: &RenderState) {
+        self.state.borrow_mut().apply(state);
+    }
+
+    /// Makes the next `apply_state` set everything again, e.g. after GL state was changed
+    /// without going through it.
+    pub fn invalidate_state(&self) {
+        self.state.borrow_mut().invalidate();
+    }
+
+    /// Clears `buffers` of the bound framebuffer to `values`.
+    ///
+    /// The write masks of the cleared buffers are enabled for this, but an enabled scissor
+    /// box still limits it.
+    pub fn clear(&self, buffers: BufferMask, values: &ClearValues) {
+        self.state.borrow_mut().clear(buffers, values);
+    }
+
+    /// Enables the write masks of `buffers` through the state cache, for clears that don't
+    /// go through `clear`.
+    pub(super) fn enable_writes(&self, buffers: BufferMask) {
+        self.state.borrow_mut().enable_writes(buffers);
     }
 }
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
 use std::ffi;
+use std::fmt;
 use std::fs;
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 #[derive(Debug)]
 pub enum Error {
@@ -16,11 +19,36 @@ impl From<io::Error> for Error {
     }
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::FileContainsNil => write!(f, "File contains a nil byte"),
+            Error::FailedToGetExePath => write!(f, "Failed to get the executable path"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 pub struct Resources {
     root_path: PathBuf,
 }
 
 impl Resources {
+    pub fn from_path(root_path: &Path) -> Resources {
+        Resources {
+            root_path: root_path.into(),
+        }
+    }
+
     pub fn from_relative_exe_path(rel_path: &Path) -> Result<Resources, Error> {
         let exe_file_name = ::std::env::current_exe().map_err(|_| Error::FailedToGetExePath)?;
 
@@ -43,6 +71,79 @@ impl Resources {
 
         Ok(unsafe {ffi::CString::from_vec_unchecked(buffer)})
     }
+
+    pub fn load_string(&self, resource_name: &str) -> Result<String, Error> {
+        let text = fs::read_to_string(resource_name_to_path(&self.root_path, resource_name))?;
+
+        if text.contains('\0') {
+            return Err(Error::FileContainsNil);
+        }
+
+        Ok(text)
+    }
+
+    pub fn load_bytes(&self, resource_name: &str) -> Result<Vec<u8>, Error> {
+        Ok(fs::read(resource_name_to_path(&self.root_path, resource_name))?)
+    }
+
+    pub fn exists(&self, resource_name: &str) -> bool {
+        resource_name_to_path(&self.root_path, resource_name).is_file()
+    }
+
+    pub fn modified(&self, resource_name: &str) -> Result<SystemTime, Error> {
+        let metadata = fs::metadata(resource_name_to_path(&self.root_path, resource_name))?;
+        Ok(metadata.modified()?)
+    }
+
+    pub fn watcher(&self) -> ResourceWatcher {
+        ResourceWatcher {
+            res: Resources::from_path(&self.root_path),
+            watched: HashMap::new(),
+        }
+    }
+}
+
+/// Polls the modification time of a set of resources.
+pub struct ResourceWatcher {
+    res: Resources,
+    watched: HashMap<String, Option<SystemTime>>,
+}
+
+impl ResourceWatcher {
+    pub fn watch(&mut self, resource_name: &str) {
+        let modified = self.res.modified(resource_name).ok();
+        self.watched.insert(String::from(resource_name), modified);
+    }
+
+    pub fn unwatch(&mut self, resource_name: &str) {
+        self.watched.remove(resource_name);
+    }
+
+    /// Returns the resources whose modification time changed since the last poll.
+    pub fn poll(&mut self) -> Vec<String> {
+        let res = &self.res;
+        self.watched
+            .iter_mut()
+            .filter_map(|(name, last)| {
+                let modified = res.modified(name).ok();
+                if modified != *last {
+                    *last = modified;
+                    Some(name.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Resolves `file`, as written inside resource `name`, relative to the directory of `name`.
+pub fn sibling_name(name: &str, file: &str) -> String {
+    let file = file.replace('\\', "/");
+    match name.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, file),
+        None => file,
+    }
 }
 
 fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
@@ -54,3 +155,54 @@ fn resource_name_to_path(root_dir: &Path, location: &str) -> PathBuf {
 
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn watcher_reports_modified_files() {
+        let root = std::env::temp_dir().join(format!("resources_watcher_{}", std::process::id()));
+        fs::create_dir_all(root.join("shaders")).unwrap();
+        let path = root.join("shaders").join("watched.vert");
+        fs::write(&path, "void main() {}").unwrap();
+
+        let res = Resources::from_path(&root);
+        let mut watcher = res.watcher();
+        watcher.watch("shaders/watched.vert");
+        assert!(watcher.poll().is_empty());
+
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(vec![String::from("shaders/watched.vert")], watcher.poll());
+        assert!(watcher.poll().is_empty());
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(vec![String::from("shaders/watched.vert")], watcher.poll());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn sibling_names_share_the_directory() {
+        assert_eq!("models/crate.mtl", sibling_name("models/crate.obj", "crate.mtl"));
+        assert_eq!("models/tex/a.png", sibling_name("models/crate.obj", "tex\\a.png"));
+        assert_eq!("crate.mtl", sibling_name("crate.obj", "crate.mtl"));
+    }
+
+    #[test]
+    fn exists_only_for_files() {
+        let root = std::env::temp_dir().join(format!("resources_exists_{}", std::process::id()));
+        fs::create_dir_all(root.join("shaders")).unwrap();
+        fs::write(root.join("shaders").join("basic.vert"), "").unwrap();
+
+        let res = Resources::from_path(&root);
+        assert!(res.exists("shaders/basic.vert"));
+        assert!(!res.exists("shaders/basic.geom"));
+        assert!(!res.exists("shaders"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,155 +1,316 @@
-use std::ops::{Add, Mul};
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 #[repr(C)]
 pub struct Vec2 {
-    x: f32,
-    y: f32,
+    pub x: f32,
+    pub y: f32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 #[repr(C)]
 pub struct Vec3 {
-    x: f32,
-    y: f32,
-    z: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 #[repr(C)]
 pub struct Vec4 {
-    x: f32,
-    y: f32,
-    z: f32,
-    w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
 }
 
+macro_rules! impl_vec_ops {
+    ($name:ident { $($field:ident),+ }) => {
+        impl $name {
+            pub fn zero() -> $name {
+                $name { $($field: 0.0),+ }
+            }
+
+            pub fn splat(v: f32) -> $name {
+                $name { $($field: v),+ }
+            }
+
+            pub fn dot(self, other: Self) -> f32 {
+                0.0 $(+ self.$field * other.$field)+
+            }
+
+            pub fn length_squared(self) -> f32 {
+                self.dot(self)
+            }
+
+            pub fn length(self) -> f32 {
+                self.length_squared().sqrt()
+            }
+
+            pub fn distance(self, other: Self) -> f32 {
+                (other - self).length()
+            }
+
+            /// Returns the vector scaled to unit length, or the zero vector unchanged.
+            pub fn normalize(self) -> Self {
+                let len = self.length();
+                if len == 0.0 {
+                    self
+                } else {
+                    self / len
+                }
+            }
+
+            pub fn lerp(self, other: Self, t: f32) -> Self {
+                self + (other - self) * t
+            }
+
+            pub fn min(self, other: Self) -> Self {
+                $name { $($field: self.$field.min(other.$field)),+ }
+            }
+
+            pub fn max(self, other: Self) -> Self {
+                $name { $($field: self.$field.max(other.$field)),+ }
+            }
+
+            pub fn abs(self) -> Self {
+                $name { $($field: self.$field.abs()),+ }
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self::Output {
+                $name { $($field: self.$field + other.$field),+ }
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self::Output {
+                $name { $($field: self.$field - other.$field),+ }
+            }
+        }
+
+        impl Neg for $name {
+            type Output = Self;
+
+            fn neg(self) -> Self::Output {
+                $name { $($field: -self.$field),+ }
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: f32) -> Self::Output {
+                $name { $($field: self.$field * rhs),+ }
+            }
+        }
+
+        impl Mul<$name> for f32 {
+            type Output = $name;
+
+            fn mul(self, rhs: Self::Output) -> Self::Output {
+                rhs * self
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                $name { $($field: self.$field * rhs.$field),+ }
+            }
+        }
+
+        impl Div<f32> for $name {
+            type Output = Self;
+
+            fn div(self, rhs: f32) -> Self::Output {
+                $name { $($field: self.$field / rhs),+ }
+            }
+        }
+
+        impl Div for $name {
+            type Output = Self;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                $name { $($field: self.$field / rhs.$field),+ }
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign<f32> for $name {
+            fn mul_assign(&mut self, rhs: f32) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl DivAssign<f32> for $name {
+            fn div_assign(&mut self, rhs: f32) {
+                *self = *self / rhs;
+            }
+        }
+
+        impl Index<usize> for $name {
+            type Output = f32;
+
+            fn index(&self, index: usize) -> &f32 {
+                [$(&self.$field),+][index]
+            }
+        }
+
+        impl IndexMut<usize> for $name {
+            fn index_mut(&mut self, index: usize) -> &mut f32 {
+                IntoIterator::into_iter([$(&mut self.$field),+])
+                    .nth(index)
+                    .unwrap_or_else(|| panic!("index out of bounds: {}", index))
+            }
+        }
+    };
+}
+
+impl_vec_ops!(Vec2 { x, y });
+impl_vec_ops!(Vec3 { x, y, z });
+impl_vec_ops!(Vec4 { x, y, z, w });
+
 impl Vec2 {
+    pub const COMPONENTS: i32 = 2;
+    pub const COMPONENT_TYPE: gl::types::GLenum = gl::FLOAT;
+
     pub fn new(x: f32, y: f32) -> Vec2 {
         Vec2 { x, y }
     }
 
+    /// Sets up the vertex attribute at `location` as two floats.
+    ///
+    /// # Safety
+    ///
+    /// A vertex array and an array buffer must be bound on the current GL context.
     pub unsafe fn vertex_attrib_pointer(stride: usize, location: usize, offset: usize) {
         gl::EnableVertexAttribArray(location as gl::types::GLuint);
         gl::VertexAttribPointer(
             location as gl::types::GLuint,
-            2,
-            gl::FLOAT,
+            Self::COMPONENTS,
+            Self::COMPONENT_TYPE,
             gl::FALSE,
             stride as gl::types::GLint,
             offset as *const gl::types::GLvoid,
         );
     }
-}
 
-impl From<(f32, f32)> for Vec2 {
-    fn from((x, y): (f32, f32)) -> Self {
-        Self { x, y }
+    /// The z component of the 3D cross product of the two vectors.
+    pub fn cross(self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
     }
-}
-
-impl Add for Vec2 {
-    type Output = Self;
 
-    fn add(self, other: Self) -> Self::Output {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-        }
+    pub fn perp(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
     }
-}
-
-impl Mul<f32> for Vec2 {
-    type Output = Self;
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        Self {
-            x: self.x * rhs,
-            y: self.y * rhs,
-        }
+    pub fn extend(self, z: f32) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
     }
 }
 
-impl Mul<Vec2> for f32 {
-    type Output = Vec2;
-
-    fn mul(self, rhs: Self::Output) -> Self::Output {
-        rhs * self
+impl From<(f32, f32)> for Vec2 {
+    fn from((x, y): (f32, f32)) -> Self {
+        Self { x, y }
     }
 }
 
 impl Vec3 {
+    pub const COMPONENTS: i32 = 3;
+    pub const COMPONENT_TYPE: gl::types::GLenum = gl::FLOAT;
+
     pub fn new(x: f32, y: f32, z: f32) -> Vec3 {
         Vec3 { x, y, z }
     }
 
+    /// Sets up the vertex attribute at `location` as three floats.
+    ///
+    /// # Safety
+    ///
+    /// A vertex array and an array buffer must be bound on the current GL context.
     pub unsafe fn vertex_attrib_pointer(stride: usize, location: usize, offset: usize) {
         gl::EnableVertexAttribArray(location as gl::types::GLuint);
         gl::VertexAttribPointer(
             location as gl::types::GLuint,
-            3,
-            gl::FLOAT,
+            Self::COMPONENTS,
+            Self::COMPONENT_TYPE,
             gl::FALSE,
             stride as gl::types::GLint,
             offset as *const gl::types::GLvoid,
         );
     }
-}
-
-impl From<(f32, f32, f32)> for Vec3 {
-    fn from((x, y, z): (f32, f32, f32)) -> Self {
-        Self { x, y, z }
-    }
-}
 
-impl Add for Vec3 {
-    type Output = Self;
-
-    fn add(self, other: Self) -> Self::Output {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
         }
     }
-}
 
-impl Mul<f32> for Vec3 {
-    type Output = Self;
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, w)
+    }
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        Self {
-            x: self.x * rhs,
-            y: self.y * rhs,
-            z: self.z * rhs,
-        }
+    pub fn truncate(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
     }
 }
 
-impl Mul<Vec3> for f32 {
-    type Output = Vec3;
-
-    fn mul(self, rhs: Self::Output) -> Self::Output {
-        rhs * self
+impl From<(f32, f32, f32)> for Vec3 {
+    fn from((x, y, z): (f32, f32, f32)) -> Self {
+        Self { x, y, z }
     }
 }
 
 impl Vec4 {
+    pub const COMPONENTS: i32 = 4;
+    pub const COMPONENT_TYPE: gl::types::GLenum = gl::FLOAT;
+
     pub fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
         Vec4 { x, y, z, w }
     }
 
+    /// Sets up the vertex attribute at `location` as four floats.
+    ///
+    /// # Safety
+    ///
+    /// A vertex array and an array buffer must be bound on the current GL context.
     pub unsafe fn vertex_attrib_pointer(stride: usize, location: usize, offset: usize) {
         gl::EnableVertexAttribArray(location as gl::types::GLuint);
         gl::VertexAttribPointer(
             location as gl::types::GLuint,
-            4,
-            gl::FLOAT,
+            Self::COMPONENTS,
+            Self::COMPONENT_TYPE,
             gl::FALSE,
             stride as gl::types::GLint,
             offset as *const gl::types::GLvoid,
         );
     }
+
+    pub fn truncate(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
 }
 
 impl From<(f32, f32, f32, f32)> for Vec4 {
@@ -158,37 +319,536 @@ impl From<(f32, f32, f32, f32)> for Vec4 {
     }
 }
 
-impl Add for Vec4 {
+/// Column-major 2x2 matrix, laid out the way GL expects it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Mat2 {
+    pub cols: [Vec2; 2],
+}
+
+/// Column-major 3x3 matrix, laid out the way GL expects it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Mat3 {
+    pub cols: [Vec3; 3],
+}
+
+/// Column-major 4x4 matrix, laid out the way GL expects it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Mat4 {
+    pub cols: [Vec4; 4],
+}
+
+macro_rules! impl_mat_ops {
+    ($name:ident, $vec:ident, $n:expr) => {
+        impl $name {
+            pub fn zero() -> $name {
+                $name {
+                    cols: [$vec::zero(); $n],
+                }
+            }
+
+            pub fn identity() -> $name {
+                let mut m = $name::zero();
+                for i in 0..$n {
+                    m.cols[i][i] = 1.0;
+                }
+                m
+            }
+
+            pub fn col(&self, index: usize) -> $vec {
+                self.cols[index]
+            }
+
+            pub fn row(&self, index: usize) -> $vec {
+                let mut row = $vec::zero();
+                for c in 0..$n {
+                    row[c] = self.cols[c][index];
+                }
+                row
+            }
+
+            pub fn transpose(&self) -> $name {
+                let mut m = $name::zero();
+                for c in 0..$n {
+                    m.cols[c] = self.row(c);
+                }
+                m
+            }
+
+            pub fn as_ptr(&self) -> *const f32 {
+                self.cols.as_ptr() as *const f32
+            }
+        }
+
+        impl Mul for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                let mut m = $name::zero();
+                for c in 0..$n {
+                    m.cols[c] = self * rhs.cols[c];
+                }
+                m
+            }
+        }
+
+        impl Mul<$vec> for $name {
+            type Output = $vec;
+
+            fn mul(self, rhs: $vec) -> Self::Output {
+                let mut v = $vec::zero();
+                for c in 0..$n {
+                    v += self.cols[c] * rhs[c];
+                }
+                v
+            }
+        }
+
+        impl Mul<f32> for $name {
+            type Output = Self;
+
+            fn mul(self, rhs: f32) -> Self::Output {
+                let mut m = self;
+                for c in 0..$n {
+                    m.cols[c] *= rhs;
+                }
+                m
+            }
+        }
+
+        impl Mul<$name> for f32 {
+            type Output = $name;
+
+            fn mul(self, rhs: Self::Output) -> Self::Output {
+                rhs * self
+            }
+        }
+
+        impl Add for $name {
+            type Output = Self;
+
+            fn add(self, other: Self) -> Self::Output {
+                let mut m = self;
+                for c in 0..$n {
+                    m.cols[c] += other.cols[c];
+                }
+                m
+            }
+        }
+
+        impl Sub for $name {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self::Output {
+                let mut m = self;
+                for c in 0..$n {
+                    m.cols[c] -= other.cols[c];
+                }
+                m
+            }
+        }
+
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, rhs: Self) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl Index<usize> for $name {
+            type Output = $vec;
+
+            fn index(&self, index: usize) -> &$vec {
+                &self.cols[index]
+            }
+        }
+
+        impl IndexMut<usize> for $name {
+            fn index_mut(&mut self, index: usize) -> &mut $vec {
+                &mut self.cols[index]
+            }
+        }
+    };
+}
+
+impl_mat_ops!(Mat2, Vec2, 2);
+impl_mat_ops!(Mat3, Vec3, 3);
+impl_mat_ops!(Mat4, Vec4, 4);
+
+impl Mat2 {
+    pub fn from_cols(c0: Vec2, c1: Vec2) -> Mat2 {
+        Mat2 { cols: [c0, c1] }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.cols[0].cross(self.cols[1])
+    }
+
+    pub fn inverse(&self) -> Option<Mat2> {
+        let det = self.determinant();
+        if det == 0.0 {
+            return None;
+        }
+
+        let [a, b] = self.cols;
+        Some(Mat2::from_cols(Vec2::new(b.y, -a.y), Vec2::new(-b.x, a.x)) * (1.0 / det))
+    }
+}
+
+impl Mat3 {
+    pub fn from_cols(c0: Vec3, c1: Vec3, c2: Vec3) -> Mat3 {
+        Mat3 { cols: [c0, c1, c2] }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.cols[0].dot(self.cols[1].cross(self.cols[2]))
+    }
+
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det == 0.0 {
+            return None;
+        }
+
+        let [a, b, c] = self.cols;
+        let rows = Mat3::from_cols(b.cross(c), c.cross(a), a.cross(b));
+        Some(rows.transpose() * (1.0 / det))
+    }
+}
+
+impl From<Mat4> for Mat3 {
+    fn from(m: Mat4) -> Self {
+        Mat3::from_cols(
+            m.cols[0].truncate(),
+            m.cols[1].truncate(),
+            m.cols[2].truncate(),
+        )
+    }
+}
+
+impl Mat4 {
+    pub fn from_cols(c0: Vec4, c1: Vec4, c2: Vec4, c3: Vec4) -> Mat4 {
+        Mat4 {
+            cols: [c0, c1, c2, c3],
+        }
+    }
+
+    fn minor(&self, col: usize, row: usize) -> Mat3 {
+        let mut m = Mat3::zero();
+        for (dc, c) in (0..4).filter(|&c| c != col).enumerate() {
+            for (dr, r) in (0..4).filter(|&r| r != row).enumerate() {
+                m.cols[dc][dr] = self.cols[c][r];
+            }
+        }
+        m
+    }
+
+    pub fn cofactor(&self, col: usize, row: usize) -> f32 {
+        let sign = if (col + row).is_multiple_of(2) {
+            1.0
+        } else {
+            -1.0
+        };
+        sign * self.minor(col, row).determinant()
+    }
+
+    pub fn determinant(&self) -> f32 {
+        (0..4).map(|c| self.cols[c][0] * self.cofactor(c, 0)).sum()
+    }
+
+    pub fn inverse(&self) -> Option<Mat4> {
+        let det = self.determinant();
+        if det == 0.0 {
+            return None;
+        }
+
+        let mut m = Mat4::zero();
+        for c in 0..4 {
+            for r in 0..4 {
+                m.cols[c][r] = self.cofactor(r, c) / det;
+            }
+        }
+        Some(m)
+    }
+
+    pub fn from_translation(t: Vec3) -> Mat4 {
+        let mut m = Mat4::identity();
+        m.cols[3] = t.extend(1.0);
+        m
+    }
+
+    pub fn from_scale(s: Vec3) -> Mat4 {
+        Mat4::from_cols(
+            Vec4::new(s.x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, s.y, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, s.z, 0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
+
+    /// Orthographic projection mapping the given box onto GL's [-1, 1] clip cube.
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        Mat4::from_cols(
+            Vec4::new(2.0 / (right - left), 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / (top - bottom), 0.0, 0.0),
+            Vec4::new(0.0, 0.0, -2.0 / (far - near), 0.0),
+            Vec4::new(
+                -(right + left) / (right - left),
+                -(top + bottom) / (top - bottom),
+                -(far + near) / (far - near),
+                1.0,
+            ),
+        )
+    }
+
+    /// Right-handed perspective projection with depth mapped to GL's [-1, 1] range.
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        Mat4::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, (far + near) / (near - far), -1.0),
+            Vec4::new(0.0, 0.0, 2.0 * far * near / (near - far), 0.0),
+        )
+    }
+
+    /// Right-handed perspective projection mapping `near` to depth 1 and `far` to depth 0.
+    ///
+    /// Only useful with a `RenderState` using `ClipDepth::ZeroToOne` and
+    /// `DepthState::reverse_z()`, and a depth clear of 0.
+    pub fn perspective_reverse_z(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        Mat4::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, near / (far - near), -1.0),
+            Vec4::new(0.0, 0.0, near * far / (far - near), 0.0),
+        )
+    }
+
+    /// Right-handed view matrix looking from `eye` towards `target`.
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        let f = (target - eye).normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(f);
+
+        Mat4::from_cols(
+            Vec4::new(s.x, u.x, -f.x, 0.0),
+            Vec4::new(s.y, u.y, -f.y, 0.0),
+            Vec4::new(s.z, u.z, -f.z, 0.0),
+            Vec4::new(-s.dot(eye), -u.dot(eye), f.dot(eye), 1.0),
+        )
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let v = *self * p.extend(1.0);
+        v.truncate() / v.w
+    }
+}
+
+impl From<Mat3> for Mat4 {
+    fn from(m: Mat3) -> Self {
+        Mat4::from_cols(
+            m.cols[0].extend(0.0),
+            m.cols[1].extend(0.0),
+            m.cols[2].extend(0.0),
+            Vec4::new(0.0, 0.0, 0.0, 1.0),
+        )
+    }
+}
+
+/// Rotation quaternion with the vector part in `x`, `y`, `z` and the scalar part in `w`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Quat {
+        Quat { x, y, z, w }
+    }
+
+    pub fn identity() -> Quat {
+        Quat::new(0.0, 0.0, 0.0, 1.0)
+    }
+
+    /// Rotation of `angle` radians counter-clockwise around `axis`.
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let (s, c) = (angle * 0.5).sin_cos();
+        let axis = axis.normalize() * s;
+        Quat::new(axis.x, axis.y, axis.z, c)
+    }
+
+    /// Rotation from Euler angles in radians, applied as roll (z), then pitch (x), then yaw (y).
+    pub fn from_euler(pitch: f32, yaw: f32, roll: f32) -> Quat {
+        Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), yaw)
+            * Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), pitch)
+            * Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), roll)
+    }
+
+    pub fn to_axis_angle(self) -> (Vec3, f32) {
+        let q = self.normalize();
+        let s = (1.0 - q.w * q.w).max(0.0).sqrt();
+        let axis = if s < 1e-6 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(q.x, q.y, q.z) / s
+        };
+        (axis, 2.0 * q.w.clamp(-1.0, 1.0).acos())
+    }
+
+    pub fn vector(self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn dot(self, other: Quat) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(self) -> Quat {
+        let len = self.length();
+        if len == 0.0 {
+            Quat::identity()
+        } else {
+            self * (1.0 / len)
+        }
+    }
+
+    pub fn conjugate(self) -> Quat {
+        Quat::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn inverse(self) -> Quat {
+        self.conjugate() * (1.0 / self.dot(self))
+    }
+
+    /// Normalized linear interpolation, cheaper than `slerp` but not constant velocity.
+    pub fn nlerp(self, other: Quat, t: f32) -> Quat {
+        let other = if self.dot(other) < 0.0 { -other } else { other };
+        (self * (1.0 - t) + other * t).normalize()
+    }
+
+    /// Spherical linear interpolation along the shortest arc.
+    pub fn slerp(self, other: Quat, t: f32) -> Quat {
+        let mut cos_theta = self.dot(other);
+        let mut other = other;
+        if cos_theta < 0.0 {
+            other = -other;
+            cos_theta = -cos_theta;
+        }
+
+        if cos_theta > 0.9995 {
+            return self.nlerp(other, t);
+        }
+
+        let theta = cos_theta.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        self * a + other * b
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let u = self.vector();
+        let t = 2.0 * u.cross(v);
+        v + self.w * t + u.cross(t)
+    }
+
+    pub fn to_mat3(self) -> Mat3 {
+        let Quat { x, y, z, w } = self;
+        let (xx, yy, zz) = (x * x, y * y, z * z);
+        let (xy, xz, yz) = (x * y, x * z, y * z);
+        let (wx, wy, wz) = (w * x, w * y, w * z);
+
+        Mat3::from_cols(
+            Vec3::new(1.0 - 2.0 * (yy + zz), 2.0 * (xy + wz), 2.0 * (xz - wy)),
+            Vec3::new(2.0 * (xy - wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz + wx)),
+            Vec3::new(2.0 * (xz + wy), 2.0 * (yz - wx), 1.0 - 2.0 * (xx + yy)),
+        )
+    }
+
+    pub fn to_mat4(self) -> Mat4 {
+        self.to_mat3().into()
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Quat::identity()
+    }
+}
+
+impl Add for Quat {
     type Output = Self;
 
     fn add(self, other: Self) -> Self::Output {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-            w: self.w + other.w,
-        }
+        Quat::new(
+            self.x + other.x,
+            self.y + other.y,
+            self.z + other.z,
+            self.w + other.w,
+        )
+    }
+}
+
+impl Neg for Quat {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Quat::new(-self.x, -self.y, -self.z, -self.w)
     }
 }
 
-impl Mul<f32> for Vec4 {
+impl Mul<f32> for Quat {
     type Output = Self;
 
     fn mul(self, rhs: f32) -> Self::Output {
-        Self {
-            x: self.x * rhs,
-            y: self.y * rhs,
-            z: self.z * rhs,
-            w: self.w * rhs,
-        }
+        Quat::new(self.x * rhs, self.y * rhs, self.z * rhs, self.w * rhs)
+    }
+}
+
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (a, b) = (self.vector(), rhs.vector());
+        let v = self.w * b + rhs.w * a + a.cross(b);
+        Quat::new(v.x, v.y, v.z, self.w * rhs.w - a.dot(b))
+    }
+}
+
+impl MulAssign for Quat {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
     }
 }
 
-impl Mul<Vec4> for f32 {
-    type Output = Vec4;
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Self::Output {
+        self.rotate(rhs)
+    }
+}
+
+impl From<Quat> for Mat3 {
+    fn from(q: Quat) -> Self {
+        q.to_mat3()
+    }
+}
 
-    fn mul(self, rhs: Self::Output) -> Self::Output {
-        rhs * self
+impl From<Quat> for Mat4 {
+    fn from(q: Quat) -> Self {
+        q.to_mat4()
     }
 }
 
@@ -303,4 +963,236 @@ mod tests {
         assert_eq!(v1, v2);
         assert_eq!(v1, v3);
     }
+
+    fn assert_mat4_approx_eq(a: Mat4, b: Mat4) {
+        for c in 0..4 {
+            for r in 0..4 {
+                assert!((a[c][r] - b[c][r]).abs() < 1e-5, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn vec_sub_neg_div() {
+        let v1 = Vec3::new(1.0, 2.0, 3.0);
+        let v2 = Vec3::new(1.0, 1.0, 1.0);
+
+        assert_eq!(Vec3::new(0.0, 1.0, 2.0), v1 - v2);
+        assert_eq!(Vec3::new(-1.0, -2.0, -3.0), -v1);
+        assert_eq!(Vec3::new(0.5, 1.0, 1.5), v1 / 2.0);
+        assert_eq!(Vec3::new(1.0, 4.0, 9.0), v1 * v1);
+        assert_eq!(v2, v1 / v1);
+    }
+
+    #[test]
+    fn vec_dot_cross() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        let z = Vec3::new(0.0, 0.0, 1.0);
+
+        assert_eq!(0.0, x.dot(y));
+        assert_eq!(14.0, Vec3::new(1.0, 2.0, 3.0).dot(Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(z, x.cross(y));
+        assert_eq!(-z, y.cross(x));
+        assert_eq!(1.0, Vec2::new(1.0, 0.0).cross(Vec2::new(0.0, 1.0)));
+    }
+
+    #[test]
+    fn vec_length_normalize_lerp() {
+        let v = Vec2::new(3.0, 4.0);
+
+        assert_eq!(5.0, v.length());
+        assert_eq!(Vec2::new(0.6, 0.8), v.normalize());
+        assert_eq!(Vec2::zero(), Vec2::zero().normalize());
+        assert_eq!(Vec2::new(1.5, 2.0), Vec2::zero().lerp(v, 0.5));
+        assert_eq!(4.0, Vec4::new(1.0, 1.0, 1.0, 1.0).length_squared());
+    }
+
+    #[test]
+    fn vec_index() {
+        let mut v = Vec4::new(1.0, 2.0, 3.0, 4.0);
+        v[2] = 5.0;
+
+        assert_eq!(1.0, v[0]);
+        assert_eq!(5.0, v.z);
+        assert_eq!(4.0, v[3]);
+    }
+
+    #[test]
+    fn mat_mul() {
+        let m = Mat2::from_cols(Vec2::new(1.0, 3.0), Vec2::new(2.0, 4.0));
+        let n = Mat2::from_cols(Vec2::new(5.0, 7.0), Vec2::new(6.0, 8.0));
+
+        assert_eq!(m, m * Mat2::identity());
+        assert_eq!(Vec2::new(5.0, 11.0), m * Vec2::new(1.0, 2.0));
+        assert_eq!(
+            Mat2::from_cols(Vec2::new(19.0, 43.0), Vec2::new(22.0, 50.0)),
+            m * n
+        );
+    }
+
+    #[test]
+    fn mat_transpose() {
+        let m = Mat3::from_cols(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(4.0, 5.0, 6.0),
+            Vec3::new(7.0, 8.0, 9.0),
+        );
+
+        assert_eq!(Vec3::new(1.0, 4.0, 7.0), m.transpose().col(0));
+        assert_eq!(m, m.transpose().transpose());
+    }
+
+    #[test]
+    fn mat_determinant() {
+        let m2 = Mat2::from_cols(Vec2::new(1.0, 3.0), Vec2::new(2.0, 4.0));
+        let m3 = Mat3::from_cols(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+            Vec3::new(3.0, 4.0, 1.0),
+        );
+        let singular = Mat3::from_cols(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(4.0, 5.0, 6.0),
+            Vec3::new(7.0, 8.0, 9.0),
+        );
+
+        assert_eq!(-2.0, m2.determinant());
+        assert_eq!(1.0, m3.determinant());
+        assert_eq!(0.0, singular.determinant());
+        assert_eq!(None, singular.inverse());
+        assert_eq!(16.0, (Mat4::identity() * 2.0).determinant());
+    }
+
+    #[test]
+    fn mat_inverse() {
+        let m2 = Mat2::from_cols(Vec2::new(2.0, 1.0), Vec2::new(5.0, 3.0));
+        assert_eq!(Mat2::identity(), m2 * m2.inverse().unwrap());
+
+        let m3 = Mat3::from_cols(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+            Vec3::new(3.0, 4.0, 1.0),
+        );
+        assert_eq!(Mat3::identity(), m3 * m3.inverse().unwrap());
+
+        let m4 = Mat4::from_cols(
+            Vec4::new(1.0, 0.0, 2.0, 0.0),
+            Vec4::new(0.0, 3.0, 0.0, 1.0),
+            Vec4::new(1.0, 0.0, 1.0, 0.0),
+            Vec4::new(5.0, -2.0, 4.0, 1.0),
+        );
+        assert_mat4_approx_eq(Mat4::identity(), m4 * m4.inverse().unwrap());
+        assert_mat4_approx_eq(Mat4::identity(), m4.inverse().unwrap() * m4);
+    }
+
+    fn assert_vec3_approx_eq(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn quat_rotate() {
+        let q = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), std::f32::consts::FRAC_PI_2);
+
+        assert_vec3_approx_eq(Vec3::new(0.0, 1.0, 0.0), q * Vec3::new(1.0, 0.0, 0.0));
+        assert_vec3_approx_eq(Vec3::new(-1.0, 0.0, 0.0), q * Vec3::new(0.0, 1.0, 0.0));
+        assert_vec3_approx_eq(
+            Vec3::new(1.0, 0.0, 0.0),
+            q.inverse() * (q * Vec3::new(1.0, 0.0, 0.0)),
+        );
+    }
+
+    #[test]
+    fn quat_mul_composes() {
+        let a = Quat::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), 0.3);
+        let b = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), 1.1);
+        let v = Vec3::new(1.0, 2.0, 3.0);
+
+        assert_vec3_approx_eq(a * (b * v), (a * b) * v);
+        assert_vec3_approx_eq(Quat::from_euler(1.1, 0.3, 0.0) * v, (a * b) * v);
+    }
+
+    #[test]
+    fn quat_to_mat() {
+        let q = Quat::from_euler(0.4, -1.2, 2.0);
+        let v = Vec3::new(-1.0, 0.5, 2.0);
+
+        assert_vec3_approx_eq(q * v, q.to_mat3() * v);
+        assert_vec3_approx_eq(q * v, (Mat4::from(q) * v.extend(1.0)).truncate());
+    }
+
+    #[test]
+    fn quat_axis_angle_roundtrip() {
+        let (axis, angle) = Quat::from_axis_angle(Vec3::new(0.0, 2.0, 0.0), 0.75).to_axis_angle();
+
+        assert_vec3_approx_eq(Vec3::new(0.0, 1.0, 0.0), axis);
+        assert!((angle - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn quat_slerp() {
+        let axis = Vec3::new(0.0, 0.0, 1.0);
+        let a = Quat::identity();
+        let b = Quat::from_axis_angle(axis, 2.0);
+        let half = Quat::from_axis_angle(axis, 1.0);
+        let v = Vec3::new(1.0, 0.0, 0.0);
+
+        assert_vec3_approx_eq(half * v, a.slerp(b, 0.5) * v);
+        assert_vec3_approx_eq(a * v, a.slerp(b, 0.0) * v);
+        assert_vec3_approx_eq(b * v, a.slerp(b, 1.0) * v);
+        assert_vec3_approx_eq(half * v, a.nlerp(b, 0.5) * v);
+    }
+
+    #[test]
+    fn mat4_orthographic() {
+        let m = Mat4::orthographic(-2.0, 2.0, -1.0, 1.0, 0.0, 10.0);
+
+        assert_vec3_approx_eq(
+            Vec3::new(-1.0, -1.0, -1.0),
+            m.transform_point(Vec3::new(-2.0, -1.0, 0.0)),
+        );
+        assert_vec3_approx_eq(
+            Vec3::new(1.0, 1.0, 1.0),
+            m.transform_point(Vec3::new(2.0, 1.0, -10.0)),
+        );
+    }
+
+    #[test]
+    fn mat4_perspective() {
+        let m = Mat4::perspective(std::f32::consts::FRAC_PI_2, 2.0, 1.0, 100.0);
+
+        assert_vec3_approx_eq(
+            Vec3::new(0.5, 1.0, -1.0),
+            m.transform_point(Vec3::new(1.0, 1.0, -1.0)),
+        );
+        assert!((m.transform_point(Vec3::new(0.0, 0.0, -100.0)).z - 1.0).abs() < 1e-5);
+
+        let r = Mat4::perspective_reverse_z(std::f32::consts::FRAC_PI_2, 2.0, 1.0, 100.0);
+        assert!((r.transform_point(Vec3::new(0.0, 0.0, -1.0)).z - 1.0).abs() < 1e-5);
+        assert!(r.transform_point(Vec3::new(0.0, 0.0, -100.0)).z.abs() < 1e-5);
+    }
+
+    #[test]
+    fn mat4_look_at() {
+        let eye = Vec3::new(0.0, 0.0, 5.0);
+        let m = Mat4::look_at(eye, Vec3::zero(), Vec3::new(0.0, 1.0, 0.0));
+
+        assert_vec3_approx_eq(Vec3::zero(), m.transform_point(eye));
+        assert_vec3_approx_eq(Vec3::new(0.0, 0.0, -5.0), m.transform_point(Vec3::zero()));
+        assert_vec3_approx_eq(
+            Vec3::new(1.0, 0.0, -5.0),
+            m.transform_point(Vec3::new(1.0, 0.0, 0.0)),
+        );
+    }
+
+    #[test]
+    fn mat4_translation_scale() {
+        let m =
+            Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)) * Mat4::from_scale(Vec3::splat(2.0));
+
+        assert_eq!(
+            Vec3::new(3.0, 4.0, 5.0),
+            m.transform_point(Vec3::splat(1.0))
+        );
+    }
 }